@@ -1,5 +1,7 @@
+use crate::annotations::point::Point;
 use serde::{Deserialize, Serialize};
 use std::fmt;
+use std::hash::Hash;
 
 /// A set of custom errors for more informative error handling.
 #[derive(Debug, PartialEq)]
@@ -31,6 +33,15 @@ impl fmt::Display for BoundingBoxError {
 
 impl std::error::Error for BoundingBoxError {}
 
+/// The type used to label what a box contains.
+///
+/// Models output categories as strings, which is the default everywhere. A project with a fixed
+/// set of classes can use its own enum instead, which makes comparisons cheap and typos into
+/// compile errors. Any type that is Clone, Eq, Hash and Display is a Category.
+pub trait Category: Clone + Eq + Hash + fmt::Display {}
+
+impl<C: Clone + Eq + Hash + fmt::Display> Category for C {}
+
 /// A struct representing a bounding box.
 ///
 /// A bounding box is a rectangle used to annotate objects in images for training deep object
@@ -41,23 +52,23 @@ impl std::error::Error for BoundingBoxError {}
 ///
 /// This project uses the standard convention of the left side of the image being x=0 and the top
 /// of the image being y=0.
-#[derive(Debug, Deserialize, PartialEq, Serialize)]
-pub struct BoundingBox {
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct BoundingBox<C: Category = String> {
     left: f32,
     top: f32,
     right: f32,
     bottom: f32,
-    category: String,
+    category: C,
 }
 
-impl BoundingBox {
+impl<C: Category> BoundingBox<C> {
     /// Checks if a box has valid parameters before constructing.
     pub fn new(
         left: f32,
         top: f32,
         right: f32,
         bottom: f32,
-        category: String,
+        category: C,
     ) -> Result<Self, BoundingBoxError> {
         if left > right {
             Err(BoundingBoxError::InvalidLeftRight { left, right })
@@ -73,9 +84,61 @@ impl BoundingBox {
             })
         }
     }
+
+    /// Creates the smallest box that encloses every box in the slice.
+    ///
+    /// The enclosing box takes the category of the first box. Returns None for an empty slice.
+    pub fn enclosing<T: BoundingBoxGeometry<Category = C>>(boxes: &[T]) -> Option<BoundingBox<C>> {
+        let first = boxes.first()?;
+        let mut enclosing_box = BoundingBox {
+            left: first.left(),
+            top: first.top(),
+            right: first.right(),
+            bottom: first.bottom(),
+            category: first.category().clone(),
+        };
+        for b in boxes.iter().skip(1) {
+            enclosing_box.left = enclosing_box.left.min(b.left());
+            enclosing_box.top = enclosing_box.top.min(b.top());
+            enclosing_box.right = enclosing_box.right.max(b.right());
+            enclosing_box.bottom = enclosing_box.bottom.max(b.bottom());
+        }
+        Some(enclosing_box)
+    }
+
+    /// Creates a box from (left, top, right, bottom) given as fractions of the image's size.
+    ///
+    /// The inverse of as_normalized, for reading formats and models that use fractional
+    /// coordinates.
+    pub fn from_normalized(
+        left: f32,
+        top: f32,
+        right: f32,
+        bottom: f32,
+        image_width: f32,
+        image_height: f32,
+        category: C,
+    ) -> Result<Self, BoundingBoxError> {
+        BoundingBox::new(
+            left * image_width,
+            top * image_height,
+            right * image_width,
+            bottom * image_height,
+            category,
+        )
+    }
+
+    /// Checks if every edge of two boxes is within epsilon, and that their categories match.
+    pub fn approx_eq(&self, other: &BoundingBox<C>, epsilon: f32) -> bool {
+        (self.left - other.left).abs() <= epsilon
+            && (self.top - other.top).abs() <= epsilon
+            && (self.right - other.right).abs() <= epsilon
+            && (self.bottom - other.bottom).abs() <= epsilon
+            && self.category == other.category
+    }
 }
 
-impl fmt::Display for BoundingBox {
+impl<C: Category> fmt::Display for BoundingBox<C> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
@@ -94,25 +157,94 @@ impl fmt::Display for BoundingBox {
 /// Any annotation that uses a bounding box as its base has "box like geometry", and therefore can
 /// be passed in to useful functions like non-maximum suppression and intersection over union.
 pub trait BoundingBoxGeometry {
+    type Category: Category;
+
     fn left(&self) -> f32;
     fn top(&self) -> f32;
     fn right(&self) -> f32;
     fn bottom(&self) -> f32;
-    fn category(&self) -> &String;
+    fn category(&self) -> &Self::Category;
     fn left_mut(&mut self) -> &mut f32;
     fn top_mut(&mut self) -> &mut f32;
     fn right_mut(&mut self) -> &mut f32;
     fn bottom_mut(&mut self) -> &mut f32;
-    fn category_mut(&mut self) -> &mut String;
+    fn category_mut(&mut self) -> &mut Self::Category;
     fn area(&self) -> f32;
     fn center(&self) -> (f32, f32);
     fn as_xyxy(&self) -> (f32, f32, f32, f32);
     fn intersection_area<T: BoundingBoxGeometry>(&self, other: &T) -> f32;
     fn union_area<T: BoundingBoxGeometry>(&self, other: &T) -> f32;
     fn intersection_over_union<T: BoundingBoxGeometry>(&self, other: &T) -> f32;
+    fn intersection_over_min_area<T: BoundingBoxGeometry>(&self, other: &T) -> f32;
+
+    fn width(&self) -> f32 {
+        self.right() - self.left()
+    }
+
+    fn height(&self) -> f32 {
+        self.bottom() - self.top()
+    }
+
+    /// Computes width / height. A box with zero height has an infinite aspect ratio, or NaN if
+    /// its width is also zero.
+    fn aspect_ratio(&self) -> f32 {
+        self.width() / self.height()
+    }
+
+    /// Shifts the box by dx along the x axis and dy along the y axis.
+    fn translate(&mut self, dx: f32, dy: f32) {
+        *self.left_mut() += dx;
+        *self.top_mut() += dy;
+        *self.right_mut() += dx;
+        *self.bottom_mut() += dy;
+    }
+
+    /// Scales the box's coordinates about the origin, for instance when resizing an image.
+    ///
+    /// sx and sy must be positive, otherwise the edges would swap places.
+    fn scale(&mut self, sx: f32, sy: f32) {
+        *self.left_mut() *= sx;
+        *self.top_mut() *= sy;
+        *self.right_mut() *= sx;
+        *self.bottom_mut() *= sy;
+    }
+
+    /// Returns the corners in the order top left, top right, bottom right, bottom left.
+    fn corners(&self) -> [Point; 4] {
+        [
+            Point {
+                x: self.left(),
+                y: self.top(),
+            },
+            Point {
+                x: self.right(),
+                y: self.top(),
+            },
+            Point {
+                x: self.right(),
+                y: self.bottom(),
+            },
+            Point {
+                x: self.left(),
+                y: self.bottom(),
+            },
+        ]
+    }
+
+    /// Returns the box as (left, top, right, bottom) fractions of the image's width and height.
+    fn as_normalized(&self, image_width: f32, image_height: f32) -> (f32, f32, f32, f32) {
+        (
+            self.left() / image_width,
+            self.top() / image_height,
+            self.right() / image_width,
+            self.bottom() / image_height,
+        )
+    }
 }
 
-impl BoundingBoxGeometry for BoundingBox {
+impl<C: Category> BoundingBoxGeometry for BoundingBox<C> {
+    type Category = C;
+
     fn left(&self) -> f32 {
         self.left
     }
@@ -125,7 +257,7 @@ impl BoundingBoxGeometry for BoundingBox {
     fn bottom(&self) -> f32 {
         self.bottom
     }
-    fn category(&self) -> &String {
+    fn category(&self) -> &C {
         &self.category
     }
 
@@ -141,7 +273,7 @@ impl BoundingBoxGeometry for BoundingBox {
     fn bottom_mut(&mut self) -> &mut f32 {
         &mut self.bottom
     }
-    fn category_mut(&mut self) -> &mut String {
+    fn category_mut(&mut self) -> &mut C {
         &mut self.category
     }
 
@@ -151,8 +283,8 @@ impl BoundingBoxGeometry for BoundingBox {
 
     fn center(&self) -> (f32, f32) {
         (
-            0.5_f32 * (self.right() - self.left()),
-            0.5_f32 * (self.bottom() - self.top()),
+            0.5_f32 * (self.left() + self.right()),
+            0.5_f32 * (self.top() + self.bottom()),
         )
     }
 
@@ -196,6 +328,19 @@ impl BoundingBoxGeometry for BoundingBox {
         }
         intersection_area / union_area
     }
+
+    /// Computes the intersection divided by the area of the smaller box.
+    ///
+    /// Unlike IoU, this is 1.0 whenever one box is entirely inside the other, no matter how much
+    /// larger the outer box is. If either box is degenerate there is nothing to contain, so 0.0
+    /// is returned.
+    fn intersection_over_min_area<T: BoundingBoxGeometry>(&self, other: &T) -> f32 {
+        let min_area = self.area().min(other.area());
+        if min_area == 0_f32 {
+            return 0_f32;
+        }
+        self.intersection_area(other) / min_area
+    }
 }
 
 #[cfg(test)]
@@ -249,6 +394,25 @@ mod tests {
         )
     }
 
+    #[test]
+    fn enclosing_scattered_boxes() {
+        let boxes = vec![
+            BoundingBox::new(2_f32, 3_f32, 4_f32, 5_f32, String::from("first")).unwrap(),
+            BoundingBox::new(-1_f32, 4_f32, 1_f32, 6_f32, String::from("second")).unwrap(),
+            BoundingBox::new(5_f32, 0_f32, 8_f32, 2_f32, String::from("third")).unwrap(),
+        ];
+        assert_eq!(
+            BoundingBox::enclosing(&boxes),
+            Some(BoundingBox::new(-1_f32, 0_f32, 8_f32, 6_f32, String::from("first")).unwrap())
+        );
+    }
+
+    #[test]
+    fn enclosing_empty() {
+        let boxes: Vec<BoundingBox> = vec![];
+        assert_eq!(BoundingBox::enclosing(&boxes), None);
+    }
+
     #[test]
     fn area() {
         let left = 0_f32;
@@ -279,6 +443,16 @@ mod tests {
         assert_eq!(bbox.center(), (1_f32, 0.5_f32));
     }
 
+    #[test]
+    fn center_away_from_origin() {
+        let left = 2_f32;
+        let top = 4_f32;
+        let right = 6_f32;
+        let bottom = 5_f32;
+        let bbox = BoundingBox::new(left, top, right, bottom, String::from("test")).unwrap();
+        assert_eq!(bbox.center(), (4_f32, 4.5_f32));
+    }
+
     #[test]
     fn as_xyxy() {
         let left = 1_f32;
@@ -528,6 +702,32 @@ mod tests {
         assert_eq!(bbox_1.intersection_over_union(&bbox_0), 0_f32);
     }
 
+    #[test]
+    fn iomin_nested() {
+        let bbox_0 = BoundingBox::new(0_f32, 0_f32, 10_f32, 10_f32, String::from("test")).unwrap();
+        let bbox_1 = BoundingBox::new(2_f32, 2_f32, 3_f32, 4_f32, String::from("test")).unwrap();
+
+        assert_eq!(bbox_0.intersection_over_min_area(&bbox_1), 1_f32);
+        assert_eq!(bbox_1.intersection_over_min_area(&bbox_0), 1_f32);
+    }
+
+    #[test]
+    fn iomin_corners_overlap() {
+        let bbox_0 = BoundingBox::new(1_f32, 3_f32, 3_f32, 5_f32, String::from("test")).unwrap();
+        let bbox_1 = BoundingBox::new(2_f32, 1_f32, 5_f32, 4_f32, String::from("test")).unwrap();
+
+        assert_eq!(bbox_0.intersection_over_min_area(&bbox_1), 1_f32 / 4_f32);
+        assert_eq!(bbox_1.intersection_over_min_area(&bbox_0), 1_f32 / 4_f32);
+    }
+
+    #[test]
+    fn iomin_one_box_degenerate() {
+        let bbox_0 = BoundingBox::new(1_f32, 1_f32, 3_f32, 5_f32, String::from("test")).unwrap();
+        let bbox_1 = BoundingBox::new(2_f32, 2_f32, 2_f32, 4_f32, String::from("test")).unwrap();
+
+        assert_eq!(bbox_0.intersection_over_min_area(&bbox_1), 0_f32);
+    }
+
     #[test]
     #[should_panic(
         expected = "Both bounding boxes are degenerate union area is 0, so IOU is undefined."
@@ -550,4 +750,84 @@ mod tests {
         assert_eq!(bbox_0.intersection_over_union(&bbox_1), 0_f32);
         assert_eq!(bbox_1.intersection_over_union(&bbox_0), 0_f32);
     }
+
+    #[test]
+    fn approx_eq_boxes() {
+        let bbox = BoundingBox::new(1_f32, 1_f32, 2_f32, 2_f32, "test".to_string()).unwrap();
+        let near =
+            BoundingBox::new(1.05_f32, 0.95_f32, 2_f32, 2.1_f32, "test".to_string()).unwrap();
+        assert!(bbox.approx_eq(&near, 0.1_f32));
+        assert!(!bbox.approx_eq(&near, 0.09_f32));
+    }
+
+    #[test]
+    fn approx_eq_boxes_different_category() {
+        let bbox = BoundingBox::new(1_f32, 1_f32, 2_f32, 2_f32, "test".to_string()).unwrap();
+        let other = BoundingBox::new(1_f32, 1_f32, 2_f32, 2_f32, "other".to_string()).unwrap();
+        assert!(!bbox.approx_eq(&other, 0.1_f32));
+    }
+
+    #[test]
+    fn translate_box() {
+        let mut bbox = BoundingBox::new(1_f32, 2_f32, 3_f32, 5_f32, "test".to_string()).unwrap();
+        bbox.translate(10_f32, -2_f32);
+        assert_eq!(bbox.as_xyxy(), (11_f32, 0_f32, 13_f32, 3_f32));
+        assert_eq!(bbox.area(), 6_f32);
+    }
+
+    #[test]
+    fn scale_box() {
+        let mut bbox = BoundingBox::new(1_f32, 2_f32, 3_f32, 5_f32, "test".to_string()).unwrap();
+        let area = bbox.area();
+        bbox.scale(2_f32, 0.5_f32);
+        assert_eq!(bbox.as_xyxy(), (2_f32, 1_f32, 6_f32, 2.5_f32));
+        assert_eq!(bbox.area(), area * 2_f32 * 0.5_f32);
+    }
+
+    #[test]
+    fn normalized_round_trip() {
+        let bbox = BoundingBox::new(64_f32, 120_f32, 320_f32, 360_f32, "test".to_string()).unwrap();
+        let (left, top, right, bottom) = bbox.as_normalized(640_f32, 480_f32);
+        assert_eq!((left, top, right, bottom), (0.1, 0.25, 0.5, 0.75));
+        let restored = BoundingBox::from_normalized(
+            left,
+            top,
+            right,
+            bottom,
+            640_f32,
+            480_f32,
+            "test".to_string(),
+        )
+        .unwrap();
+        assert!(restored.approx_eq(&bbox, 1e-4));
+    }
+
+    #[test]
+    fn width_height_and_aspect_ratio() {
+        let bbox = BoundingBox::new(2_f32, 1_f32, 8_f32, 4_f32, "test".to_string()).unwrap();
+        assert_eq!(bbox.width(), 6_f32);
+        assert_eq!(bbox.height(), 3_f32);
+        assert_eq!(bbox.aspect_ratio(), 2_f32);
+    }
+
+    #[test]
+    fn degenerate_aspect_ratio() {
+        let bbox = BoundingBox::new(2_f32, 1_f32, 8_f32, 1_f32, "test".to_string()).unwrap();
+        assert_eq!(bbox.height(), 0_f32);
+        assert_eq!(bbox.aspect_ratio(), f32::INFINITY);
+    }
+
+    #[test]
+    fn corners_are_clockwise_from_top_left() {
+        let bbox = BoundingBox::new(1.0, 2.0, 5.0, 8.0, "test".to_string()).unwrap();
+        assert_eq!(
+            bbox.corners(),
+            [
+                Point { x: 1.0, y: 2.0 },
+                Point { x: 5.0, y: 2.0 },
+                Point { x: 5.0, y: 8.0 },
+                Point { x: 1.0, y: 8.0 },
+            ]
+        );
+    }
 }
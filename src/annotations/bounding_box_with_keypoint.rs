@@ -1,4 +1,6 @@
-use crate::annotations::bounding_box::{BoundingBox, BoundingBoxError, BoundingBoxGeometry};
+use crate::annotations::bounding_box::{
+    BoundingBox, BoundingBoxError, BoundingBoxGeometry, Category,
+};
 use crate::annotations::point::Point;
 use serde::{Deserialize, Serialize};
 use std::fmt;
@@ -9,13 +11,13 @@ use std::fmt;
 /// to place keypoints into the frame as well. Therefore, the output of pose models is both a
 /// bounding box as well as a list of points relating to the "pose" of the object. For this project
 /// we only have pose models that predict a single keypoint.
-#[derive(Debug, Deserialize, Serialize)]
-pub struct BoundingBoxWithKeypoint {
-    bounding_box: BoundingBox,
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct BoundingBoxWithKeypoint<C: Category = String> {
+    bounding_box: BoundingBox<C>,
     keypoint: Point,
 }
 
-impl BoundingBoxWithKeypoint {
+impl<C: Category> BoundingBoxWithKeypoint<C> {
     pub fn new(
         left: f32,
         top: f32,
@@ -23,8 +25,8 @@ impl BoundingBoxWithKeypoint {
         bottom: f32,
         keypoint_x: f32,
         keypoint_y: f32,
-        category: String,
-    ) -> Result<BoundingBoxWithKeypoint, BoundingBoxError> {
+        category: C,
+    ) -> Result<BoundingBoxWithKeypoint<C>, BoundingBoxError> {
         Ok(BoundingBoxWithKeypoint {
             bounding_box: BoundingBox::new(left, top, right, bottom, category)?,
             keypoint: Point {
@@ -35,7 +37,7 @@ impl BoundingBoxWithKeypoint {
     }
 }
 
-impl fmt::Display for BoundingBoxWithKeypoint {
+impl<C: Category> fmt::Display for BoundingBoxWithKeypoint<C> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
             f,
@@ -45,16 +47,18 @@ impl fmt::Display for BoundingBoxWithKeypoint {
     }
 }
 
-impl BoundingBoxWithKeypoint {
-    fn get_keypoint_x(&self) -> f32 {
+impl<C: Category> BoundingBoxWithKeypoint<C> {
+    pub fn get_keypoint_x(&self) -> f32 {
         self.keypoint.x
     }
-    fn get_keypoint_y(&self) -> f32 {
+    pub fn get_keypoint_y(&self) -> f32 {
         self.keypoint.y
     }
 }
 
-impl BoundingBoxGeometry for BoundingBoxWithKeypoint {
+impl<C: Category> BoundingBoxGeometry for BoundingBoxWithKeypoint<C> {
+    type Category = C;
+
     fn left(&self) -> f32 {
         self.bounding_box.left()
     }
@@ -67,7 +71,7 @@ impl BoundingBoxGeometry for BoundingBoxWithKeypoint {
     fn bottom(&self) -> f32 {
         self.bounding_box.bottom()
     }
-    fn category(&self) -> &String {
+    fn category(&self) -> &C {
         self.bounding_box.category()
     }
 
@@ -83,7 +87,7 @@ impl BoundingBoxGeometry for BoundingBoxWithKeypoint {
     fn bottom_mut(&mut self) -> &mut f32 {
         self.bounding_box.bottom_mut()
     }
-    fn category_mut(&mut self) -> &mut String {
+    fn category_mut(&mut self) -> &mut C {
         self.bounding_box.category_mut()
     }
 
@@ -110,4 +114,21 @@ impl BoundingBoxGeometry for BoundingBoxWithKeypoint {
     fn intersection_over_union<T: BoundingBoxGeometry>(&self, other: &T) -> f32 {
         self.bounding_box.intersection_over_union(other)
     }
+
+    fn intersection_over_min_area<T: BoundingBoxGeometry>(&self, other: &T) -> f32 {
+        self.bounding_box.intersection_over_min_area(other)
+    }
+
+    /// Shifts the keypoint along with the box, so tiled pose detections land in image coordinates.
+    fn translate(&mut self, dx: f32, dy: f32) {
+        self.bounding_box.translate(dx, dy);
+        self.keypoint.x += dx;
+        self.keypoint.y += dy;
+    }
+
+    fn scale(&mut self, sx: f32, sy: f32) {
+        self.bounding_box.scale(sx, sy);
+        self.keypoint.x *= sx;
+        self.keypoint.y *= sy;
+    }
 }
@@ -0,0 +1,112 @@
+use crate::annotations::bounding_box::BoundingBoxGeometry;
+use crate::annotations::detection::Detection;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// The coordinate space a set of detections is expressed in.
+///
+/// Every frame is described relative to the original image, so detections can be moved between
+/// any two frames by going through the original. Registration onto a template is not a frame of
+/// the same image, and goes through a PointTransform instead.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub enum CoordinateFrame {
+    /// Pixel coordinates of the original image.
+    Original,
+    /// Coordinates local to a tile whose top left corner is at (x_offset, y_offset) in the
+    /// original image.
+    Tile { x_offset: f32, y_offset: f32 },
+    /// Coordinates of the original image resized by sx along x and sy along y.
+    Resized { sx: f32, sy: f32 },
+    /// Coordinates of the original image scaled by scale and then padded by (pad_x, pad_y), as
+    /// done by LetterboxPreprocessor.
+    Letterboxed { scale: f32, pad_x: f32, pad_y: f32 },
+}
+
+impl CoordinateFrame {
+    /// Returns (sx, sy, dx, dy) such that original = frame * s + d along each axis.
+    fn scale_and_offset_to_original(&self) -> (f32, f32, f32, f32) {
+        match *self {
+            CoordinateFrame::Original => (1.0, 1.0, 0.0, 0.0),
+            CoordinateFrame::Tile { x_offset, y_offset } => (1.0, 1.0, x_offset, y_offset),
+            CoordinateFrame::Resized { sx, sy } => (1.0 / sx, 1.0 / sy, 0.0, 0.0),
+            CoordinateFrame::Letterboxed {
+                scale,
+                pad_x,
+                pad_y,
+            } => (1.0 / scale, 1.0 / scale, -pad_x / scale, -pad_y / scale),
+        }
+    }
+}
+
+/// A set of detections tagged with the coordinate frame they are in.
+///
+/// Carrying the frame alongside the detections means a box can't be silently used in the wrong
+/// space, since the only way to change frames is an explicit to_frame.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct FramedDetections<T: BoundingBoxGeometry + fmt::Display> {
+    pub frame: CoordinateFrame,
+    pub dets: Vec<Detection<T>>,
+}
+
+impl<T: BoundingBoxGeometry + fmt::Display> FramedDetections<T> {
+    /// Converts the detections into another frame, through the original image's frame.
+    pub fn to_frame(&self, frame: CoordinateFrame) -> FramedDetections<T>
+    where
+        T: Clone,
+    {
+        let (from_sx, from_sy, from_dx, from_dy) = self.frame.scale_and_offset_to_original();
+        let (to_sx, to_sy, to_dx, to_dy) = frame.scale_and_offset_to_original();
+        let mut dets = self.dets.clone();
+        for det in dets.iter_mut() {
+            det.annotation.scale(from_sx, from_sy);
+            det.annotation.translate(from_dx - to_dx, from_dy - to_dy);
+            det.annotation.scale(1.0 / to_sx, 1.0 / to_sy);
+        }
+        FramedDetections { frame, dets }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotations::bounding_box::BoundingBox;
+
+    fn create_testing_framed_detections(frame: CoordinateFrame) -> FramedDetections<BoundingBox> {
+        FramedDetections {
+            frame,
+            dets: vec![Detection {
+                annotation: BoundingBox::new(10.0, 20.0, 30.0, 60.0, "test".to_string()).unwrap(),
+                confidence: 0.9,
+            }],
+        }
+    }
+
+    #[test]
+    fn to_frame_tile_to_original_and_back() {
+        let tile = CoordinateFrame::Tile {
+            x_offset: 320.0,
+            y_offset: 640.0,
+        };
+        let original = create_testing_framed_detections(tile).to_frame(CoordinateFrame::Original);
+        assert_eq!(original.frame, CoordinateFrame::Original);
+        assert_eq!(
+            original.dets[0].annotation.as_xyxy(),
+            (330.0, 660.0, 350.0, 700.0)
+        );
+        let back = original.to_frame(tile);
+        assert_eq!(back, create_testing_framed_detections(tile));
+    }
+
+    #[test]
+    fn to_frame_between_tile_and_resized() {
+        let resized = create_testing_framed_detections(CoordinateFrame::Tile {
+            x_offset: 100.0,
+            y_offset: 0.0,
+        })
+        .to_frame(CoordinateFrame::Resized { sx: 0.5, sy: 2.0 });
+        assert_eq!(
+            resized.dets[0].annotation.as_xyxy(),
+            (55.0, 40.0, 65.0, 120.0)
+        );
+    }
+}
@@ -1,17 +1,36 @@
 use crate::annotations::bounding_box::BoundingBoxGeometry;
 use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
 use std::fmt;
 
 /// A detection is what is produced as output from an object detection model.
 ///
 /// A detection is any annotation combined with a confidence score: a probability value that
 /// encodes the model's belief that the detection is true.
-#[derive(Debug, Deserialize, PartialEq, Serialize)]
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
 pub struct Detection<T: BoundingBoxGeometry + fmt::Display> {
     pub annotation: T,
     pub confidence: f32,
 }
 
+impl<T: BoundingBoxGeometry + fmt::Display> Detection<T> {
+    /// Sorts detections from highest to lowest confidence.
+    ///
+    /// Models can emit NaN scores, which partial_cmp cannot order. NaN confidences are placed
+    /// after every other detection rather than panicking.
+    pub fn sort_by_confidence_desc(dets: &mut [Detection<T>]) {
+        dets.sort_by(
+            |a, b| match (a.confidence.is_nan(), b.confidence.is_nan()) {
+                (true, true) => Ordering::Equal,
+                (true, false) => Ordering::Greater,
+                (false, true) => Ordering::Less,
+                (false, false) => b.confidence.total_cmp(&a.confidence),
+            },
+        );
+    }
+}
+
 impl<T: BoundingBoxGeometry + fmt::Display> fmt::Display for Detection<T> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(
@@ -21,3 +40,113 @@ impl<T: BoundingBoxGeometry + fmt::Display> fmt::Display for Detection<T> {
         )
     }
 }
+
+/// A compact description of a set of detections, for logging.
+///
+/// The confidences are None when the set is empty.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DetectionSummary {
+    pub total: usize,
+    pub per_category: HashMap<String, usize>,
+    pub min_confidence: Option<f32>,
+    pub max_confidence: Option<f32>,
+}
+
+impl fmt::Display for DetectionSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} detections across {} categories",
+            self.total,
+            self.per_category.len()
+        )?;
+        if let (Some(min), Some(max)) = (self.min_confidence, self.max_confidence) {
+            write!(f, ", confidence {:.2}–{:.2}", min, max)?;
+        }
+        Ok(())
+    }
+}
+
+/// Counts detections per category and finds the range of their confidences.
+pub fn summarize_detections<T: BoundingBoxGeometry + fmt::Display>(
+    dets: &[Detection<T>],
+) -> DetectionSummary {
+    let mut per_category: HashMap<String, usize> = HashMap::new();
+    for det in dets {
+        *per_category
+            .entry(det.annotation.category().to_string())
+            .or_default() += 1;
+    }
+    let confidences = dets.iter().map(|det| det.confidence);
+    DetectionSummary {
+        total: dets.len(),
+        per_category,
+        min_confidence: confidences.clone().reduce(f32::min),
+        max_confidence: confidences.reduce(f32::max),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotations::bounding_box::BoundingBox;
+
+    fn create_testing_detection(confidence: f32) -> Detection<BoundingBox> {
+        Detection {
+            annotation: BoundingBox::new(0_f32, 0_f32, 1_f32, 1_f32, "test".to_string()).unwrap(),
+            confidence,
+        }
+    }
+
+    #[test]
+    fn sort_by_confidence_desc_with_nan() {
+        let mut dets = vec![
+            create_testing_detection(0.2_f32),
+            create_testing_detection(f32::NAN),
+            create_testing_detection(0.9_f32),
+            create_testing_detection(0.5_f32),
+        ];
+        Detection::sort_by_confidence_desc(&mut dets);
+        let confidences: Vec<f32> = dets.iter().map(|d| d.confidence).collect();
+        assert_eq!(confidences[..3], [0.9_f32, 0.5_f32, 0.2_f32]);
+        assert!(confidences[3].is_nan());
+    }
+
+    #[test]
+    fn serde_round_trip() {
+        let dets = vec![
+            create_testing_detection(0.2_f32),
+            Detection {
+                annotation: BoundingBox::new(2_f32, 3_f32, 4_f32, 5_f32, "other".to_string())
+                    .unwrap(),
+                confidence: 0.7_f32,
+            },
+        ];
+        let json = serde_json::to_string(&dets).unwrap();
+        let round_tripped: Vec<Detection<BoundingBox>> = serde_json::from_str(&json).unwrap();
+        assert_eq!(dets, round_tripped);
+    }
+
+    #[test]
+    fn summarize_detections_mixed_set() {
+        let dets: Vec<Detection<BoundingBox>> =
+            [("systolic", 0.41), ("systolic", 0.98), ("heart_rate", 0.7)]
+                .iter()
+                .map(|(category, confidence)| Detection {
+                    annotation: BoundingBox::new(0_f32, 0_f32, 1_f32, 1_f32, category.to_string())
+                        .unwrap(),
+                    confidence: *confidence,
+                })
+                .collect();
+        let summary = summarize_detections(&dets);
+        assert_eq!(summary.total, 3);
+        assert_eq!(summary.per_category["systolic"], 2);
+        assert_eq!(summary.per_category["heart_rate"], 1);
+        assert_eq!(summary.min_confidence, Some(0.41));
+        assert_eq!(summary.max_confidence, Some(0.98));
+        assert_eq!(
+            summary.to_string(),
+            "3 detections across 2 categories, confidence 0.41–0.98"
+        );
+    }
+}
@@ -1,4 +1,5 @@
 pub mod bounding_box;
 pub mod bounding_box_with_keypoint;
+pub mod coordinate_frame;
 pub mod detection;
 pub mod point;
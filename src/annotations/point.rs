@@ -9,6 +9,59 @@ pub struct Point {
     pub y: f32,
 }
 
+/// The ways of measuring the distance between two points.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DistanceMetric {
+    /// The straight line distance.
+    Euclidean,
+    /// The sum of the distances along each axis.
+    Manhattan,
+    /// The largest of the distances along each axis.
+    Chebyshev,
+}
+
+impl Point {
+    /// Computes the distance to another point under the given metric.
+    pub fn distance(&self, other: &Point, metric: DistanceMetric) -> f32 {
+        let dx = (self.x - other.x).abs();
+        let dy = (self.y - other.y).abs();
+        match metric {
+            DistanceMetric::Euclidean => (dx * dx + dy * dy).sqrt(),
+            DistanceMetric::Manhattan => dx + dy,
+            DistanceMetric::Chebyshev => dx.max(dy),
+        }
+    }
+
+    /// Checks if two points are within epsilon of each other along both axes.
+    ///
+    /// PartialEq compares the exact bits so that points can be hashed, which makes it too strict
+    /// for comparing coordinates that went through a transformation.
+    pub fn approx_eq(&self, other: &Point, epsilon: f32) -> bool {
+        (self.x - other.x).abs() <= epsilon && (self.y - other.y).abs() <= epsilon
+    }
+
+    /// Rotates the point counterclockwise around center by the given angle in radians.
+    ///
+    /// With image coordinates, where y points down, the rotation appears clockwise.
+    pub fn rotate_around(&self, center: Point, radians: f32) -> Point {
+        let (sin, cos) = radians.sin_cos();
+        let dx = self.x - center.x;
+        let dy = self.y - center.y;
+        Point {
+            x: cos * dx - sin * dy + center.x,
+            y: sin * dx + cos * dy + center.y,
+        }
+    }
+
+    /// Applies the affine map matrix * point + translation, where matrix is row major.
+    pub fn apply_affine(&self, matrix: [[f32; 2]; 2], translation: Point) -> Point {
+        Point {
+            x: matrix[0][0] * self.x + matrix[0][1] * self.y + translation.x,
+            y: matrix[1][0] * self.x + matrix[1][1] * self.y + translation.y,
+        }
+    }
+}
+
 impl fmt::Display for Point {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "Point {{ x: {}, y: {} }}", self.x, self.y)
@@ -39,3 +92,52 @@ impl hash::Hash for Point {
         self.y.to_bits().hash(state);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn approx_eq_within_epsilon() {
+        let a = Point { x: 2.0, y: 3.0 };
+        let b = Point {
+            x: 1.9999998,
+            y: 3.0000002,
+        };
+        assert!(a.approx_eq(&b, 0.00001));
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn approx_eq_at_epsilon_boundary() {
+        let a = Point { x: 0.0, y: 0.0 };
+        assert!(a.approx_eq(&Point { x: 0.5, y: -0.5 }, 0.5));
+        assert!(!a.approx_eq(&Point { x: 0.5, y: 0.51 }, 0.5));
+    }
+
+    #[test]
+    fn distance_metrics() {
+        let a = Point { x: 1.0, y: 1.0 };
+        let b = Point { x: 4.0, y: 5.0 };
+        assert_eq!(a.distance(&b, DistanceMetric::Euclidean), 5.0);
+        assert_eq!(a.distance(&b, DistanceMetric::Manhattan), 7.0);
+        assert_eq!(a.distance(&b, DistanceMetric::Chebyshev), 4.0);
+    }
+
+    #[test]
+    fn rotate_around_quarter_turn() {
+        let p = Point { x: 1.0, y: 0.0 };
+        let origin = Point { x: 0.0, y: 0.0 };
+        let rotated = p.rotate_around(origin, std::f32::consts::FRAC_PI_2);
+        assert!(rotated.approx_eq(&Point { x: 0.0, y: 1.0 }, 1e-6));
+        let rotated = p.rotate_around(Point { x: 1.0, y: 1.0 }, std::f32::consts::FRAC_PI_2);
+        assert!(rotated.approx_eq(&Point { x: 2.0, y: 1.0 }, 1e-6));
+    }
+
+    #[test]
+    fn apply_affine_known_map() {
+        let p = Point { x: 2.0, y: 3.0 };
+        let mapped = p.apply_affine([[2.0, 1.0], [0.0, -1.0]], Point { x: 5.0, y: 1.0 });
+        assert_eq!(mapped, Point { x: 12.0, y: -2.0 });
+    }
+}
@@ -0,0 +1,160 @@
+use crate::image_utils::image_io::ImageIoError;
+use crate::image_utils::padding::ImagePaddingError;
+use crate::image_utils::tiling::TilingError;
+use crate::object_detection::object_detection_model::InferenceError;
+use crate::object_detection::object_detection_utils::TileAndPredictError;
+use crate::registration::thin_plate_splines::TpsError;
+use std::fmt;
+
+/// A single error type for the whole extraction pipeline.
+///
+/// Each module keeps its own error type, and converts into this one with ?, so a function
+/// running several stages can return one error without losing which stage failed.
+#[derive(Debug)]
+pub enum ChartExtractionError {
+    Tiling(TilingError),
+    Padding(ImagePaddingError),
+    ImageIo(ImageIoError),
+    Registration(TpsError),
+    Inference(InferenceError),
+}
+
+impl fmt::Display for ChartExtractionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChartExtractionError::Tiling(e) => write!(f, "{}", e),
+            ChartExtractionError::Padding(e) => write!(f, "{}", e),
+            ChartExtractionError::ImageIo(e) => write!(f, "{}", e),
+            ChartExtractionError::Registration(e) => write!(f, "{}", e),
+            ChartExtractionError::Inference(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for ChartExtractionError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ChartExtractionError::Tiling(e) => Some(e),
+            ChartExtractionError::Padding(e) => Some(e),
+            ChartExtractionError::ImageIo(e) => Some(e),
+            ChartExtractionError::Registration(e) => Some(e),
+            ChartExtractionError::Inference(e) => Some(e),
+        }
+    }
+}
+
+impl From<TilingError> for ChartExtractionError {
+    fn from(e: TilingError) -> Self {
+        ChartExtractionError::Tiling(e)
+    }
+}
+
+impl From<ImagePaddingError> for ChartExtractionError {
+    fn from(e: ImagePaddingError) -> Self {
+        ChartExtractionError::Padding(e)
+    }
+}
+
+impl From<ImageIoError> for ChartExtractionError {
+    fn from(e: ImageIoError) -> Self {
+        ChartExtractionError::ImageIo(e)
+    }
+}
+
+impl From<TpsError> for ChartExtractionError {
+    fn from(e: TpsError) -> Self {
+        ChartExtractionError::Registration(e)
+    }
+}
+
+impl From<InferenceError> for ChartExtractionError {
+    fn from(e: InferenceError) -> Self {
+        ChartExtractionError::Inference(e)
+    }
+}
+
+impl From<TileAndPredictError> for ChartExtractionError {
+    fn from(e: TileAndPredictError) -> Self {
+        match e {
+            TileAndPredictError::Tiling(e) => ChartExtractionError::Tiling(e),
+            TileAndPredictError::Inference(e) => ChartExtractionError::Inference(e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotations::bounding_box::BoundingBoxError;
+    use crate::image_utils::image_io::read_rgb8_from_bytes;
+    use crate::image_utils::tiling::OverlapProportion;
+
+    fn convert<E: Into<ChartExtractionError>>(e: E) -> ChartExtractionError {
+        e.into()
+    }
+
+    #[test]
+    fn module_errors_convert_and_display() {
+        let tiling = convert(TilingError::InvalidTileSize {
+            tile_size: 8,
+            image_width: 16,
+            image_height: 4,
+        });
+        assert!(matches!(tiling, ChartExtractionError::Tiling(_)));
+        assert_eq!(
+            tiling.to_string(),
+            "Failed to tile image, tile size (8) > image height (4)."
+        );
+
+        let padding = convert(ImagePaddingError::InvalidWidth {
+            original_width: 10,
+            new_width: 5,
+        });
+        assert!(matches!(padding, ChartExtractionError::Padding(_)));
+        assert_eq!(
+            padding.to_string(),
+            "Failed to pad image, new width (5) < original width (10)."
+        );
+
+        let image_io = convert(read_rgb8_from_bytes(b"not an image").unwrap_err());
+        assert!(matches!(image_io, ChartExtractionError::ImageIo(_)));
+        assert!(image_io.to_string().starts_with("Failed to read image"));
+
+        let registration = convert(TpsError::TooFewControlPoints { found: 2 });
+        assert!(matches!(
+            registration,
+            ChartExtractionError::Registration(_)
+        ));
+        assert!(registration.to_string().contains("2 were found"));
+
+        let inference = convert(InferenceError::InvalidBox(
+            BoundingBoxError::InvalidLeftRight {
+                left: 2.0,
+                right: 1.0,
+            },
+        ));
+        assert!(matches!(inference, ChartExtractionError::Inference(_)));
+        assert_eq!(
+            inference.to_string(),
+            "The model produced an invalid box: Failed to create BoundingBox, left (2) > right (1)."
+        );
+    }
+
+    #[test]
+    fn tile_and_predict_error_is_flattened() {
+        let proportion = OverlapProportion {
+            numerator: 1,
+            denominator: 0,
+        };
+        let error = convert(TileAndPredictError::Tiling(
+            TilingError::InvalidOverlapProportion {
+                numerator: proportion.numerator,
+                denominator: proportion.denominator,
+            },
+        ));
+        assert!(matches!(
+            error,
+            ChartExtractionError::Tiling(TilingError::InvalidOverlapProportion { .. })
+        ));
+    }
+}
@@ -0,0 +1,88 @@
+use crate::annotations::bounding_box::BoundingBoxGeometry;
+use crate::annotations::bounding_box_with_keypoint::BoundingBoxWithKeypoint;
+use crate::annotations::detection::Detection;
+use crate::annotations::point::Point;
+use crate::digitization::grid_assignment::assign_to_grid;
+use std::collections::HashMap;
+
+/// Reads the systolic and diastolic blood pressures off of the intraoperative chart.
+///
+/// Blood pressures are recorded with a caret for systolic and a 'V' for diastolic, and the pose
+/// model places a keypoint at the tip of each symbol. The keypoint is mapped to the nearest time
+/// column and the nearest value row, where the time axis is given as (timestamp, x) and the value
+/// axis as (mmHg, y). Symbols are split by their category, which must be "systolic" or
+/// "diastolic". Symbols with any other category, or whose value row does not parse as an
+/// integer, are skipped.
+///
+/// Returns (systolic, diastolic), each a map from timestamp to mmHg.
+pub fn extract_blood_pressure(
+    dets: &[Detection<BoundingBoxWithKeypoint>],
+    time_axis: &[(String, f32)],
+    value_axis: &[(String, f32)],
+) -> (HashMap<String, u32>, HashMap<String, u32>) {
+    let mut systolic: HashMap<String, u32> = HashMap::new();
+    let mut diastolic: HashMap<String, u32> = HashMap::new();
+    for det in dets {
+        let readings = match det.annotation.category().as_str() {
+            "systolic" => &mut systolic,
+            "diastolic" => &mut diastolic,
+            _ => continue,
+        };
+        let keypoint = Point {
+            x: det.annotation.get_keypoint_x(),
+            y: det.annotation.get_keypoint_y(),
+        };
+        let Some((time, value)) = assign_to_grid(keypoint, time_axis, value_axis) else {
+            continue;
+        };
+        if let Ok(value) = value.parse::<u32>() {
+            readings.insert(time, value);
+        }
+    }
+    (systolic, diastolic)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_testing_symbol(
+        keypoint_x: f32,
+        keypoint_y: f32,
+        category: &str,
+    ) -> Detection<BoundingBoxWithKeypoint> {
+        Detection {
+            annotation: BoundingBoxWithKeypoint::new(
+                keypoint_x - 5_f32,
+                keypoint_y - 5_f32,
+                keypoint_x + 5_f32,
+                keypoint_y + 5_f32,
+                keypoint_x,
+                keypoint_y,
+                category.to_string(),
+            )
+            .unwrap(),
+            confidence: 0.9,
+        }
+    }
+
+    #[test]
+    fn extract_two_symbols() {
+        let time_axis = vec![
+            ("08:00".to_string(), 100_f32),
+            ("08:05".to_string(), 120_f32),
+        ];
+        let value_axis = vec![
+            ("120".to_string(), 50_f32),
+            ("100".to_string(), 60_f32),
+            ("80".to_string(), 70_f32),
+        ];
+        let dets = vec![
+            create_testing_symbol(101_f32, 49_f32, "systolic"),
+            create_testing_symbol(119_f32, 71_f32, "diastolic"),
+        ];
+        let (systolic, diastolic) = extract_blood_pressure(&dets, &time_axis, &value_axis);
+        assert_eq!(systolic, HashMap::from([("08:00".to_string(), 120)]));
+        assert_eq!(diastolic, HashMap::from([("08:05".to_string(), 80)]));
+    }
+}
@@ -0,0 +1,148 @@
+use crate::annotations::bounding_box::BoundingBoxGeometry;
+use crate::annotations::detection::Detection;
+use crate::annotations::point::{DistanceMetric, Point};
+use crate::digitization::grid_assignment::find_min_distance_key;
+use std::collections::HashMap;
+use std::fmt;
+
+/// A set of custom errors for more informative error handling.
+#[derive(Debug, PartialEq)]
+pub enum CheckboxError {
+    NoCentroids,
+    NanCenter { detection_index: usize },
+}
+
+impl fmt::Display for CheckboxError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CheckboxError::NoCentroids => {
+                write!(
+                    f,
+                    "Failed to digitize checkboxes, no centroids were provided."
+                )
+            }
+            CheckboxError::NanCenter { detection_index } => {
+                write!(
+                    f,
+                    "Failed to digitize checkboxes, detection {} has a NaN center.",
+                    detection_index
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for CheckboxError {}
+
+/// Reads which of the chart's checkboxes are ticked.
+///
+/// The checkbox model labels each box "checked" or "unchecked", and each detection is assigned
+/// to the nearest named centroid. When several detections land on the same checkbox, the most
+/// confident one decides its value. Detections with any other category are skipped, and
+/// checkboxes without a detection are left out of the map.
+pub fn digitize_checkboxes<T: BoundingBoxGeometry<Category = String> + fmt::Display>(
+    dets: &[Detection<T>],
+    centroids: &HashMap<String, Point>,
+) -> Result<HashMap<String, bool>, CheckboxError> {
+    if centroids.is_empty() {
+        return Err(CheckboxError::NoCentroids);
+    }
+    let mut most_confident: HashMap<String, (f32, bool)> = HashMap::new();
+    for (detection_index, det) in dets.iter().enumerate() {
+        let is_checked = match det.annotation.category().as_str() {
+            "checked" => true,
+            "unchecked" => false,
+            _ => continue,
+        };
+        let (x, y) = det.annotation.center();
+        if x.is_nan() || y.is_nan() {
+            return Err(CheckboxError::NanCenter { detection_index });
+        }
+        let Some(name) =
+            find_min_distance_key(Point { x, y }, centroids, DistanceMetric::Euclidean)
+        else {
+            continue;
+        };
+        let entry = most_confident
+            .entry(name)
+            .or_insert((det.confidence, is_checked));
+        if det.confidence > entry.0 {
+            *entry = (det.confidence, is_checked);
+        }
+    }
+    Ok(most_confident
+        .into_iter()
+        .map(|(name, (_, is_checked))| (name, is_checked))
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotations::bounding_box::BoundingBox;
+
+    fn create_testing_checkbox(
+        x: f32,
+        y: f32,
+        category: &str,
+        confidence: f32,
+    ) -> Detection<BoundingBox> {
+        Detection {
+            annotation: BoundingBox::new(
+                x - 2_f32,
+                y - 2_f32,
+                x + 2_f32,
+                y + 2_f32,
+                category.to_string(),
+            )
+            .unwrap(),
+            confidence,
+        }
+    }
+
+    fn create_testing_centroids() -> HashMap<String, Point> {
+        HashMap::from([
+            ("mask_ventilation".to_string(), Point { x: 10.0, y: 10.0 }),
+            ("oral_airway".to_string(), Point { x: 30.0, y: 10.0 }),
+            ("eye_protection".to_string(), Point { x: 50.0, y: 10.0 }),
+        ])
+    }
+
+    #[test]
+    fn digitize_checkboxes_without_centroids() {
+        let dets = vec![create_testing_checkbox(10.0, 10.0, "checked", 0.9)];
+        assert_eq!(
+            digitize_checkboxes(&dets, &HashMap::new()),
+            Err(CheckboxError::NoCentroids)
+        );
+    }
+
+    #[test]
+    fn digitize_checkboxes_standard_usage() {
+        let dets = vec![
+            create_testing_checkbox(11.0, 9.0, "checked", 0.9),
+            create_testing_checkbox(29.0, 11.0, "unchecked", 0.8),
+            create_testing_checkbox(30.0, 10.0, "checked", 0.4),
+        ];
+        let checkboxes = digitize_checkboxes(&dets, &create_testing_centroids()).unwrap();
+        assert_eq!(
+            checkboxes,
+            HashMap::from([
+                ("mask_ventilation".to_string(), true),
+                ("oral_airway".to_string(), false),
+            ])
+        );
+    }
+
+    #[test]
+    fn digitize_checkboxes_nan_center() {
+        let dets = vec![
+            create_testing_checkbox(10.0, 10.0, "checked", 0.9),
+            create_testing_checkbox(f32::NAN, 10.0, "checked", 0.9),
+        ];
+        assert_eq!(
+            digitize_checkboxes(&dets, &create_testing_centroids()),
+            Err(CheckboxError::NanCenter { detection_index: 1 })
+        );
+    }
+}
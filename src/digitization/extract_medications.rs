@@ -0,0 +1,148 @@
+use crate::annotations::bounding_box::BoundingBox;
+use crate::annotations::detection::Detection;
+use crate::digitization::grid_assignment::{group_by_column, group_by_row};
+use crate::digitization::read_boxed_digits::assemble_number;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+
+/// The largest value a three digit drug code can take.
+const MAX_CODE: u32 = 999;
+
+/// A three digit code identifying a drug.
+///
+/// Codes can start with zeros, which are lost when the digits are parsed as a number, so the
+/// code is always displayed padded to three digits.
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Code(pub u32);
+
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:03}", self.0)
+    }
+}
+
+/// The doses given of a single drug.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct DosingRecord {
+    /// The drug's code, if one was written.
+    pub code: Option<Code>,
+    /// A map from timestamp to the dose given at that time.
+    pub doses: HashMap<String, u32>,
+}
+
+/// The medication section of the intraoperative chart.
+///
+/// The first three rows are preprinted with propofol, rocuronium and fentanyl, so they are kept
+/// in their own fields (None when the row is empty). The remaining rows are filled in by hand and
+/// are kept in chart order.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct MedicationSection {
+    pub propofol: Option<DosingRecord>,
+    pub rocuronium: Option<DosingRecord>,
+    pub fentanyl: Option<DosingRecord>,
+    pub other_medications: Vec<DosingRecord>,
+}
+
+/// Builds a row's record from its code and dose digits. Returns None for an empty row.
+fn build_dosing_record(
+    code_dets: &[Detection<BoundingBox>],
+    dose_dets: &[Detection<BoundingBox>],
+    time_axis: &[(String, f32)],
+) -> Option<DosingRecord> {
+    let code = assemble_number(code_dets)
+        .filter(|code| *code <= MAX_CODE)
+        .map(Code);
+    let doses: HashMap<String, u32> = group_by_column(dose_dets, time_axis)
+        .into_iter()
+        .filter_map(|(time, digits)| Some((time, assemble_number(&digits)?)))
+        .collect();
+    if code.is_none() && doses.is_empty() {
+        return None;
+    }
+    Some(DosingRecord { code, doses })
+}
+
+/// Reads the medication section of the intraoperative chart.
+///
+/// Code and dose digits are assigned to the nearest row, where rows are given as (label, y) in
+/// chart order from top to bottom. Each row's code digits are assembled into its three digit code,
+/// and its dose digits are grouped by the nearest time column (given as (timestamp, x)) and
+/// assembled into one dose per timestamp.
+pub fn extract_medication_section(
+    code_dets: &[Detection<BoundingBox>],
+    dose_dets: &[Detection<BoundingBox>],
+    row_centroids: &[(String, f32)],
+    time_axis: &[(String, f32)],
+) -> MedicationSection {
+    let mut code_rows = group_by_row(code_dets, row_centroids);
+    let mut dose_rows = group_by_row(dose_dets, row_centroids);
+    let mut records = row_centroids.iter().map(|(label, _)| {
+        build_dosing_record(
+            &code_rows.remove(label).unwrap_or_default(),
+            &dose_rows.remove(label).unwrap_or_default(),
+            time_axis,
+        )
+    });
+    MedicationSection {
+        propofol: records.next().flatten(),
+        rocuronium: records.next().flatten(),
+        fentanyl: records.next().flatten(),
+        other_medications: records.flatten().collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_testing_digit(x: f32, y: f32, digit: &str) -> Detection<BoundingBox> {
+        Detection {
+            annotation: BoundingBox::new(
+                x - 2_f32,
+                y - 3_f32,
+                x + 2_f32,
+                y + 3_f32,
+                digit.to_string(),
+            )
+            .unwrap(),
+            confidence: 0.9,
+        }
+    }
+
+    #[test]
+    fn extract_one_dosing_record() {
+        let row_centroids: Vec<(String, f32)> = (0..9)
+            .map(|row| (row.to_string(), 100_f32 + 20_f32 * row as f32))
+            .collect();
+        let time_axis = vec![
+            ("08:00".to_string(), 200_f32),
+            ("08:15".to_string(), 240_f32),
+        ];
+        // The fourth row (y = 160) has code 042, 50 given at 08:00 and 5 given at 08:15.
+        let code_dets = vec![
+            create_testing_digit(10_f32, 161_f32, "0"),
+            create_testing_digit(15_f32, 159_f32, "4"),
+            create_testing_digit(20_f32, 160_f32, "2"),
+        ];
+        let dose_dets = vec![
+            create_testing_digit(203_f32, 160_f32, "0"),
+            create_testing_digit(197_f32, 160_f32, "5"),
+            create_testing_digit(241_f32, 162_f32, "5"),
+        ];
+        let section =
+            extract_medication_section(&code_dets, &dose_dets, &row_centroids, &time_axis);
+        let true_record = DosingRecord {
+            code: Some(Code(42)),
+            doses: HashMap::from([("08:00".to_string(), 50), ("08:15".to_string(), 5)]),
+        };
+        assert_eq!(section.propofol, None);
+        assert_eq!(section.rocuronium, None);
+        assert_eq!(section.fentanyl, None);
+        assert_eq!(section.other_medications, vec![true_record]);
+        assert_eq!(
+            section.other_medications[0].code.unwrap().to_string(),
+            "042"
+        );
+    }
+}
@@ -0,0 +1,103 @@
+use crate::annotations::bounding_box::{BoundingBox, BoundingBoxGeometry};
+use crate::annotations::detection::Detection;
+use crate::digitization::grid_assignment::group_by_column;
+use std::collections::HashMap;
+
+/// Finds the vertical band covered by a value axis, padded by half of the mean row spacing.
+///
+/// Returns None for an empty axis.
+fn value_axis_band(value_axis: &[(String, f32)]) -> Option<(f32, f32)> {
+    let min_y = value_axis.iter().map(|(_, y)| *y).reduce(f32::min)?;
+    let max_y = value_axis.iter().map(|(_, y)| *y).reduce(f32::max)?;
+    let padding = if value_axis.len() > 1 {
+        0.5 * (max_y - min_y) / (value_axis.len() - 1) as f32
+    } else {
+        0_f32
+    };
+    Some((min_y - padding, max_y + padding))
+}
+
+/// Reads a time series section of the chart, such as SpO2, EtCO2 or temperature.
+///
+/// Detections whose boxes do not reach the rows spanned by the value axis (given as (label, y))
+/// belong to other sections and are dropped. The remaining detections are grouped by
+/// the nearest time column (given as (timestamp, x)), and value_parse turns each column's
+/// detections into a value, for instance by assembling digits with assemble_number. Columns for
+/// which value_parse returns None are left out.
+///
+/// Returns a map from timestamp to value.
+pub fn extract_time_series<T>(
+    dets: &[Detection<BoundingBox>],
+    time_axis: &[(String, f32)],
+    value_axis: &[(String, f32)],
+    value_parse: impl Fn(&[Detection<BoundingBox>]) -> Option<T>,
+) -> HashMap<String, T> {
+    let Some((band_top, band_bottom)) = value_axis_band(value_axis) else {
+        return HashMap::new();
+    };
+    let dets_in_band: Vec<Detection<BoundingBox>> = dets
+        .iter()
+        .filter(|det| det.annotation.top() <= band_bottom && band_top <= det.annotation.bottom())
+        .cloned()
+        .collect();
+    group_by_column(&dets_in_band, time_axis)
+        .into_iter()
+        .filter_map(|(time, column)| Some((time, value_parse(&column)?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::digitization::read_boxed_digits::assemble_number;
+
+    fn create_testing_digit(x: f32, y: f32, digit: &str) -> Detection<BoundingBox> {
+        Detection {
+            annotation: BoundingBox::new(
+                x - 2_f32,
+                y - 3_f32,
+                x + 2_f32,
+                y + 3_f32,
+                digit.to_string(),
+            )
+            .unwrap(),
+            confidence: 0.9,
+        }
+    }
+
+    #[test]
+    fn extract_two_column_integer_series() {
+        let time_axis = vec![
+            ("08:00".to_string(), 100_f32),
+            ("08:05".to_string(), 120_f32),
+        ];
+        let value_axis = vec![("spo2".to_string(), 50_f32)];
+        let dets = vec![
+            create_testing_digit(97_f32, 50_f32, "9"),
+            create_testing_digit(103_f32, 50_f32, "8"),
+            create_testing_digit(117_f32, 50_f32, "9"),
+            create_testing_digit(123_f32, 50_f32, "9"),
+            // A digit from the row below, which is not part of this series.
+            create_testing_digit(100_f32, 70_f32, "1"),
+        ];
+        let series = extract_time_series(&dets, &time_axis, &value_axis, assemble_number);
+        let true_series = HashMap::from([("08:00".to_string(), 98), ("08:05".to_string(), 99)]);
+        assert_eq!(series, true_series);
+    }
+
+    #[test]
+    fn extract_series_as_floats() {
+        let time_axis = vec![("08:00".to_string(), 100_f32)];
+        let value_axis = vec![("temperature".to_string(), 50_f32)];
+        let dets = vec![
+            create_testing_digit(95_f32, 50_f32, "3"),
+            create_testing_digit(100_f32, 50_f32, "6"),
+            create_testing_digit(105_f32, 50_f32, "5"),
+        ];
+        // Temperatures are written without the decimal point, in tenths of a degree.
+        let series = extract_time_series(&dets, &time_axis, &value_axis, |column| {
+            assemble_number(column).map(|tenths| tenths as f32 / 10_f32)
+        });
+        assert_eq!(series, HashMap::from([("08:00".to_string(), 36.5_f32)]));
+    }
+}
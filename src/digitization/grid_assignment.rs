@@ -0,0 +1,253 @@
+use crate::annotations::bounding_box::{BoundingBox, BoundingBoxGeometry};
+use crate::annotations::detection::Detection;
+use crate::annotations::point::{DistanceMetric, Point};
+use std::collections::HashMap;
+use std::fmt::Display;
+
+/// Finds the label of the centroid closest to a value along a single axis.
+///
+/// Returns None if there are no centroids.
+pub fn find_nearest_label(value: f32, centroids: &[(String, f32)]) -> Option<&String> {
+    centroids
+        .iter()
+        .min_by(|a, b| (a.1 - value).abs().total_cmp(&(b.1 - value).abs()))
+        .map(|(label, _)| label)
+}
+
+/// Finds the name of the centroid closest to a point.
+///
+/// Euclidean distance is the natural choice for free-floating landmarks, while Manhattan or
+/// Chebyshev distance can give more intuitive results when snapping to an axis-aligned grid.
+/// Returns None if there are no centroids.
+pub fn find_min_distance_key(
+    point: Point,
+    centroids: &HashMap<String, Point>,
+    metric: DistanceMetric,
+) -> Option<String> {
+    centroids
+        .iter()
+        .map(|(key, centroid)| (key, point.distance(centroid, metric)))
+        .min_by(|a, b| a.1.total_cmp(&b.1).then_with(|| a.0.cmp(b.0)))
+        .map(|(key, _)| key.clone())
+}
+
+/// Lists the names found in only one of a centroid map and a model's class names.
+///
+/// A centroid map whose keys don't match what the model emits silently produces no matches, so
+/// this should be checked when the two are loaded. The mismatched names are returned sorted.
+pub fn validate_centroids_against_classes(
+    centroids: &HashMap<String, Point>,
+    class_names: &[String],
+) -> Vec<String> {
+    let mut mismatched: Vec<String> = centroids
+        .keys()
+        .filter(|key| !class_names.contains(key))
+        .chain(
+            class_names
+                .iter()
+                .filter(|name| !centroids.contains_key(*name)),
+        )
+        .cloned()
+        .collect();
+    mismatched.sort();
+    mismatched.dedup();
+    mismatched
+}
+
+/// Assigns a point to a cell of the chart's grid.
+///
+/// The time series sections of the chart are laid out on a grid, with columns for timestamps and
+/// rows for values. Columns are given as (label, x) and rows as (label, y). The nearest column
+/// and the nearest row are picked independently, and their labels are returned as
+/// (column_label, row_label). Returns None if either set of centroids is empty.
+pub fn assign_to_grid(
+    det_center: Point,
+    column_centroids: &[(String, f32)],
+    row_centroids: &[(String, f32)],
+) -> Option<(String, String)> {
+    let column_label = find_nearest_label(det_center.x, column_centroids)?;
+    let row_label = find_nearest_label(det_center.y, row_centroids)?;
+    Some((column_label.clone(), row_label.clone()))
+}
+
+/// Groups detections by the label of the row nearest to the y coordinate of their center.
+pub fn group_by_row<T: BoundingBoxGeometry + Display + Clone>(
+    dets: &[Detection<T>],
+    row_centroids: &[(String, f32)],
+) -> HashMap<String, Vec<Detection<T>>> {
+    let mut rows: HashMap<String, Vec<Detection<T>>> = HashMap::new();
+    for det in dets {
+        if let Some(label) = find_nearest_label(det.annotation.center().1, row_centroids) {
+            rows.entry(label.clone()).or_default().push(det.clone());
+        }
+    }
+    rows
+}
+
+/// Groups detections by the label of the column nearest to the x coordinate of their center.
+pub fn group_by_column<T: BoundingBoxGeometry + Display + Clone>(
+    dets: &[Detection<T>],
+    column_centroids: &[(String, f32)],
+) -> HashMap<String, Vec<Detection<T>>> {
+    let mut columns: HashMap<String, Vec<Detection<T>>> = HashMap::new();
+    for det in dets {
+        if let Some(label) = find_nearest_label(det.annotation.center().0, column_centroids) {
+            columns.entry(label.clone()).or_default().push(det.clone());
+        }
+    }
+    columns
+}
+
+/// How resolve_one_per_centroid picks between detections assigned to the same centroid.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ResolvePolicy {
+    /// Keeps the detection the model was most confident in.
+    HighestConfidence,
+    /// Keeps the detection whose center is closest to the centroid.
+    NearestDistance,
+}
+
+/// Keeps at most one detection per centroid, for fields that can only hold a single value.
+///
+/// Each detection is assigned to its nearest centroid by Euclidean distance, and when several
+/// land on the same centroid the policy picks the one to keep. Centroids without a detection are
+/// left out of the map.
+pub fn resolve_one_per_centroid(
+    dets: Vec<Detection<BoundingBox>>,
+    centroids: &HashMap<String, Point>,
+    policy: ResolvePolicy,
+) -> HashMap<String, Detection<BoundingBox>> {
+    let mut resolved: HashMap<String, (f32, Detection<BoundingBox>)> = HashMap::new();
+    for det in dets {
+        let (x, y) = det.annotation.center();
+        let center = Point { x, y };
+        let Some(name) = find_min_distance_key(center, centroids, DistanceMetric::Euclidean) else {
+            continue;
+        };
+        let distance = center.distance(&centroids[&name], DistanceMetric::Euclidean);
+        let is_better = |kept: &(f32, Detection<BoundingBox>)| match policy {
+            ResolvePolicy::HighestConfidence => det.confidence > kept.1.confidence,
+            ResolvePolicy::NearestDistance => distance < kept.0,
+        };
+        match resolved.get(&name) {
+            Some(kept) if !is_better(kept) => {}
+            _ => {
+                resolved.insert(name, (distance, det));
+            }
+        }
+    }
+    resolved
+        .into_iter()
+        .map(|(name, (_, det))| (name, det))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_testing_columns() -> Vec<(String, f32)> {
+        vec![
+            ("08:00".to_string(), 100_f32),
+            ("08:05".to_string(), 120_f32),
+            ("08:10".to_string(), 140_f32),
+        ]
+    }
+
+    fn create_testing_rows() -> Vec<(String, f32)> {
+        vec![
+            ("120".to_string(), 50_f32),
+            ("110".to_string(), 60_f32),
+            ("100".to_string(), 70_f32),
+        ]
+    }
+
+    #[test]
+    fn assign_near_intersection() {
+        let columns = create_testing_columns();
+        let rows = create_testing_rows();
+        let assignment = assign_to_grid(Point { x: 122.5, y: 68.0 }, &columns, &rows);
+        assert_eq!(assignment, Some(("08:05".to_string(), "100".to_string())));
+    }
+
+    #[test]
+    fn assign_outside_grid_snaps_to_edge() {
+        let columns = create_testing_columns();
+        let rows = create_testing_rows();
+        let assignment = assign_to_grid(Point { x: 0.0, y: 500.0 }, &columns, &rows);
+        assert_eq!(assignment, Some(("08:00".to_string(), "100".to_string())));
+    }
+
+    #[test]
+    fn assign_without_columns() {
+        let rows = create_testing_rows();
+        assert_eq!(assign_to_grid(Point { x: 0.0, y: 0.0 }, &[], &rows), None);
+    }
+
+    #[test]
+    fn find_min_distance_key_depends_on_metric() {
+        let centroids = HashMap::from([
+            ("diagonal".to_string(), Point { x: 3.0, y: 3.0 }),
+            ("straight".to_string(), Point { x: 5.0, y: 0.0 }),
+        ]);
+        let point = Point { x: 0.0, y: 0.0 };
+        assert_eq!(
+            find_min_distance_key(point, &centroids, DistanceMetric::Euclidean),
+            Some("diagonal".to_string())
+        );
+        assert_eq!(
+            find_min_distance_key(point, &centroids, DistanceMetric::Manhattan),
+            Some("straight".to_string())
+        );
+    }
+
+    #[test]
+    fn validate_centroids_reports_mismatched_keys() {
+        let centroids = HashMap::from([
+            ("systolic".to_string(), Point { x: 0.0, y: 0.0 }),
+            ("diastolic".to_string(), Point { x: 1.0, y: 0.0 }),
+            ("heartrate".to_string(), Point { x: 2.0, y: 0.0 }),
+        ]);
+        let class_names = vec![
+            "systolic".to_string(),
+            "diastolic".to_string(),
+            "heart_rate".to_string(),
+        ];
+        assert_eq!(
+            validate_centroids_against_classes(&centroids, &class_names),
+            vec!["heart_rate".to_string(), "heartrate".to_string()]
+        );
+        assert_eq!(
+            validate_centroids_against_classes(&centroids, &class_names[..2]),
+            vec!["heartrate".to_string()]
+        );
+    }
+
+    #[test]
+    fn resolve_one_per_centroid_policy_picks_winner() {
+        let centroids = HashMap::from([
+            ("tube_size".to_string(), Point { x: 10.0, y: 10.0 }),
+            ("cuff_pressure".to_string(), Point { x: 100.0, y: 10.0 }),
+        ]);
+        let create_det = |x: f32, category: &str, confidence: f32| Detection {
+            annotation: BoundingBox::new(x - 2.0, 8.0, x + 2.0, 12.0, category.to_string())
+                .unwrap(),
+            confidence,
+        };
+        let dets = vec![
+            create_det(11.0, "7.0", 0.6),
+            create_det(16.0, "7.5", 0.9),
+            create_det(99.0, "20", 0.8),
+        ];
+
+        let by_confidence =
+            resolve_one_per_centroid(dets.clone(), &centroids, ResolvePolicy::HighestConfidence);
+        assert_eq!(by_confidence.len(), 2);
+        assert_eq!(by_confidence["tube_size"].annotation.category(), "7.5");
+        assert_eq!(by_confidence["cuff_pressure"].annotation.category(), "20");
+
+        let by_distance =
+            resolve_one_per_centroid(dets, &centroids, ResolvePolicy::NearestDistance);
+        assert_eq!(by_distance["tube_size"].annotation.category(), "7.0");
+    }
+}
@@ -0,0 +1,244 @@
+use crate::digitization::time_axis::Time;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// The values read from one page of an intraoperative record.
+///
+/// Timestamps are "HH:MM" labels of the chart's time columns, as used by the time series
+/// extraction functions.
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub struct IntraoperativeChart {
+    /// Long surgeries span several pages, numbered from 1.
+    pub page_num: u32,
+    pub anesthesia_start: Option<Time>,
+    pub anesthesia_end: Option<Time>,
+    pub systolic: HashMap<String, u32>,
+    pub diastolic: HashMap<String, u32>,
+    pub heart_rate: HashMap<String, u32>,
+    pub spo2: HashMap<String, u32>,
+    pub checkboxes: HashMap<String, bool>,
+}
+
+/// A time series value dropped while merging pages, because an earlier page already had a value
+/// at its timestamp.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PageOverlap {
+    pub series: &'static str,
+    pub timestamp: String,
+    pub page_num: u32,
+}
+
+/// Combines the pages of one surgery into a single chart with a continuous timeline.
+///
+/// The anesthesia start is the earliest one recorded on any page and the anesthesia end is the
+/// latest. Times are compared by the minutes elapsed since the first anesthesia time in page_num
+/// order, so a surgery running past midnight still ends after it starts. The time series are
+/// concatenated, and a timestamp appearing on more than one page keeps its value from the
+/// earlier page, with the dropped values returned alongside the chart. A checkbox is checked if
+/// it is checked on any page.
+///
+/// The merged chart takes the page_num of the first page, and no pages gives an empty chart.
+pub fn merge_intraop_pages(
+    mut pages: Vec<IntraoperativeChart>,
+) -> (IntraoperativeChart, Vec<PageOverlap>) {
+    pages.sort_by_key(|page| page.page_num);
+    let reference = pages
+        .iter()
+        .find_map(|page| page.anesthesia_start.or(page.anesthesia_end));
+    let elapsed = |time: &Time| reference.map_or(0, |reference| reference.minutes_until(time));
+    let mut overlaps: Vec<PageOverlap> = Vec::new();
+    let mut pages = pages.into_iter();
+    let Some(mut merged) = pages.next() else {
+        return (IntraoperativeChart::default(), overlaps);
+    };
+    for page in pages {
+        merged.anesthesia_start = [merged.anesthesia_start, page.anesthesia_start]
+            .into_iter()
+            .flatten()
+            .min_by_key(elapsed);
+        merged.anesthesia_end = [merged.anesthesia_end, page.anesthesia_end]
+            .into_iter()
+            .flatten()
+            .max_by_key(elapsed);
+        merge_time_series(
+            &mut merged.systolic,
+            page.systolic,
+            "systolic",
+            page.page_num,
+            &mut overlaps,
+        );
+        merge_time_series(
+            &mut merged.diastolic,
+            page.diastolic,
+            "diastolic",
+            page.page_num,
+            &mut overlaps,
+        );
+        merge_time_series(
+            &mut merged.heart_rate,
+            page.heart_rate,
+            "heart rate",
+            page.page_num,
+            &mut overlaps,
+        );
+        merge_time_series(
+            &mut merged.spo2,
+            page.spo2,
+            "SpO2",
+            page.page_num,
+            &mut overlaps,
+        );
+        for (name, is_checked) in page.checkboxes {
+            *merged.checkboxes.entry(name).or_insert(false) |= is_checked;
+        }
+    }
+    (merged, overlaps)
+}
+
+/// Adds a page's series to the merged one, recording values at timestamps already present.
+fn merge_time_series(
+    merged: &mut HashMap<String, u32>,
+    page_series: HashMap<String, u32>,
+    series: &'static str,
+    page_num: u32,
+    overlaps: &mut Vec<PageOverlap>,
+) {
+    for (timestamp, value) in page_series {
+        if merged.contains_key(&timestamp) {
+            overlaps.push(PageOverlap {
+                series,
+                timestamp,
+                page_num,
+            });
+            continue;
+        }
+        merged.insert(timestamp, value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merge_two_pages_with_disjoint_timestamps() {
+        let first_page = IntraoperativeChart {
+            page_num: 1,
+            anesthesia_start: Some(Time { hour: 8, minute: 0 }),
+            anesthesia_end: None,
+            systolic: HashMap::from([("08:00".to_string(), 120), ("08:05".to_string(), 118)]),
+            heart_rate: HashMap::from([("08:00".to_string(), 70)]),
+            checkboxes: HashMap::from([
+                ("intubated".to_string(), true),
+                ("eyes_taped".to_string(), false),
+            ]),
+            ..Default::default()
+        };
+        let second_page = IntraoperativeChart {
+            page_num: 2,
+            anesthesia_start: None,
+            anesthesia_end: Some(Time {
+                hour: 10,
+                minute: 30,
+            }),
+            systolic: HashMap::from([("10:00".to_string(), 115)]),
+            heart_rate: HashMap::from([("10:00".to_string(), 72)]),
+            checkboxes: HashMap::from([("eyes_taped".to_string(), true)]),
+            ..Default::default()
+        };
+        // Pages may be given out of order.
+        let (merged, overlaps) = merge_intraop_pages(vec![second_page, first_page]);
+        assert!(overlaps.is_empty());
+        assert_eq!(merged.page_num, 1);
+        assert_eq!(merged.anesthesia_start, Some(Time { hour: 8, minute: 0 }));
+        assert_eq!(
+            merged.anesthesia_end,
+            Some(Time {
+                hour: 10,
+                minute: 30
+            })
+        );
+        assert_eq!(
+            merged.systolic,
+            HashMap::from([
+                ("08:00".to_string(), 120),
+                ("08:05".to_string(), 118),
+                ("10:00".to_string(), 115),
+            ])
+        );
+        assert_eq!(
+            merged.heart_rate,
+            HashMap::from([("08:00".to_string(), 70), ("10:00".to_string(), 72)])
+        );
+        assert_eq!(
+            merged.checkboxes,
+            HashMap::from([
+                ("intubated".to_string(), true),
+                ("eyes_taped".to_string(), true),
+            ])
+        );
+        assert_eq!(
+            merge_intraop_pages(vec![]),
+            (IntraoperativeChart::default(), Vec::new())
+        );
+    }
+
+    #[test]
+    fn merge_overnight_pages_with_overlap() {
+        let first_page = IntraoperativeChart {
+            page_num: 1,
+            anesthesia_start: Some(Time {
+                hour: 22,
+                minute: 30,
+            }),
+            anesthesia_end: Some(Time {
+                hour: 23,
+                minute: 55,
+            }),
+            systolic: HashMap::from([("23:55".to_string(), 120)]),
+            ..Default::default()
+        };
+        // The second page repeats the last column of the first, and its start was misread as
+        // later than the first page's.
+        let second_page = IntraoperativeChart {
+            page_num: 2,
+            anesthesia_start: Some(Time {
+                hour: 23,
+                minute: 0,
+            }),
+            anesthesia_end: Some(Time {
+                hour: 1,
+                minute: 15,
+            }),
+            systolic: HashMap::from([("23:55".to_string(), 110), ("00:00".to_string(), 118)]),
+            ..Default::default()
+        };
+        let (merged, overlaps) = merge_intraop_pages(vec![first_page, second_page]);
+        assert_eq!(
+            merged.anesthesia_start,
+            Some(Time {
+                hour: 22,
+                minute: 30
+            })
+        );
+        assert_eq!(
+            merged.anesthesia_end,
+            Some(Time {
+                hour: 1,
+                minute: 15
+            })
+        );
+        assert_eq!(
+            merged.systolic,
+            HashMap::from([("23:55".to_string(), 120), ("00:00".to_string(), 118)])
+        );
+        assert_eq!(
+            overlaps,
+            vec![PageOverlap {
+                series: "systolic",
+                timestamp: "23:55".to_string(),
+                page_num: 2,
+            }]
+        );
+    }
+}
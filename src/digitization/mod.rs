@@ -0,0 +1,9 @@
+pub mod extract_blood_pressure;
+pub mod extract_checkboxes;
+pub mod extract_medications;
+pub mod extract_physiological;
+pub mod grid_assignment;
+pub mod intraoperative_chart;
+pub mod page_viability;
+pub mod read_boxed_digits;
+pub mod time_axis;
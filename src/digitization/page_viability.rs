@@ -0,0 +1,51 @@
+use crate::annotations::bounding_box::{BoundingBox, BoundingBoxGeometry};
+use crate::annotations::detection::Detection;
+use std::collections::HashSet;
+
+/// Checks whether enough landmarks were found on a page for registration to be worthwhile.
+///
+/// Blank or badly degraded scans yield almost no landmarks, and registering against those
+/// produces garbage. Landmarks are counted by distinct category, so a model firing twice on one
+/// landmark doesn't count as two.
+pub fn is_page_viable(landmark_dets: &[Detection<BoundingBox>], min_landmarks: usize) -> bool {
+    let found: HashSet<&String> = landmark_dets
+        .iter()
+        .map(|det| det.annotation.category())
+        .collect();
+    found.len() >= min_landmarks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_testing_landmarks(categories: &[&str]) -> Vec<Detection<BoundingBox>> {
+        categories
+            .iter()
+            .enumerate()
+            .map(|(ix, category)| Detection {
+                annotation: BoundingBox::new(
+                    ix as f32 * 10_f32,
+                    0_f32,
+                    ix as f32 * 10_f32 + 5_f32,
+                    5_f32,
+                    category.to_string(),
+                )
+                .unwrap(),
+                confidence: 0.9_f32,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn near_empty_page_is_not_viable() {
+        let dets = create_testing_landmarks(&["time_header", "time_header"]);
+        assert!(!is_page_viable(&dets, 3));
+    }
+
+    #[test]
+    fn normal_page_is_viable() {
+        let dets = create_testing_landmarks(&["time_header", "mmhg_header", "checkbox_header"]);
+        assert!(is_page_viable(&dets, 3));
+    }
+}
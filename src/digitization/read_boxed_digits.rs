@@ -0,0 +1,85 @@
+use crate::annotations::bounding_box::{BoundingBox, BoundingBoxGeometry};
+use crate::annotations::detection::Detection;
+
+/// The intersection over min area above which two digit boxes are considered the same digit.
+const DUPLICATE_DIGIT_THRESHOLD: f32 = 0.5;
+
+/// Assembles single digit detections into a multi-digit number.
+///
+/// Handwritten numbers on the chart are written in boxes, one digit per box. The digit model
+/// detects each digit separately, with the digit as its category ("0" through "9"). The digits
+/// are read left to right by the x coordinate of their box centers.
+///
+/// When two digit boxes mostly overlap (by intersection over min area, so a small box inside a
+/// larger one counts), the model has fired twice on the same digit and only the higher confidence
+/// detection is kept.
+///
+/// Returns None if there are no digits, if any category is not a single digit, or if the number
+/// does not fit in a u32.
+pub fn assemble_number(digit_detections: &[Detection<BoundingBox>]) -> Option<u32> {
+    let mut by_confidence: Vec<&Detection<BoundingBox>> = digit_detections.iter().collect();
+    by_confidence.sort_by(|a, b| b.confidence.total_cmp(&a.confidence));
+    let mut digits: Vec<&Detection<BoundingBox>> = Vec::new();
+    for det in by_confidence {
+        let is_duplicate = digits.iter().any(|kept| {
+            kept.annotation.intersection_over_min_area(&det.annotation) > DUPLICATE_DIGIT_THRESHOLD
+        });
+        if !is_duplicate {
+            digits.push(det);
+        }
+    }
+    if digits.is_empty() {
+        return None;
+    }
+    digits.sort_by(|a, b| a.annotation.center().0.total_cmp(&b.annotation.center().0));
+
+    let mut number_string = String::new();
+    for det in digits {
+        let category = det.annotation.category();
+        if category.len() != 1 || !category.chars().all(|c| c.is_ascii_digit()) {
+            return None;
+        }
+        number_string.push_str(category);
+    }
+    number_string.parse::<u32>().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn digit(left: f32, category: &str, confidence: f32) -> Detection<BoundingBox> {
+        Detection {
+            annotation: BoundingBox::new(left, 0_f32, left + 10_f32, 20_f32, category.to_string())
+                .unwrap(),
+            confidence,
+        }
+    }
+
+    #[test]
+    fn assemble_two_digits() {
+        let dets = vec![digit(12_f32, "2", 0.9), digit(0_f32, "4", 0.8)];
+        assert_eq!(assemble_number(&dets), Some(42));
+    }
+
+    #[test]
+    fn assemble_drops_duplicate_digit() {
+        let dets = vec![
+            digit(0_f32, "4", 0.8),
+            digit(1_f32, "1", 0.4),
+            digit(12_f32, "2", 0.9),
+        ];
+        assert_eq!(assemble_number(&dets), Some(42));
+    }
+
+    #[test]
+    fn assemble_rejects_non_digit() {
+        let dets = vec![digit(0_f32, "4", 0.8), digit(12_f32, "x", 0.9)];
+        assert_eq!(assemble_number(&dets), None);
+    }
+
+    #[test]
+    fn assemble_empty() {
+        assert_eq!(assemble_number(&[]), None);
+    }
+}
@@ -0,0 +1,175 @@
+use crate::annotations::bounding_box::{BoundingBox, BoundingBoxGeometry};
+use crate::annotations::detection::Detection;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// A time of day on the chart, with minute resolution.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub struct Time {
+    pub hour: u32,
+    pub minute: u32,
+}
+
+impl Time {
+    /// Adds minutes to the time, wrapping past midnight since cases can run overnight.
+    pub fn add_minutes(&self, minutes: u32) -> Time {
+        let total = (self.hour * 60 + self.minute + minutes) % (24 * 60);
+        Time {
+            hour: total / 60,
+            minute: total % 60,
+        }
+    }
+
+    /// Counts the minutes from this time until other, wrapping past midnight.
+    pub fn minutes_until(&self, other: &Time) -> u32 {
+        let minutes = |time: &Time| time.hour * 60 + time.minute;
+        (minutes(other) + 24 * 60 - minutes(self)) % (24 * 60)
+    }
+}
+
+impl fmt::Display for Time {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:02}:{:02}", self.hour, self.minute)
+    }
+}
+
+/// Labels the chart's time columns from its detected vertical gridlines.
+///
+/// Gridlines are sorted from left to right, and the leftmost is start_time, with each following
+/// gridline minutes_per_column later. The result pairs each "HH:MM" label with the x coordinate
+/// of its gridline's center, as the column centroids for assign_to_grid.
+pub fn build_time_axis(
+    gridline_dets: &[Detection<BoundingBox>],
+    start_time: Time,
+    minutes_per_column: u32,
+) -> Vec<(String, f32)> {
+    let mut xs: Vec<f32> = gridline_dets
+        .iter()
+        .map(|det| det.annotation.center().0)
+        .collect();
+    xs.sort_by(f32::total_cmp);
+    xs.into_iter()
+        .enumerate()
+        .map(|(column_ix, x)| {
+            let time = start_time.add_minutes(column_ix as u32 * minutes_per_column);
+            (time.to_string(), x)
+        })
+        .collect()
+}
+
+/// Labels the chart's time columns by spreading the anesthesia start to end interval over them.
+///
+/// The leftmost column is start and the rightmost is end, and the columns in between get times
+/// linearly interpolated from their x positions, rounded to the minute. An end before start is
+/// taken to be on the next day. The result pairs each "HH:MM" label with its x coordinate, sorted
+/// from left to right, as the column centroids for assign_to_grid.
+pub fn fit_time_axis(start: Time, end: Time, column_xs: &[f32]) -> Vec<(String, f32)> {
+    let mut xs: Vec<f32> = column_xs.to_vec();
+    xs.sort_by(f32::total_cmp);
+    let (Some(&first_x), Some(&last_x)) = (xs.first(), xs.last()) else {
+        return Vec::new();
+    };
+    let span = last_x - first_x;
+    let duration = start.minutes_until(&end) as f32;
+    xs.into_iter()
+        .map(|x| {
+            let fraction = if span > 0.0 {
+                (x - first_x) / span
+            } else {
+                0.0
+            };
+            let time = start.add_minutes((fraction * duration).round() as u32);
+            (time.to_string(), x)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_testing_gridline(x: f32) -> Detection<BoundingBox> {
+        Detection {
+            annotation: BoundingBox::new(
+                x - 1_f32,
+                0_f32,
+                x + 1_f32,
+                100_f32,
+                "gridline".to_string(),
+            )
+            .unwrap(),
+            confidence: 0.9_f32,
+        }
+    }
+
+    #[test]
+    fn build_time_axis_evenly_spaced_gridlines() {
+        let dets = vec![
+            create_testing_gridline(140_f32),
+            create_testing_gridline(100_f32),
+            create_testing_gridline(120_f32),
+        ];
+        let axis = build_time_axis(&dets, Time { hour: 8, minute: 0 }, 5);
+        assert_eq!(
+            axis,
+            vec![
+                ("08:00".to_string(), 100_f32),
+                ("08:05".to_string(), 120_f32),
+                ("08:10".to_string(), 140_f32),
+            ]
+        );
+    }
+
+    #[test]
+    fn time_wraps_past_midnight() {
+        let time = Time {
+            hour: 23,
+            minute: 55,
+        };
+        assert_eq!(time.add_minutes(10), Time { hour: 0, minute: 5 });
+    }
+
+    #[test]
+    fn fit_time_axis_fifteen_minute_columns() {
+        let axis = fit_time_axis(
+            Time { hour: 8, minute: 0 },
+            Time { hour: 9, minute: 0 },
+            &[300.0, 100.0, 200.0, 150.0, 250.0],
+        );
+        assert_eq!(
+            axis,
+            vec![
+                ("08:00".to_string(), 100_f32),
+                ("08:15".to_string(), 150_f32),
+                ("08:30".to_string(), 200_f32),
+                ("08:45".to_string(), 250_f32),
+                ("09:00".to_string(), 300_f32),
+            ]
+        );
+    }
+
+    #[test]
+    fn fit_time_axis_across_midnight() {
+        let axis = fit_time_axis(
+            Time {
+                hour: 23,
+                minute: 30,
+            },
+            Time {
+                hour: 0,
+                minute: 30,
+            },
+            &[0.0, 10.0, 20.0],
+        );
+        let labels: Vec<&str> = axis.iter().map(|(label, _)| label.as_str()).collect();
+        assert_eq!(labels, vec!["23:30", "00:00", "00:30"]);
+        assert!(
+            fit_time_axis(
+                Time { hour: 8, minute: 0 },
+                Time { hour: 9, minute: 0 },
+                &[]
+            )
+            .is_empty()
+        );
+    }
+}
@@ -0,0 +1,160 @@
+use crate::annotations::bounding_box::{BoundingBox, BoundingBoxGeometry};
+use crate::annotations::detection::Detection;
+use crate::annotations::point::Point;
+use image::{Rgb, RgbImage};
+use imageproc::geometric_transformations::{Interpolation, rotate};
+
+/// Boxes at least this many times wider than they are tall are taken to be horizontal gridlines.
+/// A thin line stays this elongated up to roughly 14 degrees of skew.
+const MIN_HORIZONTAL_GRIDLINE_ASPECT_RATIO: f32 = 4.0;
+
+/// Estimates the rotation that levels the chart's horizontal gridlines.
+///
+/// An axis aligned box around a tilted line is the same whichever way the line tilts, so the
+/// angle is found from the gridlines' relative positions instead. The horizontal gridlines of a
+/// chart span the same columns, so their centers lie on a line perpendicular to them, and a
+/// least squares fit of the centers' x against their y gives its tilt. Boxes that aren't
+/// elongated enough to be horizontal gridlines are ignored.
+///
+/// The result is in radians, in the convention of Point::rotate_around, so rotating points by it
+/// deskews them. Returns 0 if fewer than two gridlines at different heights are found.
+pub fn estimate_skew_angle(gridline_dets: &[Detection<BoundingBox>]) -> f32 {
+    let centers: Vec<(f32, f32)> = gridline_dets
+        .iter()
+        .filter(|det| det.annotation.aspect_ratio() >= MIN_HORIZONTAL_GRIDLINE_ASPECT_RATIO)
+        .map(|det| det.annotation.center())
+        .collect();
+    if centers.len() < 2 {
+        return 0.0;
+    }
+    let num_centers = centers.len() as f32;
+    let mean_x = centers.iter().map(|c| c.0).sum::<f32>() / num_centers;
+    let mean_y = centers.iter().map(|c| c.1).sum::<f32>() / num_centers;
+    let covariance: f32 = centers
+        .iter()
+        .map(|(x, y)| (x - mean_x) * (y - mean_y))
+        .sum();
+    let y_variance: f32 = centers.iter().map(|(_, y)| (y - mean_y).powi(2)).sum();
+    if y_variance <= f32::EPSILON {
+        return 0.0;
+    }
+    (covariance / y_variance).atan()
+}
+
+/// Rotates an image and the detections made on it together, for instance to deskew a scan.
+///
+/// The rotation follows Point::rotate_around, so a skew angle from estimate_skew_angle can be
+/// passed straight in. Each box becomes the axis aligned envelope of its rotated corners, which
+/// is larger than the original unless the angle is a multiple of 90 degrees. The image keeps its
+/// size, with uncovered pixels set to black.
+pub fn rotate_image_and_detections(
+    image: RgbImage,
+    dets: Vec<Detection<BoundingBox>>,
+    radians: f32,
+    center: Point,
+) -> (RgbImage, Vec<Detection<BoundingBox>>) {
+    // imageproc samples pixel (i, j) at (i, j), while a box edge at i is the pixel's left edge,
+    // so the pixel centers are half a pixel further along.
+    let rotated_image = rotate(
+        &image,
+        (center.x - 0.5, center.y - 0.5),
+        radians,
+        Interpolation::Bilinear,
+        Rgb([0, 0, 0]),
+    );
+    let rotated_dets = dets
+        .into_iter()
+        .map(|det| Detection {
+            annotation: rotated_envelope(&det.annotation, center, radians),
+            confidence: det.confidence,
+        })
+        .collect();
+    (rotated_image, rotated_dets)
+}
+
+fn rotated_envelope(b: &BoundingBox, center: Point, radians: f32) -> BoundingBox {
+    let corners = b
+        .corners()
+        .map(|corner| corner.rotate_around(center, radians));
+    let xs = corners.map(|corner| corner.x);
+    let ys = corners.map(|corner| corner.y);
+    BoundingBox::new(
+        xs.into_iter().fold(f32::INFINITY, f32::min),
+        ys.into_iter().fold(f32::INFINITY, f32::min),
+        xs.into_iter().fold(f32::NEG_INFINITY, f32::max),
+        ys.into_iter().fold(f32::NEG_INFINITY, f32::max),
+        b.category().clone(),
+    )
+    .expect("The envelope's minimums never exceed its maximums.")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_rotated_gridline(y: f32, center: Point, radians: f32) -> Detection<BoundingBox> {
+        let gridline =
+            BoundingBox::new(50.0, y - 1.0, 550.0, y + 1.0, "gridline".to_string()).unwrap();
+        Detection {
+            annotation: rotated_envelope(&gridline, center, radians),
+            confidence: 0.9,
+        }
+    }
+
+    #[test]
+    fn estimate_skew_angle_recovers_rotation() {
+        let center = Point { x: 300.0, y: 175.0 };
+        for skew in [0.05_f32, -0.08] {
+            let mut dets: Vec<Detection<BoundingBox>> = [100.0, 150.0, 200.0, 250.0]
+                .into_iter()
+                .map(|y| create_rotated_gridline(y, center, skew))
+                .collect();
+            // A vertical gridline, which should be ignored.
+            dets.push(Detection {
+                annotation: BoundingBox::new(299.0, 80.0, 301.0, 270.0, "gridline".to_string())
+                    .unwrap(),
+                confidence: 0.9,
+            });
+            let angle = estimate_skew_angle(&dets);
+            assert!((angle + skew).abs() < 1e-3, "{} for skew {}", angle, skew);
+        }
+    }
+
+    #[test]
+    fn estimate_skew_angle_needs_two_gridlines() {
+        let center = Point { x: 300.0, y: 175.0 };
+        assert_eq!(
+            estimate_skew_angle(&[create_rotated_gridline(100.0, center, 0.05)]),
+            0.0
+        );
+    }
+
+    #[test]
+    fn rotate_image_and_detections_quarter_turn() {
+        let mut image = RgbImage::from_pixel(20, 20, Rgb([0, 0, 0]));
+        for x in 2..6 {
+            for y in 8..12 {
+                image.put_pixel(x, y, Rgb([255, 255, 255]));
+            }
+        }
+        let dets = vec![Detection {
+            annotation: BoundingBox::new(2.0, 8.0, 6.0, 12.0, "mark".to_string()).unwrap(),
+            confidence: 0.9,
+        }];
+        let (rotated_image, rotated_dets) = rotate_image_and_detections(
+            image,
+            dets,
+            std::f32::consts::FRAC_PI_2,
+            Point { x: 10.0, y: 10.0 },
+        );
+        let rotated_box = &rotated_dets[0].annotation;
+        assert!(rotated_box.approx_eq(
+            &BoundingBox::new(8.0, 2.0, 12.0, 6.0, "mark".to_string()).unwrap(),
+            1e-4
+        ));
+        for (x, y, pixel) in rotated_image.enumerate_pixels() {
+            let inside_box = (8..12).contains(&x) && (2..6).contains(&y);
+            assert_eq!(pixel[0] > 127, inside_box, "pixel ({}, {})", x, y);
+        }
+    }
+}
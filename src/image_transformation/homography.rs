@@ -1,20 +1,55 @@
 use crate::annotations::point::Point;
 use image::{Rgb, RgbImage};
-use imageproc::geometric_transformations::{Interpolation, Projection, warp};
+use imageproc::geometric_transformations::{Interpolation, Projection, warp, warp_into};
 
+/// Warps an image with a homography, using bilinear interpolation and a black fill.
+///
+/// See homography_transform_image_with for choosing the interpolation and fill.
 pub fn homography_transform_image(
     image: RgbImage,
     source_points: Vec<Point>,
     destination_points: Vec<Point>,
 ) -> Option<RgbImage> {
-    let proj: Projection = compute_homography_projection(source_points, destination_points)?;
-    let default_pixel_color: Rgb<u8> = Rgb([0, 0, 0]);
-    Some(warp(
-        &image,
-        &proj,
+    homography_transform_image_with(
+        image,
+        source_points,
+        destination_points,
         Interpolation::Bilinear,
-        default_pixel_color,
-    ))
+        Rgb([0, 0, 0]),
+    )
+}
+
+/// Warps an image with a homography.
+///
+/// Pixels that map from outside of the source image are set to default_pixel_color. For binarized
+/// scans, Interpolation::Nearest avoids introducing gray pixels along the edges of the ink.
+pub fn homography_transform_image_with(
+    image: RgbImage,
+    source_points: Vec<Point>,
+    destination_points: Vec<Point>,
+    interpolation: Interpolation,
+    default_pixel_color: Rgb<u8>,
+) -> Option<RgbImage> {
+    let proj: Projection = compute_homography_projection(source_points, destination_points)?;
+    Some(warp(&image, &proj, interpolation, default_pixel_color))
+}
+
+/// Warps an image with a projection into a fresh canvas of (out_width, out_height).
+///
+/// When dewarping a photo of a chart, the output should have the clean chart's dimensions rather
+/// than the photo's. Pixels of the canvas that map from outside of the source image are set to
+/// fill.
+pub fn homography_transform_image_sized(
+    image: &RgbImage,
+    proj: &Projection,
+    out_width: u32,
+    out_height: u32,
+    interpolation: Interpolation,
+    fill: Rgb<u8>,
+) -> RgbImage {
+    let mut output = RgbImage::new(out_width, out_height);
+    warp_into(image, proj, interpolation, fill, &mut output);
+    output
 }
 
 /// Computes the homography transformation.
@@ -24,7 +59,8 @@ pub fn homography_transform_image(
 /// Our project uses the homography to transform an off-angle photo into
 /// the coordinates of a perfect, scan-like version of the image.
 ///
-/// source_points and destination_points must be exactly four points.
+/// source_points and destination_points must be exactly four points. Returns None if three of
+/// the points on either side are collinear or nearly so, since the fit is then unstable.
 pub fn compute_homography_projection(
     source_points: Vec<Point>,
     destination_points: Vec<Point>,
@@ -48,5 +84,204 @@ pub fn compute_homography_projection(
         .unwrap_or_else(|v: Vec<(f32, f32)>| {
             panic!("Expected Vec of length {} but found {}.", 4, v.len())
         });
+    if is_nearly_collinear(&from_points) || is_nearly_collinear(&to_points) {
+        return None;
+    }
     Projection::from_control_points(from_points, to_points)
 }
+
+/// How flat a triangle may be, as twice its area over its longest side squared, before the
+/// points are treated as collinear.
+const MIN_TRIANGLE_FLATNESS: f32 = 1e-3;
+
+/// Checks whether any three of the four control points are collinear or nearly so.
+fn is_nearly_collinear(points: &[(f32, f32); 4]) -> bool {
+    let triangles = [[0, 1, 2], [0, 1, 3], [0, 2, 3], [1, 2, 3]];
+    triangles.iter().any(|&[i, j, k]| {
+        let (ax, ay) = points[i];
+        let (bx, by) = points[j];
+        let (cx, cy) = points[k];
+        let twice_area = ((bx - ax) * (cy - ay) - (by - ay) * (cx - ax)).abs();
+        let longest_side_squared = [(bx - ax, by - ay), (cx - ax, cy - ay), (cx - bx, cy - by)]
+            .iter()
+            .map(|(dx, dy)| dx * dx + dy * dy)
+            .fold(0_f32, f32::max);
+        longest_side_squared == 0.0 || twice_area / longest_side_squared < MIN_TRIANGLE_FLATNESS
+    })
+}
+
+/// Computes the similarity transformation that best maps the source points onto the destination.
+///
+/// A similarity is a rotation, a uniform scale, and a translation. It is fit by least squares
+/// over every pair of points, so any number of points (at least two) can be used. Because it has
+/// only four degrees of freedom it stays well behaved when the points are nearly collinear,
+/// which makes it a safe fallback when the homography cannot be computed.
+///
+/// Returns None if the point counts differ, fewer than two points are given, or all of the
+/// source points coincide.
+pub fn compute_similarity_transform(
+    source_points: Vec<Point>,
+    destination_points: Vec<Point>,
+) -> Option<Projection> {
+    if source_points.len() != destination_points.len() || source_points.len() < 2 {
+        return None;
+    }
+    let num_points = source_points.len() as f32;
+    let source_mean_x = source_points.iter().map(|p| p.x).sum::<f32>() / num_points;
+    let source_mean_y = source_points.iter().map(|p| p.y).sum::<f32>() / num_points;
+    let destination_mean_x = destination_points.iter().map(|p| p.x).sum::<f32>() / num_points;
+    let destination_mean_y = destination_points.iter().map(|p| p.y).sum::<f32>() / num_points;
+
+    // Treating the points as complex numbers, the transform is z -> a * z + t, where a holds the
+    // scale and rotation. The least squares a over the centered points is sum(conj(s) * d) / sum(|s|^2).
+    let mut real: f32 = 0.0;
+    let mut imaginary: f32 = 0.0;
+    let mut source_norm: f32 = 0.0;
+    for (source, destination) in source_points.iter().zip(destination_points.iter()) {
+        let (sx, sy) = (source.x - source_mean_x, source.y - source_mean_y);
+        let (dx, dy) = (
+            destination.x - destination_mean_x,
+            destination.y - destination_mean_y,
+        );
+        real += sx * dx + sy * dy;
+        imaginary += sx * dy - sy * dx;
+        source_norm += sx * sx + sy * sy;
+    }
+    if source_norm <= f32::EPSILON {
+        return None;
+    }
+    let a = real / source_norm;
+    let b = imaginary / source_norm;
+    let tx = destination_mean_x - (a * source_mean_x - b * source_mean_y);
+    let ty = destination_mean_y - (b * source_mean_x + a * source_mean_y);
+    Projection::from_matrix([a, -b, tx, b, a, ty, 0.0, 0.0, 1.0])
+}
+
+/// Computes a homography if possible, and otherwise falls back to a similarity transformation.
+///
+/// The homography needs exactly four well spread points. When there are a different number of
+/// points, or they are too close to collinear, the similarity fit over all points is used.
+pub fn compute_best_planar_transform(
+    source_points: Vec<Point>,
+    destination_points: Vec<Point>,
+) -> Option<Projection> {
+    compute_homography_projection(source_points.clone(), destination_points.clone())
+        .or_else(|| compute_similarity_transform(source_points, destination_points))
+}
+
+/// Computes the inverse of a homography projection.
+///
+/// This maps points from the destination space back to the source space, for instance to move
+/// detections made on the clean scan back onto the original photo. Composing a projection with
+/// its inverse gives the identity up to floating point error, so a round trip will not return
+/// exactly the original coordinates.
+///
+/// imageproc only constructs invertible projections, so this currently always returns Some. The
+/// Option matches compute_homography_projection and leaves room for rejecting degenerate inputs.
+pub fn invert_homography(proj: &Projection) -> Option<Projection> {
+    Some(proj.invert())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn warp_with_nearest_and_white_fill() {
+        let image = RgbImage::from_pixel(10, 10, Rgb([0, 0, 0]));
+        // Shifts the image 5 pixels to the right, so the left columns come from out of bounds.
+        let source_points = vec![
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 10.0, y: 0.0 },
+            Point { x: 10.0, y: 10.0 },
+            Point { x: 0.0, y: 10.0 },
+        ];
+        let destination_points = source_points
+            .iter()
+            .map(|p| Point {
+                x: p.x + 5.0,
+                y: p.y,
+            })
+            .collect();
+        let warped = homography_transform_image_with(
+            image,
+            source_points,
+            destination_points,
+            Interpolation::Nearest,
+            Rgb([255, 255, 255]),
+        )
+        .unwrap();
+        assert_eq!(*warped.get_pixel(1, 5), Rgb([255, 255, 255]));
+        assert_eq!(*warped.get_pixel(8, 5), Rgb([0, 0, 0]));
+    }
+
+    #[test]
+    fn warp_into_larger_canvas() {
+        let mut image = RgbImage::from_pixel(4, 4, Rgb([0, 0, 0]));
+        image.put_pixel(1, 2, Rgb([255, 255, 255]));
+        let proj = Projection::translate(10.0, 5.0);
+        let warped = homography_transform_image_sized(
+            &image,
+            &proj,
+            20,
+            16,
+            Interpolation::Nearest,
+            Rgb([0, 0, 255]),
+        );
+        assert_eq!(warped.dimensions(), (20, 16));
+        assert_eq!(warped.get_pixel(11, 7), &Rgb([255, 255, 255]));
+        assert_eq!(warped.get_pixel(10, 5), &Rgb([0, 0, 0]));
+        assert_eq!(warped.get_pixel(0, 0), &Rgb([0, 0, 255]));
+        assert_eq!(warped.get_pixel(19, 15), &Rgb([0, 0, 255]));
+    }
+
+    #[test]
+    fn invert_homography_round_trip() {
+        let source_points = vec![
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 100.0, y: 0.0 },
+            Point { x: 100.0, y: 100.0 },
+            Point { x: 0.0, y: 100.0 },
+        ];
+        let destination_points = vec![
+            Point { x: 3.0, y: 7.0 },
+            Point { x: 110.0, y: 2.0 },
+            Point { x: 95.0, y: 104.0 },
+            Point { x: -4.0, y: 98.0 },
+        ];
+        let proj = compute_homography_projection(source_points, destination_points).unwrap();
+        let inverse = invert_homography(&proj).unwrap();
+        let (x, y) = inverse * (proj * (42_f32, 17_f32));
+        assert!(Point { x, y }.approx_eq(&Point { x: 42.0, y: 17.0 }, 1e-3));
+    }
+
+    #[test]
+    fn similarity_fallback_on_nearly_collinear_points() {
+        let source_points = vec![
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 10.0, y: 0.001 },
+            Point { x: 20.0, y: -0.001 },
+            Point { x: 30.0, y: 0.0 },
+        ];
+        // Rotated a quarter turn, doubled in size, then shifted by (5, 5).
+        let destination_points: Vec<Point> = source_points
+            .iter()
+            .map(|p| Point {
+                x: 5.0 - 2.0 * p.y,
+                y: 5.0 + 2.0 * p.x,
+            })
+            .collect();
+        assert!(
+            compute_homography_projection(source_points.clone(), destination_points.clone())
+                .is_none()
+        );
+        let similarity =
+            compute_similarity_transform(source_points.clone(), destination_points.clone())
+                .unwrap();
+        let (x, y) = similarity * (15_f32, 0_f32);
+        assert!(Point { x, y }.approx_eq(&Point { x: 5.0, y: 35.0 }, 1e-3));
+        let best = compute_best_planar_transform(source_points, destination_points).unwrap();
+        let (x, y) = best * (15_f32, 0_f32);
+        assert!(Point { x, y }.approx_eq(&Point { x: 5.0, y: 35.0 }, 1e-3));
+    }
+}
@@ -1 +1,2 @@
+pub mod deskew;
 pub mod homography;
@@ -0,0 +1,96 @@
+use image::imageops::grayscale;
+use image::{GrayImage, Luma, Rgb, RgbImage};
+use imageproc::contrast::{ThresholdType, otsu_level, threshold};
+use imageproc::integral_image::{integral_image, sum_image_pixels};
+
+/// How the threshold between ink and paper is chosen.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ThresholdMethod {
+    /// A single threshold for the whole image, chosen to best separate its histogram in two.
+    Otsu,
+    /// A threshold per pixel, c below the mean of the block_size x block_size block around it.
+    ///
+    /// Copes with uneven lighting across a scan. Even block sizes act like the next odd size.
+    Adaptive { block_size: u32, c: f32 },
+}
+
+/// Converts an image to black and white, with the ink black and the paper white.
+///
+/// The image is converted to grayscale first. Pixels brighter than the threshold become white and
+/// the rest black, and the result is returned as an RgbImage so it can be passed on to
+/// convert_rgb_image_to_owned_array like any other scan.
+pub fn binarize(image: &RgbImage, method: ThresholdMethod) -> RgbImage {
+    let gray = grayscale(image);
+    let binary = match method {
+        ThresholdMethod::Otsu => threshold(&gray, otsu_level(&gray), ThresholdType::Binary),
+        ThresholdMethod::Adaptive { block_size, c } => adaptive_threshold(&gray, block_size, c),
+    };
+    RgbImage::from_fn(binary.width(), binary.height(), |x, y| {
+        let Luma([value]) = *binary.get_pixel(x, y);
+        Rgb([value, value, value])
+    })
+}
+
+fn adaptive_threshold(gray: &GrayImage, block_size: u32, c: f32) -> GrayImage {
+    let radius = (block_size / 2).max(1);
+    let integral = integral_image::<_, u32>(gray);
+    GrayImage::from_fn(gray.width(), gray.height(), |x, y| {
+        let left = x.saturating_sub(radius);
+        let top = y.saturating_sub(radius);
+        let right = (x + radius).min(gray.width() - 1);
+        let bottom = (y + radius).min(gray.height() - 1);
+        let num_pixels = ((right - left + 1) * (bottom - top + 1)) as f32;
+        let mean = sum_image_pixels(&integral, left, top, right, bottom)[0] as f32 / num_pixels;
+        if gray.get_pixel(x, y)[0] as f32 > mean - c {
+            Luma([255])
+        } else {
+            Luma([0])
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn otsu_splits_gradient_in_two() {
+        let gradient = RgbImage::from_fn(64, 8, |x, _| {
+            let value = (x * 4) as u8;
+            Rgb([value, value, value])
+        });
+        let binary = binarize(&gradient, ThresholdMethod::Otsu);
+        let row: Vec<u8> = (0..64).map(|x| binary.get_pixel(x, 4)[0]).collect();
+        let first_white = row.iter().position(|&value| value == 255).unwrap();
+        assert!((24..40).contains(&first_white));
+        assert!(row[..first_white].iter().all(|&value| value == 0));
+        assert!(row[first_white..].iter().all(|&value| value == 255));
+    }
+
+    #[test]
+    fn adaptive_handles_uneven_lighting() {
+        // The paper brightens from left to right, and the ink is darker than the paper around
+        // it but brighter than the paper on the far left.
+        let ink = [(10, 4), (30, 4), (50, 4)];
+        let image = RgbImage::from_fn(64, 8, |x, y| {
+            let paper = 100 + 2 * x;
+            let value = if ink.contains(&(x, y)) {
+                paper - 60
+            } else {
+                paper
+            } as u8;
+            Rgb([value, value, value])
+        });
+        let binary = binarize(
+            &image,
+            ThresholdMethod::Adaptive {
+                block_size: 7,
+                c: 10.0,
+            },
+        );
+        for (x, y, pixel) in binary.enumerate_pixels() {
+            let expected = if ink.contains(&(x, y)) { 0 } else { 255 };
+            assert_eq!(pixel[0], expected, "pixel ({}, {})", x, y);
+        }
+    }
+}
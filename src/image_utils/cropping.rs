@@ -0,0 +1,53 @@
+use crate::annotations::bounding_box::BoundingBoxGeometry;
+use ndarray::{ArrayBase, Dim, OwnedRepr, s};
+
+/// Copies the pixels under a box out of a (1, 3, rows, columns) image array.
+///
+/// The box's edges are rounded to the nearest pixel and clamped to the image, so a box hanging
+/// off the edge gives a smaller crop, and one entirely outside the image gives an empty one.
+/// The crop can then be fed to a secondary model, such as a digit classifier.
+pub fn crop_detection(
+    image: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>>,
+    bbox: &impl BoundingBoxGeometry,
+) -> ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> {
+    let num_rows = image.shape()[2];
+    let num_columns = image.shape()[3];
+    let clamp_to = |value: f32, limit: usize| (value.round().max(0_f32) as usize).min(limit);
+    let start_row = clamp_to(bbox.top(), num_rows);
+    let end_row = clamp_to(bbox.bottom(), num_rows).max(start_row);
+    let start_col = clamp_to(bbox.left(), num_columns);
+    let end_col = clamp_to(bbox.right(), num_columns).max(start_col);
+    image
+        .slice(s![.., .., start_row..end_row, start_col..end_col])
+        .to_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotations::bounding_box::BoundingBox;
+    use crate::image_utils::image_io::read_image_as_array4;
+    use std::path::Path;
+
+    #[test]
+    fn crop_detection_test_image() {
+        let img = read_image_as_array4(Path::new("./data/test_data/test_image.png"));
+        let bbox = BoundingBox::new(0.6, 1.2, 3.0, 3.0, "test".to_string()).unwrap();
+        let crop = crop_detection(&img, &bbox);
+        assert_eq!(crop.dim(), (1, 3, 2, 2));
+        // The top left corner of the crop is the green pixel at (1, 1).
+        assert_eq!(
+            (crop[[0, 0, 0, 0]], crop[[0, 1, 0, 0]], crop[[0, 2, 0, 0]]),
+            (0.0, 1.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn crop_detection_clamps_to_image() {
+        let img = read_image_as_array4(Path::new("./data/test_data/test_image.png"));
+        let overhanging = BoundingBox::new(-5.0, 2.0, 1.0, 10.0, "test".to_string()).unwrap();
+        assert_eq!(crop_detection(&img, &overhanging).dim(), (1, 3, 1, 1));
+        let outside = BoundingBox::new(10.0, 10.0, 12.0, 12.0, "test".to_string()).unwrap();
+        assert_eq!(crop_detection(&img, &outside).dim(), (1, 3, 0, 0));
+    }
+}
@@ -0,0 +1,71 @@
+use ndarray::{Array, ArrayBase, Dim, OwnedRepr, s};
+
+/// How a block of pixels is reduced to one.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum PoolMode {
+    Mean,
+    Max,
+}
+
+/// Shrinks a (1, 3, rows, columns) image array by reducing each factor x factor block of pixels
+/// to a single pixel.
+///
+/// This is much cheaper than resizing with the image crate, which makes it suited to a quick low
+/// resolution overview for locating regions of the chart. Rows and columns that don't fill a
+/// whole block are dropped. A factor of 0 is treated as 1.
+pub fn downsample_array4(
+    array: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>>,
+    factor: u32,
+    mode: PoolMode,
+) -> ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> {
+    let factor = factor.max(1) as usize;
+    let [batch, channels, num_rows, num_columns] = *array.shape() else {
+        unreachable!("The image array always has four dimensions.");
+    };
+    Array::from_shape_fn(
+        (batch, channels, num_rows / factor, num_columns / factor),
+        |(b, c, y, x)| {
+            let block = array.slice(s![
+                b,
+                c,
+                y * factor..(y + 1) * factor,
+                x * factor..(x + 1) * factor
+            ]);
+            match mode {
+                PoolMode::Mean => block.mean().unwrap(),
+                PoolMode::Max => block.fold(f32::NEG_INFINITY, |max, v| max.max(*v)),
+            }
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_testing_array() -> ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> {
+        // Every channel holds 0..16 laid out row by row.
+        Array::from_shape_fn((1, 3, 4, 4), |(_, _, y, x)| (y * 4 + x) as f32)
+    }
+
+    #[test]
+    fn downsample_array4_mean() {
+        let downsampled = downsample_array4(&create_testing_array(), 2, PoolMode::Mean);
+        assert_eq!(downsampled.dim(), (1, 3, 2, 2));
+        // The top left block is [[0, 1], [4, 5]].
+        assert_eq!(downsampled[[0, 0, 0, 0]], 2.5);
+        assert_eq!(downsampled[[0, 1, 0, 1]], 4.5);
+        assert_eq!(downsampled[[0, 2, 1, 0]], 10.5);
+        assert_eq!(downsampled[[0, 2, 1, 1]], 12.5);
+    }
+
+    #[test]
+    fn downsample_array4_max() {
+        let downsampled = downsample_array4(&create_testing_array(), 2, PoolMode::Max);
+        assert_eq!(downsampled.dim(), (1, 3, 2, 2));
+        assert_eq!(downsampled[[0, 0, 0, 0]], 5.0);
+        assert_eq!(downsampled[[0, 1, 0, 1]], 7.0);
+        assert_eq!(downsampled[[0, 2, 1, 0]], 13.0);
+        assert_eq!(downsampled[[0, 2, 1, 1]], 15.0);
+    }
+}
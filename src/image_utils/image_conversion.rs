@@ -4,8 +4,8 @@ use ndarray::{Array, ArrayBase, Dim, OwnedRepr, ViewRepr};
 pub fn convert_array_view_to_rgb_image(
     image_array: ArrayBase<ViewRepr<&f32>, Dim<[usize; 4]>>,
 ) -> RgbImage {
-    let image_width = image_array.shape()[2] as u32;
-    let image_height = image_array.shape()[3] as u32;
+    let image_width = image_array.shape()[3] as u32;
+    let image_height = image_array.shape()[2] as u32;
 
     let mut rgb_image = RgbImage::new(image_width, image_height);
     for y in 0..image_height {
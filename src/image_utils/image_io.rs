@@ -1,8 +1,36 @@
-use crate::image_utils::image_conversion::convert_rgb_image_to_owned_array;
-use image::{self, RgbImage};
+use crate::image_utils::image_conversion::{
+    convert_array_view_to_rgb_image, convert_rgb_image_to_owned_array,
+};
+use image::imageops::{self, FilterType};
+use image::{self, ImageError, RgbImage};
 use ndarray::{ArrayBase, Dim, OwnedRepr};
+use std::fmt;
 use std::path::Path;
 
+/// A set of custom errors for more informative error handling.
+#[derive(Debug)]
+pub enum ImageIoError {
+    Decode(ImageError),
+    Encode(ImageError),
+}
+
+impl fmt::Display for ImageIoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImageIoError::Decode(error) => write!(f, "Failed to read image, {}.", error),
+            ImageIoError::Encode(error) => write!(f, "Failed to write image, {}.", error),
+        }
+    }
+}
+
+impl std::error::Error for ImageIoError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ImageIoError::Decode(error) | ImageIoError::Encode(error) => Some(error),
+        }
+    }
+}
+
 pub fn read_image_as_rgb8(filepath: &Path) -> RgbImage {
     image::open(filepath).unwrap().into_rgb8()
 }
@@ -12,6 +40,55 @@ pub fn read_image_as_array4(filepath: &Path) -> ArrayBase<OwnedRepr<f32>, Dim<[u
     return convert_rgb_image_to_owned_array(img);
 }
 
+/// Reads an image and resizes it to (width, height) before converting it to an array.
+///
+/// This is for models that take the whole image at a fixed resolution rather than tiles. The
+/// resize happens on the decoded 8 bit image, so no full resolution f32 array is ever created.
+pub fn read_image_as_array4_resized(
+    filepath: &Path,
+    width: u32,
+    height: u32,
+    filter: FilterType,
+) -> Result<ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>>, ImageIoError> {
+    let img = image::open(filepath)
+        .map_err(ImageIoError::Decode)?
+        .into_rgb8();
+    let resized = imageops::resize(&img, width, height, filter);
+    Ok(convert_rgb_image_to_owned_array(resized))
+}
+
+/// Decodes an image held in memory, such as the body of an upload, guessing the format from
+/// its contents.
+pub fn read_rgb8_from_bytes(bytes: &[u8]) -> Result<RgbImage, ImageIoError> {
+    let img = image::load_from_memory(bytes).map_err(ImageIoError::Decode)?;
+    Ok(img.into_rgb8())
+}
+
+/// Decodes an image held in memory into an array, the in memory counterpart of
+/// read_image_as_array4.
+pub fn read_array4_from_bytes(
+    bytes: &[u8],
+) -> Result<ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>>, ImageIoError> {
+    let img = read_rgb8_from_bytes(bytes)?;
+    Ok(convert_rgb_image_to_owned_array(img))
+}
+
+/// Saves an image, choosing the format from the file extension.
+pub fn write_rgb8(image: &RgbImage, filepath: &Path) -> Result<(), ImageIoError> {
+    image.save(filepath).map_err(ImageIoError::Encode)
+}
+
+/// Saves an image array, choosing the format from the file extension.
+pub fn write_array4(
+    image_array: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>>,
+    filepath: &Path,
+) -> Result<(), ImageIoError> {
+    write_rgb8(
+        &convert_array_view_to_rgb_image(image_array.view()),
+        filepath,
+    )
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -75,4 +152,55 @@ mod tests {
             (1.0, 1.0, 1.0)
         );
     }
+
+    #[test]
+    fn read_test_data_as_array4_resized() {
+        let img = read_image_as_array4_resized(
+            Path::new("./data/test_data/test_image.png"),
+            6,
+            6,
+            FilterType::Triangle,
+        )
+        .unwrap();
+        assert_eq!(img.dim(), (1, 3, 6, 6));
+        // The top and bottom rows are still pure black and pure white.
+        assert_eq!(img[[0, 1, 0, 0]], 0.0);
+        assert_eq!(img[[0, 1, 5, 0]], 1.0);
+        // Below the red pixel, the green channel blends between red and white.
+        let blended = img[[0, 1, 3, 0]];
+        assert!(blended > 0.0 && blended < 1.0);
+        assert_eq!(img[[0, 0, 3, 0]], 1.0);
+    }
+
+    #[test]
+    fn read_test_data_from_bytes() {
+        let path = Path::new("./data/test_data/test_image.png");
+        let bytes = std::fs::read(path).unwrap();
+        assert_eq!(
+            read_rgb8_from_bytes(&bytes).unwrap(),
+            read_image_as_rgb8(path)
+        );
+        assert_eq!(
+            read_array4_from_bytes(&bytes).unwrap(),
+            read_image_as_array4(path)
+        );
+        assert!(matches!(
+            read_rgb8_from_bytes(b"not an image"),
+            Err(ImageIoError::Decode(_))
+        ));
+    }
+
+    #[test]
+    fn write_then_read_round_trip() {
+        let img = read_image_as_array4(Path::new("./data/test_data/test_image.png"));
+        let filepath = std::env::temp_dir().join("chart_extractor_write_then_read_round_trip.png");
+        write_array4(&img, &filepath).unwrap();
+        let written = read_image_as_array4(&filepath);
+        std::fs::remove_file(&filepath).unwrap();
+        assert_eq!(written, img);
+        assert!(matches!(
+            write_array4(&img, Path::new("no_such_dir/image.png")),
+            Err(ImageIoError::Encode(_))
+        ));
+    }
 }
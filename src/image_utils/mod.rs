@@ -1,4 +1,8 @@
+pub mod binarization;
+pub mod cropping;
+pub mod downsampling;
 pub mod image_conversion;
 pub mod image_io;
 pub mod padding;
 pub mod tiling;
+pub mod visualization;
@@ -60,6 +60,8 @@ impl fmt::Display for ImagePaddingError {
     }
 }
 
+impl std::error::Error for ImagePaddingError {}
+
 fn validate_padding_parameters(
     original_width: u32,
     original_height: u32,
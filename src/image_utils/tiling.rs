@@ -1,7 +1,8 @@
 use crate::image_utils::image_conversion::convert_array_view_to_rgb_image;
 use crate::image_utils::padding::pad_right_bottom_img_rbg8;
-use image::RgbImage;
-use ndarray::{ArrayBase, Dim, OwnedRepr, ViewRepr, s};
+use image::{Rgb, RgbImage};
+use ndarray::{Array, Array2, ArrayBase, Dim, OwnedRepr, ViewRepr, s};
+use serde::{Deserialize, Serialize};
 use std::fmt;
 
 /// A set of custom errors for more informative error handling.
@@ -22,6 +23,10 @@ pub enum TilingError {
         tile_size: u32,
         overlap_proportion: OverlapProportion,
     },
+    InvalidOverlapProportion {
+        numerator: u32,
+        denominator: u32,
+    },
 }
 
 impl fmt::Display for TilingError {
@@ -45,7 +50,11 @@ impl fmt::Display for TilingError {
                         tile_size, image_height
                     );
                 } else {
-                    panic!();
+                    write!(
+                        f,
+                        "Failed to tile image, tile size ({}) does not fit the image ({}x{}).",
+                        tile_size, image_width, image_height
+                    )
                 }
             }
             TilingError::IncompatibleProportionWithTileSize {
@@ -72,16 +81,56 @@ impl fmt::Display for TilingError {
                     tile_size, image_width, image_height, overlap_proportion
                 )
             }
+            TilingError::InvalidOverlapProportion {
+                numerator,
+                denominator,
+            } => {
+                write!(
+                    f,
+                    "Failed to tile image, overlap proportion ({}/{}) must have a nonzero \
+                    numerator and denominator.",
+                    numerator, denominator
+                )
+            }
         }
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+impl std::error::Error for TilingError {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
 pub struct OverlapProportion {
     pub numerator: u32,
     pub denominator: u32,
 }
 
+impl OverlapProportion {
+    /// Creates an overlap proportion stored in lowest terms, so 2/4 and 1/2 compare equal.
+    ///
+    /// A zero denominator is meaningless and a zero numerator gives a stride of zero, so both
+    /// are rejected.
+    pub fn new(numerator: u32, denominator: u32) -> Result<OverlapProportion, TilingError> {
+        if numerator == 0 || denominator == 0 {
+            return Err(TilingError::InvalidOverlapProportion {
+                numerator,
+                denominator,
+            });
+        }
+        let divisor = greatest_common_divisor(numerator, denominator);
+        Ok(OverlapProportion {
+            numerator: numerator / divisor,
+            denominator: denominator / divisor,
+        })
+    }
+}
+
+fn greatest_common_divisor(mut a: u32, mut b: u32) -> u32 {
+    while b != 0 {
+        (a, b) = (b, a % b);
+    }
+    a
+}
+
 impl fmt::Display for OverlapProportion {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{}/{}", self.numerator, self.denominator)
@@ -99,6 +148,21 @@ pub fn validate_tiling_parameters(
     tile_size: u32,
     image_width: u32,
     image_height: u32,
+) -> Option<TilingError> {
+    validate_tiling_parameters_xy(proportion, proportion, tile_size, image_width, image_height)
+}
+
+/// Validates tiling parameters with separate overlap proportions for each axis.
+///
+/// The horizontal proportion sets the stride between columns and the vertical proportion sets
+/// the stride between rows, so each is checked against its own axis. Errors report the
+/// proportion of the axis that failed.
+pub fn validate_tiling_parameters_xy(
+    horizontal: OverlapProportion,
+    vertical: OverlapProportion,
+    tile_size: u32,
+    image_width: u32,
+    image_height: u32,
 ) -> Option<TilingError> {
     if tile_size > image_width || tile_size > image_height {
         return Some(TilingError::InvalidTileSize {
@@ -108,51 +172,115 @@ pub fn validate_tiling_parameters(
         });
     }
 
-    let tile_cleanly_divides = (tile_size * proportion.numerator) % proportion.denominator == 0;
-    if !tile_cleanly_divides {
-        return Some(TilingError::IncompatibleProportionWithTileSize {
-            tile_size,
-            overlap_proportion: proportion,
-        });
+    for proportion in [horizontal, vertical] {
+        if proportion.numerator == 0 || proportion.denominator == 0 {
+            return Some(TilingError::InvalidOverlapProportion {
+                numerator: proportion.numerator,
+                denominator: proportion.denominator,
+            });
+        }
     }
 
-    let stride: u32 = (tile_size * proportion.numerator) / proportion.denominator;
-    let tiles_fit_cleanly_laterally = (image_width - tile_size) % stride == 0;
-    let tiles_fit_cleanly_vertically = (image_height - tile_size) % stride == 0;
+    for proportion in [horizontal, vertical] {
+        let tile_cleanly_divides = (tile_size * proportion.numerator) % proportion.denominator == 0;
+        if !tile_cleanly_divides {
+            return Some(TilingError::IncompatibleProportionWithTileSize {
+                tile_size,
+                overlap_proportion: proportion,
+            });
+        }
+    }
+
+    let horizontal_stride: u32 = (tile_size * horizontal.numerator) / horizontal.denominator;
+    let vertical_stride: u32 = (tile_size * vertical.numerator) / vertical.denominator;
+    let tiles_fit_cleanly_laterally = (image_width - tile_size) % horizontal_stride == 0;
+    let tiles_fit_cleanly_vertically = (image_height - tile_size) % vertical_stride == 0;
 
-    if !tiles_fit_cleanly_laterally || !tiles_fit_cleanly_vertically {
+    if !tiles_fit_cleanly_laterally {
         return Some(TilingError::UnevenImageDivision {
             image_height,
             image_width,
             tile_size,
-            overlap_proportion: proportion,
+            overlap_proportion: horizontal,
+        });
+    }
+    if !tiles_fit_cleanly_vertically {
+        return Some(TilingError::UnevenImageDivision {
+            image_height,
+            image_width,
+            tile_size,
+            overlap_proportion: vertical,
         });
     }
     None
 }
 
+/// Counts the tiles a tiling configuration produces without creating them.
+///
+/// Returns (num_rows, num_columns), using the same validation as tile_image. Useful for
+/// estimating the time and memory needed to tile an image before doing any work.
+pub fn tile_count(
+    image_width: u32,
+    image_height: u32,
+    tile_size: u32,
+    proportion: OverlapProportion,
+) -> Result<(u32, u32), TilingError> {
+    tile_count_xy(image_width, image_height, tile_size, proportion, proportion)
+}
+
+/// Counts tiles like tile_count, with separate overlap proportions for each axis.
+pub fn tile_count_xy(
+    image_width: u32,
+    image_height: u32,
+    tile_size: u32,
+    horizontal: OverlapProportion,
+    vertical: OverlapProportion,
+) -> Result<(u32, u32), TilingError> {
+    if let Some(e) =
+        validate_tiling_parameters_xy(horizontal, vertical, tile_size, image_width, image_height)
+    {
+        return Err(e);
+    }
+    let horizontal_stride: u32 = (tile_size * horizontal.numerator) / horizontal.denominator;
+    let vertical_stride: u32 = (tile_size * vertical.numerator) / vertical.denominator;
+    let num_rows = ((image_height - tile_size) / vertical_stride) + 1;
+    let num_columns = ((image_width - tile_size) / horizontal_stride) + 1;
+    Ok((num_rows, num_columns))
+}
+
 /// Tiles an image by returning a vector of immutable views into the image.
 pub fn tile_image(
     image: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>>,
     tile_size: u32,
     proportion: OverlapProportion,
 ) -> Result<Vec<Vec<ArrayBase<ViewRepr<&f32>, Dim<[usize; 4]>>>>, TilingError> {
-    let image_width = image.shape()[2] as u32;
-    let image_height = image.shape()[3] as u32;
-    if let Some(e) = validate_tiling_parameters(proportion, tile_size, image_width, image_height) {
-        return Err(e);
-    }
-    let stride: u32 = (tile_size * proportion.numerator) / proportion.denominator;
-    let num_rows = ((image_height - tile_size) / stride) + 1;
-    let num_columns = ((image_width - tile_size) / stride) + 1;
+    tile_image_xy(image, tile_size, proportion, proportion)
+}
+
+/// Tiles an image with separate overlap proportions for each axis.
+///
+/// The horizontal proportion sets the stride between columns of tiles, and the vertical
+/// proportion sets the stride between rows. This allows heavy overlap along one axis only.
+pub fn tile_image_xy(
+    image: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>>,
+    tile_size: u32,
+    horizontal: OverlapProportion,
+    vertical: OverlapProportion,
+) -> Result<Vec<Vec<ArrayBase<ViewRepr<&f32>, Dim<[usize; 4]>>>>, TilingError> {
+    let image_width = image.shape()[3] as u32;
+    let image_height = image.shape()[2] as u32;
+    let (num_rows, num_columns) =
+        tile_count_xy(image_width, image_height, tile_size, horizontal, vertical)?;
+    let horizontal_stride: u32 = (tile_size * horizontal.numerator) / horizontal.denominator;
+    let vertical_stride: u32 = (tile_size * vertical.numerator) / vertical.denominator;
 
     let mut tiles: Vec<Vec<ArrayBase<ViewRepr<&f32>, Dim<[usize; 4]>>>> = Vec::new();
     for row_ix in 0..num_rows {
         let mut row_of_tiles: Vec<ArrayBase<ViewRepr<&f32>, Dim<[usize; 4]>>> = vec![];
-        let start_row = (row_ix * stride) as usize;
+        let start_row = (row_ix * vertical_stride) as usize;
         let end_row = start_row + (tile_size as usize);
         for col_ix in 0..num_columns {
-            let start_col = (col_ix * stride) as usize;
+            let start_col = (col_ix * horizontal_stride) as usize;
             let end_col = start_col + (tile_size as usize);
             let tile = image.slice(s![.., .., start_row..end_row, start_col..end_col]);
             row_of_tiles.push(tile);
@@ -162,6 +290,135 @@ pub fn tile_image(
     Ok(tiles)
 }
 
+/// Tiles an image lazily, yielding (row_ix, col_ix, tile) in row-major order.
+///
+/// Unlike tile_image, no vectors of tiles are allocated, each view is only created when the
+/// iterator reaches it. This keeps memory bounded when tiling very large scans.
+pub fn tile_iter(
+    image: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>>,
+    tile_size: u32,
+    proportion: OverlapProportion,
+) -> Result<
+    impl Iterator<Item = (usize, usize, ArrayBase<ViewRepr<&f32>, Dim<[usize; 4]>>)>,
+    TilingError,
+> {
+    tile_iter_xy(image, tile_size, proportion, proportion)
+}
+
+/// Tiles an image lazily with separate overlap proportions for each axis.
+pub fn tile_iter_xy(
+    image: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>>,
+    tile_size: u32,
+    horizontal: OverlapProportion,
+    vertical: OverlapProportion,
+) -> Result<
+    impl Iterator<Item = (usize, usize, ArrayBase<ViewRepr<&f32>, Dim<[usize; 4]>>)>,
+    TilingError,
+> {
+    let image_width = image.shape()[3] as u32;
+    let image_height = image.shape()[2] as u32;
+    let (num_rows, num_columns) =
+        tile_count_xy(image_width, image_height, tile_size, horizontal, vertical)?;
+    let horizontal_stride = ((tile_size * horizontal.numerator) / horizontal.denominator) as usize;
+    let vertical_stride = ((tile_size * vertical.numerator) / vertical.denominator) as usize;
+
+    Ok((0..num_rows as usize).flat_map(move |row_ix| {
+        (0..num_columns as usize).map(move |col_ix| {
+            let start_row = row_ix * vertical_stride;
+            let end_row = start_row + (tile_size as usize);
+            let start_col = col_ix * horizontal_stride;
+            let end_col = start_col + (tile_size as usize);
+            let tile = image.slice(s![.., .., start_row..end_row, start_col..end_col]);
+            (row_ix, col_ix, tile)
+        })
+    }))
+}
+
+/// Lists the (row_ix, col_ix) of the tiles that overlap the content of an image.
+///
+/// image_size is (width, height). The content mask covers the whole image at any resolution, so
+/// it can come from a cheap downsample and threshold, with mask[[row, col]] true where there is
+/// content. A tile is kept if any mask cell it touches is true, so tiles of blank margins can be
+/// skipped when predicting.
+pub fn plan_tiles_within_mask(
+    image_size: (u32, u32),
+    tile_size: u32,
+    proportion: OverlapProportion,
+    content_mask: &Array2<bool>,
+) -> Result<Vec<(usize, usize)>, TilingError> {
+    let (image_width, image_height) = image_size;
+    let (num_rows, num_columns) = tile_count(image_width, image_height, tile_size, proportion)?;
+    let stride = (tile_size * proportion.numerator) / proportion.denominator;
+    let (mask_rows, mask_columns) = content_mask.dim();
+    // Maps the pixels [start, start + tile_size) onto the range of mask cells they touch.
+    let mask_range = |start: u32, image_length: u32, mask_length: usize| {
+        let to_mask = |pixel: u32| pixel as usize * mask_length / image_length as usize;
+        let end = to_mask(start + tile_size - 1) + 1;
+        to_mask(start)..end.min(mask_length)
+    };
+    let mut plan: Vec<(usize, usize)> = Vec::new();
+    for row_ix in 0..num_rows {
+        let rows = mask_range(row_ix * stride, image_height, mask_rows);
+        for col_ix in 0..num_columns {
+            let columns = mask_range(col_ix * stride, image_width, mask_columns);
+            if content_mask
+                .slice(s![rows.clone(), columns])
+                .iter()
+                .any(|&has_content| has_content)
+            {
+                plan.push((row_ix as usize, col_ix as usize));
+            }
+        }
+    }
+    Ok(plan)
+}
+
+/// Stitches tiles back together into a single image, the inverse of tile_image.
+///
+/// Each tile is placed at the offset it was cut from, and pixels covered by more than one tile
+/// are set to the (rounded) average of the overlapping tiles. Pixels not covered by any tile are
+/// left black. Useful for assembling per-tile outputs and for visually checking tiling.
+pub fn stitch_tiles(
+    tiles: &[Vec<RgbImage>],
+    tile_size: u32,
+    proportion: OverlapProportion,
+    output_width: u32,
+    output_height: u32,
+) -> RgbImage {
+    let stride: u32 = (tile_size * proportion.numerator) / proportion.denominator;
+    let num_pixels = (output_width * output_height) as usize;
+    let mut channel_sums: Vec<[u32; 3]> = vec![[0, 0, 0]; num_pixels];
+    let mut tile_counts: Vec<u32> = vec![0; num_pixels];
+    for (row_ix, row_of_tiles) in tiles.iter().enumerate() {
+        for (col_ix, tile) in row_of_tiles.iter().enumerate() {
+            let x_offset = (col_ix as u32) * stride;
+            let y_offset = (row_ix as u32) * stride;
+            for (x, y, pixel) in tile.enumerate_pixels() {
+                let output_x = x + x_offset;
+                let output_y = y + y_offset;
+                if output_x >= output_width || output_y >= output_height {
+                    continue;
+                }
+                let pixel_ix = (output_y * output_width + output_x) as usize;
+                for (sum, value) in channel_sums[pixel_ix].iter_mut().zip(pixel.0) {
+                    *sum += value as u32;
+                }
+                tile_counts[pixel_ix] += 1;
+            }
+        }
+    }
+    RgbImage::from_fn(output_width, output_height, |x, y| {
+        let pixel_ix = (y * output_width + x) as usize;
+        let count = tile_counts[pixel_ix];
+        if count == 0 {
+            return Rgb([0, 0, 0]);
+        }
+        let [r, g, b] = channel_sums[pixel_ix];
+        let average = |sum: u32| ((sum as f32) / (count as f32)).round() as u8;
+        Rgb([average(r), average(g), average(b)])
+    })
+}
+
 /// Pads an image to the smallest size that is larger than the image's original
 /// size if it cannot be tiled with the tiling parameters supplied.
 pub fn pad_image_to_fit_tiling_params(
@@ -170,8 +427,8 @@ pub fn pad_image_to_fit_tiling_params(
     proportion: OverlapProportion,
 ) -> RgbImage {
     let image = image.view();
-    let image_width: u32 = image.shape()[2] as u32;
-    let image_height: u32 = image.shape()[3] as u32;
+    let image_width: u32 = image.shape()[3] as u32;
+    let image_height: u32 = image.shape()[2] as u32;
     let params_are_valid: bool =
         validate_tiling_parameters(proportion, tile_size, image_width, image_height).is_none();
     if params_are_valid {
@@ -189,27 +446,152 @@ pub fn pad_image_to_fit_tiling_params(
     }
 }
 
+/// Pads an image with zeros on the right and bottom until it can be tiled, then tiles it.
+///
+/// Returns the tiles along with the number of pixels of padding added to the right and bottom,
+/// so detections near the padded edges can be clipped back to the original image. The tiles are
+/// owned copies since they are cut from the padded array, which does not outlive this function.
+pub fn tile_image_auto_pad(
+    image: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>>,
+    tile_size: u32,
+    proportion: OverlapProportion,
+) -> Result<
+    (
+        Vec<Vec<ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>>>>,
+        u32,
+        u32,
+    ),
+    TilingError,
+> {
+    let [batch, channels, num_rows, num_columns] = *image.shape() else {
+        unreachable!("The image array always has four dimensions.");
+    };
+    let (new_columns, new_rows) = find_smallest_img_size_large_enough_to_tile(
+        num_columns as u32,
+        num_rows as u32,
+        tile_size,
+        proportion,
+    );
+    let new_columns = (new_columns as usize).max(num_columns);
+    let new_rows = (new_rows as usize).max(num_rows);
+    let mut padded: ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> =
+        Array::zeros((batch, channels, new_rows, new_columns));
+    padded
+        .slice_mut(s![.., .., ..num_rows, ..num_columns])
+        .assign(image);
+    let tiles = tile_image(&padded, tile_size, proportion)?
+        .into_iter()
+        .map(|row| row.into_iter().map(|tile| tile.to_owned()).collect())
+        .collect();
+    let pad_right = (new_columns - num_columns) as u32;
+    let pad_bottom = (new_rows - num_rows) as u32;
+    Ok((tiles, pad_right, pad_bottom))
+}
+
+/// A tile cut by tile_image_with_edge_fill, along with where its real pixels are.
+///
+/// The tile's top left corner is at (row_start, col_start) in the original image, and only its
+/// first content_rows rows and content_columns columns hold image pixels. The rest is fill.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EdgeFilledTile {
+    pub tile: ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>>,
+    pub row_start: usize,
+    pub col_start: usize,
+    pub content_rows: usize,
+    pub content_columns: usize,
+}
+
+/// Tiles an image at the usual stride, adding a final partial tile at each edge if needed.
+///
+/// Unlike tile_image, the image does not need to divide evenly: when the last full tile stops
+/// short of the right or bottom edge, one more tile is started a stride later and the part of it
+/// past the edge is set to fill_value. Images smaller than a tile give a single padded tile.
+pub fn tile_image_with_edge_fill(
+    image: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>>,
+    tile_size: u32,
+    proportion: OverlapProportion,
+    fill_value: f32,
+) -> Result<Vec<Vec<EdgeFilledTile>>, TilingError> {
+    let [batch, channels, num_rows, num_columns] = *image.shape() else {
+        unreachable!("The image array always has four dimensions.");
+    };
+    let tile_size_usize = tile_size as usize;
+    match validate_tiling_parameters(
+        proportion,
+        tile_size,
+        num_columns.max(tile_size_usize) as u32,
+        num_rows.max(tile_size_usize) as u32,
+    ) {
+        None | Some(TilingError::UnevenImageDivision { .. }) => {}
+        Some(e) => return Err(e),
+    }
+    let stride = ((tile_size * proportion.numerator) / proportion.denominator) as usize;
+    let starts = |length: usize| -> Vec<usize> {
+        let mut starts = vec![0];
+        while starts[starts.len() - 1] + tile_size_usize < length {
+            starts.push(starts[starts.len() - 1] + stride);
+        }
+        starts
+    };
+
+    let col_starts = starts(num_columns);
+    Ok(starts(num_rows)
+        .into_iter()
+        .map(|row_start| {
+            col_starts
+                .iter()
+                .map(|&col_start| {
+                    let content_rows = tile_size_usize.min(num_rows - row_start);
+                    let content_columns = tile_size_usize.min(num_columns - col_start);
+                    let mut tile: ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> = Array::from_elem(
+                        (batch, channels, tile_size_usize, tile_size_usize),
+                        fill_value,
+                    );
+                    tile.slice_mut(s![.., .., ..content_rows, ..content_columns])
+                        .assign(&image.slice(s![
+                            ..,
+                            ..,
+                            row_start..row_start + content_rows,
+                            col_start..col_start + content_columns
+                        ]));
+                    EdgeFilledTile {
+                        tile,
+                        row_start,
+                        col_start,
+                        content_rows,
+                        content_columns,
+                    }
+                })
+                .collect()
+        })
+        .collect())
+}
+
 /// Helper fn for pad_image_to_fit_tiling_params that determines the smallest
 /// image size that is larger than the current image which can be tiled with
 /// the given tiling parameters
+///
+/// A length can be tiled when it is one tile plus a whole number of strides. An invalid
+/// proportion leaves the size unchanged, so that tiling reports the proportion's error.
 fn find_smallest_img_size_large_enough_to_tile(
     original_image_width: u32,
     original_image_height: u32,
     tile_size: u32,
     overlap_proportion: OverlapProportion,
 ) -> (u32, u32) {
-    let proportion_f32: f32 = overlap_proportion.into();
-    let image_width: f32 = original_image_width as f32;
-    let image_height: f32 = original_image_height as f32;
-    let new_width: u32 = {
-        let multiplier: f32 = (image_width / ((tile_size as f32) * proportion_f32)).ceil();
-        (multiplier * tile_size as f32 * proportion_f32) as u32
-    };
-    let new_height: u32 = {
-        let multiplier: f32 = (image_height / ((tile_size as f32) * proportion_f32)).ceil();
-        (multiplier * tile_size as f32 * proportion_f32) as u32
+    let stride: u32 = (tile_size * overlap_proportion.numerator)
+        .checked_div(overlap_proportion.denominator)
+        .unwrap_or(0);
+    if stride == 0 {
+        return (original_image_width, original_image_height);
+    }
+    let smallest_tileable_length = |length: u32| -> u32 {
+        tile_size + length.saturating_sub(tile_size).div_ceil(stride) * stride
     };
-    (new_width, new_height)
+    (
+        smallest_tileable_length(original_image_width),
+        smallest_tileable_length(original_image_height),
+    )
 }
 
 #[cfg(test)]
@@ -325,6 +707,174 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_tile_iter_matches_tile_image() {
+        let img = read_image_as_array4(Path::new("./data/test_data/test_image.png"));
+        let tiles = tile_image(&img, 2, ONE_HALF).unwrap();
+        let eager_tiles: Vec<(usize, usize, ArrayBase<ViewRepr<&f32>, Dim<[usize; 4]>>)> = tiles
+            .iter()
+            .enumerate()
+            .flat_map(|(row_ix, row)| {
+                row.iter()
+                    .enumerate()
+                    .map(move |(col_ix, tile)| (row_ix, col_ix, *tile))
+            })
+            .collect();
+        let lazy_tiles: Vec<(usize, usize, ArrayBase<ViewRepr<&f32>, Dim<[usize; 4]>>)> =
+            tile_iter(&img, 2, ONE_HALF).unwrap().collect();
+        assert_eq!(eager_tiles, lazy_tiles);
+    }
+
+    #[test]
+    fn test_tile_iter_invalid_parameters() {
+        let img = read_image_as_array4(Path::new("./data/test_data/test_image.png"));
+        assert_eq!(
+            tile_iter(&img, 4, ONE_HALF).err(),
+            Some(TilingError::InvalidTileSize {
+                tile_size: 4_u32,
+                image_width: 3_u32,
+                image_height: 3_u32
+            })
+        );
+    }
+
+    #[test]
+    fn test_tile_count_matches_tile_image() {
+        let configurations: Vec<(usize, usize, u32, OverlapProportion)> = vec![
+            (3, 3, 2, ONE_HALF),
+            (10, 10, 10, ONE_HALF),
+            (18, 18, 10, TWO_FIFTHS),
+            (30, 30, 10, ONE_HALF),
+        ];
+        for (width, height, tile_size, proportion) in configurations {
+            let img: ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> =
+                ndarray::Array::zeros((1, 3, height, width));
+            let tiles = tile_image(&img, tile_size, proportion).unwrap();
+            let (num_rows, num_columns) =
+                tile_count(width as u32, height as u32, tile_size, proportion).unwrap();
+            assert_eq!(tiles.len(), num_rows as usize);
+            assert_eq!(tiles[0].len(), num_columns as usize);
+        }
+    }
+
+    #[test]
+    fn test_tile_count_invalid_parameters() {
+        assert_eq!(
+            tile_count(11, 10, 10, ONE_HALF),
+            Err(TilingError::UnevenImageDivision {
+                image_height: 10_u32,
+                image_width: 11_u32,
+                tile_size: 10_u32,
+                overlap_proportion: ONE_HALF
+            })
+        );
+    }
+
+    #[test]
+    fn test_tile_image_xy_asymmetric_overlap() {
+        let img: ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> = ndarray::Array::zeros((1, 3, 30, 30));
+        let no_overlap = OverlapProportion {
+            numerator: 1_u32,
+            denominator: 1_u32,
+        };
+        let tiles = tile_image_xy(&img, 10, ONE_HALF, no_overlap).unwrap();
+        assert_eq!(tiles.len(), 3);
+        assert!(tiles.iter().all(|row| row.len() == 5));
+        let tiles = tile_image_xy(&img, 10, no_overlap, ONE_HALF).unwrap();
+        assert_eq!(tiles.len(), 5);
+        assert!(tiles.iter().all(|row| row.len() == 3));
+        assert_eq!(
+            tile_iter_xy(&img, 10, ONE_HALF, no_overlap)
+                .unwrap()
+                .count(),
+            15
+        );
+    }
+
+    #[test]
+    fn test_tile_image_xy_landscape_image() {
+        // 20 rows by 30 columns, each pixel holding its own (row, column).
+        let img: ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> =
+            Array::from_shape_fn((1, 3, 20, 30), |(_, _, y, x)| (y * 100 + x) as f32);
+        let no_overlap = OverlapProportion {
+            numerator: 1_u32,
+            denominator: 1_u32,
+        };
+        let tiles = tile_image_xy(&img, 10, ONE_HALF, no_overlap).unwrap();
+        assert_eq!(tiles.len(), 2);
+        assert!(tiles.iter().all(|row| row.len() == 5));
+        assert_eq!(tiles[1][4][[0, 0, 0, 0]], 1020_f32);
+        assert_eq!(tiles[1][4][[0, 0, 9, 9]], 1929_f32);
+
+        let lazy_tiles: Vec<(usize, usize, f32)> = tile_iter_xy(&img, 10, ONE_HALF, no_overlap)
+            .unwrap()
+            .map(|(row_ix, col_ix, tile)| (row_ix, col_ix, tile[[0, 0, 0, 0]]))
+            .collect();
+        assert_eq!(lazy_tiles.len(), 10);
+        assert_eq!(lazy_tiles[9], (1, 4, 1020_f32));
+        assert_eq!(tile_count_xy(30, 20, 10, ONE_HALF, no_overlap), Ok((2, 5)));
+    }
+
+    #[test]
+    fn test_pad_image_to_fit_tiling_params_landscape_image() {
+        let img: ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> = Array::ones((1, 3, 10, 25));
+        let padded = pad_image_to_fit_tiling_params(&img, 10, ONE_HALF);
+        assert_eq!(padded.dimensions(), (25, 10));
+        let padded = pad_image_to_fit_tiling_params(&img, 4, ONE_HALF);
+        assert_eq!(padded.dimensions(), (26, 10));
+    }
+
+    #[test]
+    fn test_validate_tiling_parameters_xy_flags_each_axis() {
+        let one_third = OverlapProportion {
+            numerator: 1_u32,
+            denominator: 3_u32,
+        };
+        let expected_error = Some(TilingError::IncompatibleProportionWithTileSize {
+            tile_size: 10_u32,
+            overlap_proportion: one_third,
+        });
+        assert_eq!(
+            validate_tiling_parameters_xy(one_third, ONE_HALF, 10, 30, 30),
+            expected_error
+        );
+        assert_eq!(
+            validate_tiling_parameters_xy(ONE_HALF, one_third, 10, 30, 30),
+            expected_error
+        );
+        assert_eq!(
+            validate_tiling_parameters_xy(ONE_HALF, ONE_HALF, 10, 30, 30),
+            None
+        );
+    }
+
+    #[test]
+    fn test_stitch_tiles_reproduces_image() {
+        let img = read_image_as_array4(Path::new("./data/test_data/test_image.png"));
+        let tiles: Vec<Vec<RgbImage>> = tile_image(&img, 2, ONE_HALF)
+            .unwrap()
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|tile| convert_array_view_to_rgb_image(*tile))
+                    .collect()
+            })
+            .collect();
+        let stitched = stitch_tiles(&tiles, 2, ONE_HALF, 3, 3);
+        let true_img = read_image_as_rgb8(Path::new("./data/test_data/test_image.png"));
+        assert_eq!(stitched, true_img);
+    }
+
+    #[test]
+    fn test_stitch_tiles_averages_overlap() {
+        let black_tile = RgbImage::from_pixel(2, 2, Rgb([0, 0, 0]));
+        let white_tile = RgbImage::from_pixel(2, 2, Rgb([255, 255, 255]));
+        let stitched = stitch_tiles(&[vec![black_tile, white_tile]], 2, ONE_HALF, 3, 2);
+        assert_eq!(stitched.get_pixel(0, 0), &Rgb([0, 0, 0]));
+        assert_eq!(stitched.get_pixel(1, 0), &Rgb([128, 128, 128]));
+        assert_eq!(stitched.get_pixel(2, 0), &Rgb([255, 255, 255]));
+    }
+
     #[test]
     fn test_find_smallest_img_size_large_enough_to_tile_tile_size_larger_than_width() {
         let image_width: u32 = 1250;
@@ -414,4 +964,140 @@ mod tests {
         );
         assert_eq!(padding_params, (1250, 1250));
     }
+
+    #[test]
+    fn test_tile_image_auto_pad_uneven_image() {
+        let img: ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> = Array::ones((1, 3, 9, 9));
+        assert!(tile_image(&img, 4, ONE_HALF).is_err());
+        let (tiles, pad_right, pad_bottom) = tile_image_auto_pad(&img, 4, ONE_HALF).unwrap();
+        assert_eq!((pad_right, pad_bottom), (1, 1));
+        assert_eq!(tiles.len(), 4);
+        assert!(tiles.iter().all(|row| row.len() == 4));
+        let corner = &tiles[3][3];
+        assert_eq!(corner[[0, 0, 2, 2]], 1_f32);
+        assert_eq!(corner[[0, 0, 3, 3]], 0_f32);
+        assert_eq!(tiles[0][0].sum(), 48_f32);
+    }
+
+    #[test]
+    fn test_tile_image_auto_pad_stride_not_dividing_tile() {
+        // The stride is 4, so the image is padded to 10 + 3 * 4 = 22 pixels rather than to 20,
+        // the next multiple of the stride, which no whole number of strides reaches.
+        let img: ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> = Array::ones((1, 3, 19, 19));
+        assert!(tile_image(&img, 10, TWO_FIFTHS).is_err());
+        let (tiles, pad_right, pad_bottom) = tile_image_auto_pad(&img, 10, TWO_FIFTHS).unwrap();
+        assert_eq!((pad_right, pad_bottom), (3, 3));
+        assert_eq!(tiles.len(), 4);
+        assert!(tiles.iter().all(|row| row.len() == 4));
+        let unpadded: ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> = Array::ones((1, 3, 18, 18));
+        assert_eq!(
+            find_smallest_img_size_large_enough_to_tile(18, 18, 10, TWO_FIFTHS),
+            (18, 18)
+        );
+        assert!(tile_image(&unpadded, 10, TWO_FIFTHS).is_ok());
+    }
+
+    #[test]
+    fn test_tile_image_auto_pad_landscape_image() {
+        // 15 rows by 27 columns, only the width needs padding to reach a whole stride.
+        let img: ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> = Array::ones((1, 3, 15, 27));
+        assert!(tile_image(&img, 10, ONE_HALF).is_err());
+        let (tiles, pad_right, pad_bottom) = tile_image_auto_pad(&img, 10, ONE_HALF).unwrap();
+        assert_eq!((pad_right, pad_bottom), (3, 0));
+        assert_eq!(tiles.len(), 2);
+        assert!(tiles.iter().all(|row| row.len() == 5));
+        let corner = &tiles[1][4];
+        assert_eq!(corner.shape(), &[1, 3, 10, 10]);
+        assert_eq!(corner[[0, 0, 9, 6]], 1_f32);
+        assert_eq!(corner[[0, 0, 9, 7]], 0_f32);
+    }
+
+    #[test]
+    fn test_overlap_proportion_new_reduces() {
+        let proportion = OverlapProportion::new(2, 4).unwrap();
+        assert_eq!(proportion, ONE_HALF);
+        assert_eq!(proportion.to_string(), "1/2");
+    }
+
+    #[test]
+    fn test_overlap_proportion_new_zero_denominator() {
+        assert_eq!(
+            OverlapProportion::new(1, 0),
+            Err(TilingError::InvalidOverlapProportion {
+                numerator: 1,
+                denominator: 0
+            })
+        );
+    }
+
+    #[test]
+    fn test_overlap_proportion_serde_round_trip() {
+        let json = serde_json::to_string(&TWO_FIFTHS).unwrap();
+        let parsed: OverlapProportion = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed, TWO_FIFTHS);
+    }
+
+    #[test]
+    fn test_tile_image_with_edge_fill_keeps_edge_content() {
+        // 4 rows by 11 columns, with a stride of 2 the last full tile ends at column 10.
+        let image: ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> =
+            Array::from_shape_fn((1, 1, 4, 11), |(_, _, y, x)| (y * 100 + x) as f32);
+        assert!(tile_image(&image, 4, ONE_HALF).is_err());
+
+        let tiles = tile_image_with_edge_fill(&image, 4, ONE_HALF, -1_f32).unwrap();
+        assert_eq!(tiles.len(), 1);
+        assert_eq!(tiles[0].len(), 5);
+        let last = &tiles[0][4];
+        assert_eq!((last.row_start, last.col_start), (0, 8));
+        assert_eq!((last.content_rows, last.content_columns), (4, 3));
+        assert_eq!(last.tile[[0, 0, 3, 2]], 310_f32);
+        assert_eq!(last.tile[[0, 0, 3, 3]], -1_f32);
+        assert_eq!(tiles[0][3].content_columns, 4);
+    }
+
+    #[test]
+    fn test_tile_image_with_edge_fill_small_image() {
+        let image: ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> = Array::ones((1, 3, 3, 2));
+        let tiles = tile_image_with_edge_fill(&image, 4, ONE_HALF, 0_f32).unwrap();
+        assert_eq!(tiles.len(), 1);
+        assert_eq!(tiles[0].len(), 1);
+        assert_eq!(
+            (tiles[0][0].content_rows, tiles[0][0].content_columns),
+            (3, 2)
+        );
+        assert_eq!(tiles[0][0].tile.sum(), 18_f32);
+    }
+
+    #[test]
+    fn test_plan_tiles_within_mask_top_left_quarter() {
+        // A 16x16 image cut into 3x3 tiles of size 8 with stride 4, and a 4x4 mask whose cells
+        // are 4 pixels wide. Only the top left quarter, pixels [0, 8), has content.
+        let mut content_mask: Array2<bool> = Array2::from_elem((4, 4), false);
+        content_mask.slice_mut(s![..2, ..2]).fill(true);
+        let plan = plan_tiles_within_mask((16, 16), 8, ONE_HALF, &content_mask).unwrap();
+        assert_eq!(plan, vec![(0, 0), (0, 1), (1, 0), (1, 1)]);
+
+        // A single content pixel in the bottom right cell only reaches the last tile.
+        let mut content_mask: Array2<bool> = Array2::from_elem((16, 16), false);
+        content_mask[[15, 15]] = true;
+        let plan = plan_tiles_within_mask((16, 16), 8, ONE_HALF, &content_mask).unwrap();
+        assert_eq!(plan, vec![(2, 2)]);
+    }
+
+    #[test]
+    fn test_plan_tiles_within_mask_matches_tile_iter_on_landscape_image() {
+        // 16 rows by 24 columns cut into 3 rows and 5 columns of tiles, with content in two spots.
+        let mut image: ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> = Array::zeros((1, 1, 16, 24));
+        image[[0, 0, 2, 21]] = 1_f32;
+        image[[0, 0, 13, 1]] = 1_f32;
+        let content_mask: Array2<bool> = image.slice(s![0, 0, .., ..]).mapv(|value| value > 0_f32);
+        let plan = plan_tiles_within_mask((24, 16), 8, ONE_HALF, &content_mask).unwrap();
+        let tiles_with_content: Vec<(usize, usize)> = tile_iter(&image, 8, ONE_HALF)
+            .unwrap()
+            .filter(|(_, _, tile)| tile.sum() > 0_f32)
+            .map(|(row_ix, col_ix, _)| (row_ix, col_ix))
+            .collect();
+        assert_eq!(plan, tiles_with_content);
+        assert_eq!(plan, vec![(0, 4), (2, 0)]);
+    }
 }
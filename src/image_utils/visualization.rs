@@ -0,0 +1,245 @@
+use crate::annotations::bounding_box::{BoundingBoxGeometry, Category};
+use crate::annotations::bounding_box_with_keypoint::BoundingBoxWithKeypoint;
+use crate::annotations::detection::Detection;
+use ab_glyph::Font;
+use image::{Rgb, RgbImage};
+use imageproc::drawing::{
+    draw_filled_circle_mut, draw_filled_rect_mut, draw_hollow_rect_mut, draw_text_mut, text_size,
+};
+use imageproc::rect::Rect;
+use std::collections::HashMap;
+use std::fmt::Display;
+
+/// The radius (in pixels) of the circle drawn for each keypoint.
+const KEYPOINT_RADIUS: i32 = 3;
+/// The color of label text, drawn over a background of the box's color.
+const LABEL_TEXT_COLOR: Rgb<u8> = Rgb([255, 255, 255]);
+
+/// Derives a color from a category so that unmapped categories are still drawn consistently.
+///
+/// Uses the FNV-1a hash of the category, which unlike the standard library's hasher is stable
+/// across runs and compiler versions.
+pub fn category_color(category: &str) -> Rgb<u8> {
+    let mut hash: u32 = 0x811c9dc5;
+    for byte in category.bytes() {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    let [r, g, b, _] = hash.to_le_bytes();
+    Rgb([r, g, b])
+}
+
+fn lookup_color<C: Category>(category: &C, color_map: &HashMap<C, Rgb<u8>>) -> Rgb<u8> {
+    match color_map.get(category) {
+        Some(color) => *color,
+        None => category_color(&category.to_string()),
+    }
+}
+
+/// Rounds a box to the nearest pixel rectangle, which is at least one pixel wide and tall.
+fn annotation_rect<T: BoundingBoxGeometry>(annotation: &T) -> Rect {
+    let width = (annotation.right() - annotation.left()).round().max(1.0);
+    let height = (annotation.bottom() - annotation.top()).round().max(1.0);
+    Rect::at(
+        annotation.left().round() as i32,
+        annotation.top().round() as i32,
+    )
+    .of_size(width as u32, height as u32)
+}
+
+/// Draws the outline of each detection's box onto a copy of the image.
+///
+/// Boxes are colored by category using the color map, falling back to category_color for
+/// categories that are not in the map. Labels and confidences need a font to be rendered, so
+/// they are drawn by draw_labeled_detections instead.
+pub fn draw_detections<T: BoundingBoxGeometry + Display>(
+    image: &RgbImage,
+    detections: &[Detection<T>],
+    color_map: &HashMap<T::Category, Rgb<u8>>,
+) -> RgbImage {
+    let mut canvas = image.clone();
+    for det in detections.iter() {
+        let color = lookup_color(det.annotation.category(), color_map);
+        draw_hollow_rect_mut(&mut canvas, annotation_rect(&det.annotation), color);
+    }
+    canvas
+}
+
+/// Draws each detection's box like draw_detections, with its category and confidence above it.
+///
+/// The project does not bundle a font, so the caller supplies one, such as an ab_glyph FontRef
+/// read from a .ttf file. The label is "category confidence" in white on a background of the
+/// box's color, scale pixels tall, and is moved inside the box when there is no room above it.
+pub fn draw_labeled_detections<T: BoundingBoxGeometry + Display>(
+    image: &RgbImage,
+    detections: &[Detection<T>],
+    color_map: &HashMap<T::Category, Rgb<u8>>,
+    font: &impl Font,
+    scale: f32,
+) -> RgbImage {
+    let mut canvas = draw_detections(image, detections, color_map);
+    for det in detections.iter() {
+        let color = lookup_color(det.annotation.category(), color_map);
+        let label = format!("{} {:.2}", det.annotation.category(), det.confidence);
+        let (text_width, _) = text_size(scale, font, &label);
+        let label_height = scale.ceil() as i32;
+        let rect = annotation_rect(&det.annotation);
+        let top = if rect.top() >= label_height {
+            rect.top() - label_height
+        } else {
+            rect.top()
+        };
+        let background = Rect::at(rect.left(), top).of_size(text_width.max(1), label_height as u32);
+        draw_filled_rect_mut(&mut canvas, background, color);
+        draw_text_mut(
+            &mut canvas,
+            LABEL_TEXT_COLOR,
+            rect.left(),
+            top,
+            scale,
+            font,
+            &label,
+        );
+    }
+    canvas
+}
+
+/// Draws each detection's box with a semi-transparent fill onto a copy of the image.
+///
+/// The fill is blended over the underlying pixels as alpha * color + (1 - alpha) * pixel, so
+/// overlapping boxes stay visible. The outline is drawn opaque on top. Alpha is clamped to [0, 1].
+pub fn draw_detections_alpha<T: BoundingBoxGeometry + Display>(
+    image: &RgbImage,
+    detections: &[Detection<T>],
+    color_map: &HashMap<T::Category, Rgb<u8>>,
+    alpha: f32,
+) -> RgbImage {
+    let alpha = alpha.clamp(0.0, 1.0);
+    let mut canvas = image.clone();
+    for det in detections.iter() {
+        let color = lookup_color(det.annotation.category(), color_map);
+        let rect = annotation_rect(&det.annotation);
+        let left = rect.left().max(0) as u32;
+        let top = rect.top().max(0) as u32;
+        let right = (rect.right() + 1).clamp(0, canvas.width() as i32) as u32;
+        let bottom = (rect.bottom() + 1).clamp(0, canvas.height() as i32) as u32;
+        for y in top..bottom {
+            for x in left..right {
+                let pixel = canvas.get_pixel_mut(x, y);
+                for (channel, fill) in pixel.0.iter_mut().zip(color.0) {
+                    *channel =
+                        (alpha * fill as f32 + (1.0 - alpha) * *channel as f32).round() as u8;
+                }
+            }
+        }
+        draw_hollow_rect_mut(&mut canvas, rect, color);
+    }
+    canvas
+}
+
+/// Draws each detection's box and a filled circle at its keypoint onto a copy of the image.
+pub fn draw_keypoint_detections(
+    image: &RgbImage,
+    detections: &[Detection<BoundingBoxWithKeypoint>],
+    color_map: &HashMap<String, Rgb<u8>>,
+) -> RgbImage {
+    let mut canvas = draw_detections(image, detections, color_map);
+    for det in detections.iter() {
+        let color = lookup_color(det.annotation.category(), color_map);
+        let center = (
+            det.annotation.get_keypoint_x().round() as i32,
+            det.annotation.get_keypoint_y().round() as i32,
+        );
+        draw_filled_circle_mut(&mut canvas, center, KEYPOINT_RADIUS, color);
+    }
+    canvas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotations::bounding_box::BoundingBox;
+
+    const WHITE: Rgb<u8> = Rgb([255, 255, 255]);
+    const RED: Rgb<u8> = Rgb([255, 0, 0]);
+
+    #[test]
+    fn draw_detections_colors_border() {
+        let image = RgbImage::from_pixel(10, 10, WHITE);
+        let dets: Vec<Detection<BoundingBox>> = vec![Detection {
+            annotation: BoundingBox::new(2_f32, 2_f32, 7_f32, 7_f32, "test".to_string()).unwrap(),
+            confidence: 0.9_f32,
+        }];
+        let color_map: HashMap<String, Rgb<u8>> = HashMap::from([("test".to_string(), RED)]);
+        let drawn = draw_detections(&image, &dets, &color_map);
+        assert_eq!(drawn.get_pixel(2, 4), &RED);
+        assert_eq!(drawn.get_pixel(4, 2), &RED);
+        assert_eq!(drawn.get_pixel(4, 4), &WHITE);
+        assert_eq!(image.get_pixel(2, 4), &WHITE);
+    }
+
+    #[test]
+    fn draw_labeled_detections_writes_label_above_box() {
+        let font_data = std::fs::read("./data/test_data/DejaVuSansMono.ttf").unwrap();
+        let font = ab_glyph::FontRef::try_from_slice(&font_data).unwrap();
+        let image = RgbImage::from_pixel(80, 40, WHITE);
+        let dets: Vec<Detection<BoundingBox>> = vec![Detection {
+            annotation: BoundingBox::new(5_f32, 20_f32, 70_f32, 35_f32, "test".to_string())
+                .unwrap(),
+            confidence: 0.9_f32,
+        }];
+        let color_map: HashMap<String, Rgb<u8>> = HashMap::from([("test".to_string(), RED)]);
+        let drawn = draw_labeled_detections(&image, &dets, &color_map, &font, 12_f32);
+        // The label sits on a red background in the 12 rows above the box, with white text.
+        let label_pixels: Vec<&Rgb<u8>> = (8..20)
+            .flat_map(|y| (5..40).map(move |x| (x, y)))
+            .map(|(x, y)| drawn.get_pixel(x, y))
+            .collect();
+        assert!(label_pixels.contains(&&RED));
+        assert!(label_pixels.iter().any(|pixel| **pixel != RED));
+        assert_eq!(drawn.get_pixel(5, 30), &RED);
+        assert_eq!(drawn.get_pixel(40, 2), &WHITE);
+        assert_eq!(drawn.get_pixel(30, 28), &WHITE);
+    }
+
+    #[test]
+    fn draw_keypoint_detections_fills_keypoint() {
+        let image = RgbImage::from_pixel(20, 20, WHITE);
+        let dets: Vec<Detection<BoundingBoxWithKeypoint>> = vec![Detection {
+            annotation: BoundingBoxWithKeypoint::new(
+                2_f32,
+                2_f32,
+                18_f32,
+                18_f32,
+                10_f32,
+                10_f32,
+                "test".to_string(),
+            )
+            .unwrap(),
+            confidence: 0.9_f32,
+        }];
+        let drawn = draw_keypoint_detections(&image, &dets, &HashMap::new());
+        assert_eq!(drawn.get_pixel(10, 10), &category_color("test"));
+    }
+
+    #[test]
+    fn category_color_is_deterministic() {
+        assert_eq!(category_color("systolic"), category_color("systolic"));
+        assert_ne!(category_color("systolic"), category_color("diastolic"));
+    }
+
+    #[test]
+    fn draw_detections_alpha_blends_interior() {
+        let image = RgbImage::from_pixel(10, 10, WHITE);
+        let dets: Vec<Detection<BoundingBox>> = vec![Detection {
+            annotation: BoundingBox::new(2_f32, 2_f32, 7_f32, 7_f32, "test".to_string()).unwrap(),
+            confidence: 0.9_f32,
+        }];
+        let color_map: HashMap<String, Rgb<u8>> = HashMap::from([("test".to_string(), RED)]);
+        let drawn = draw_detections_alpha(&image, &dets, &color_map, 0.5);
+        // Half red and half white is (255, 127.5, 127.5), which rounds to (255, 128, 128).
+        assert_eq!(drawn.get_pixel(4, 4), &Rgb([255, 128, 128]));
+        assert_eq!(drawn.get_pixel(2, 4), &RED);
+        assert_eq!(drawn.get_pixel(8, 8), &WHITE);
+    }
+}
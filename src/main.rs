@@ -1,6 +1,10 @@
 mod annotations;
+mod chart_extraction_error;
+mod digitization;
+mod image_transformation;
 mod image_utils;
 mod object_detection;
+mod registration;
 use annotations::bounding_box::BoundingBox;
 use image_utils::image_io::read_image_as_array4;
 use image_utils::tiling::OverlapProportion;
@@ -41,7 +45,7 @@ fn main() -> Result<(), Box<dyn Error>> {
     let now = Instant::now();
     let preds: Vec<_> = tile_and_predict::<BoundingBox, Yolov11BoundingBox>(
         &model,
-        img,
+        &img,
         640,
         OverlapProportion {
             numerator: 1_u32,
@@ -49,6 +53,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         },
         0.5_f32,
         0.1_f32,
+        None,
     )
     .unwrap();
     println!("Time elapsed: {:?}", now.elapsed());
@@ -0,0 +1,144 @@
+use crate::annotations::bounding_box::BoundingBoxGeometry;
+use crate::annotations::detection::Detection;
+use std::fmt::Display;
+
+/// Confidences are clamped this far from 0 and 1 so their logits stay finite.
+const CONFIDENCE_EPSILON: f32 = 1e-6;
+/// A prediction must overlap a ground truth box of its category by this IoU to count as correct.
+const MATCH_IOU_THRESHOLD: f32 = 0.5;
+/// The range searched by fit_temperature.
+const MIN_TEMPERATURE: f32 = 0.05;
+const MAX_TEMPERATURE: f32 = 20.0;
+const TEMPERATURE_SEARCH_ITERATIONS: usize = 60;
+
+fn logit(p: f32) -> f32 {
+    let p = p.clamp(CONFIDENCE_EPSILON, 1.0 - CONFIDENCE_EPSILON);
+    (p / (1.0 - p)).ln()
+}
+
+fn sigmoid(x: f32) -> f32 {
+    1.0 / (1.0 + (-x).exp())
+}
+
+/// Rescales confidences with temperature scaling, replacing each p with sigmoid(logit(p) / T).
+///
+/// A temperature above 1 pulls confidences towards 0.5, which corrects an overconfident model,
+/// and one below 1 pushes them apart. The order of the detections by confidence is unchanged.
+pub fn recalibrate_confidence<T: BoundingBoxGeometry + Display>(
+    dets: &mut [Detection<T>],
+    temperature: f32,
+) {
+    for det in dets.iter_mut() {
+        det.confidence = sigmoid(logit(det.confidence) / temperature);
+    }
+}
+
+/// Finds the temperature that best calibrates a model's confidences against ground truth.
+///
+/// Predictions are visited from most to least confident, and each is correct if it overlaps a
+/// not yet matched ground truth box of its category with an IoU of at least 0.5. The temperature
+/// minimizing the negative log-likelihood of those outcomes is then found by a golden section
+/// search over log temperature. Returns 1, leaving confidences unchanged, without predictions.
+pub fn fit_temperature<T: BoundingBoxGeometry + Display + Clone>(
+    preds: &[Detection<T>],
+    gts: &[T],
+) -> f32 {
+    if preds.is_empty() {
+        return 1.0;
+    }
+    let mut sorted_preds = preds.to_vec();
+    Detection::sort_by_confidence_desc(&mut sorted_preds);
+    let mut gt_is_matched = vec![false; gts.len()];
+    let outcomes: Vec<(f32, bool)> = sorted_preds
+        .iter()
+        .map(|pred| {
+            let best_gt = gts
+                .iter()
+                .enumerate()
+                .filter(|(gt_ix, gt)| {
+                    !gt_is_matched[*gt_ix] && gt.category() == pred.annotation.category()
+                })
+                .map(|(gt_ix, gt)| (gt_ix, pred.annotation.intersection_over_union(gt)))
+                .filter(|(_, iou)| *iou >= MATCH_IOU_THRESHOLD)
+                .max_by(|a, b| a.1.total_cmp(&b.1));
+            if let Some((gt_ix, _)) = best_gt {
+                gt_is_matched[gt_ix] = true;
+            }
+            (logit(pred.confidence), best_gt.is_some())
+        })
+        .collect();
+    let negative_log_likelihood = |log_temperature: f32| -> f32 {
+        let temperature = log_temperature.exp();
+        outcomes
+            .iter()
+            .map(|(logit, is_correct)| {
+                let p = sigmoid(logit / temperature)
+                    .clamp(CONFIDENCE_EPSILON, 1.0 - CONFIDENCE_EPSILON);
+                if *is_correct {
+                    -p.ln()
+                } else {
+                    -(1.0 - p).ln()
+                }
+            })
+            .sum()
+    };
+
+    let inverse_golden_ratio = (5_f32.sqrt() - 1.0) / 2.0;
+    let (mut low, mut high) = (MIN_TEMPERATURE.ln(), MAX_TEMPERATURE.ln());
+    for _ in 0..TEMPERATURE_SEARCH_ITERATIONS {
+        let lower_probe = high - inverse_golden_ratio * (high - low);
+        let upper_probe = low + inverse_golden_ratio * (high - low);
+        if negative_log_likelihood(lower_probe) < negative_log_likelihood(upper_probe) {
+            high = upper_probe;
+        } else {
+            low = lower_probe;
+        }
+    }
+    ((low + high) / 2.0).exp()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotations::bounding_box::BoundingBox;
+
+    fn create_testing_detection(left: f32, confidence: f32) -> Detection<BoundingBox> {
+        Detection {
+            annotation: BoundingBox::new(left, 0.0, left + 10.0, 10.0, "test".to_string()).unwrap(),
+            confidence,
+        }
+    }
+
+    #[test]
+    fn high_temperature_moves_confidences_towards_middle() {
+        let mut dets = vec![
+            create_testing_detection(0.0, 0.95),
+            create_testing_detection(0.0, 0.5),
+            create_testing_detection(0.0, 0.1),
+        ];
+        recalibrate_confidence(&mut dets, 2.0);
+        assert!(dets[0].confidence < 0.95 && dets[0].confidence > 0.5);
+        assert!((dets[1].confidence - 0.5).abs() < 1e-6);
+        assert!(dets[2].confidence > 0.1 && dets[2].confidence < 0.5);
+        // logit(0.95) / 2 = ln(19) / 2, so the new confidence is sqrt(19) / (1 + sqrt(19)).
+        assert!((dets[0].confidence - 19_f32.sqrt() / (1.0 + 19_f32.sqrt())).abs() < 1e-5);
+    }
+
+    #[test]
+    fn fit_temperature_calibrates_overconfident_predictions() {
+        // Ten predictions at 0.99, of which only the seven over a ground truth box are correct.
+        let preds: Vec<Detection<BoundingBox>> = (0..10)
+            .map(|ix| create_testing_detection(ix as f32 * 20.0, 0.99))
+            .collect();
+        let gts: Vec<BoundingBox> = preds[..7]
+            .iter()
+            .map(|pred| pred.annotation.clone())
+            .collect();
+        let temperature = fit_temperature(&preds, &gts);
+        assert!(temperature > 1.0);
+        let mut recalibrated = preds.clone();
+        recalibrate_confidence(&mut recalibrated, temperature);
+        assert!((recalibrated[0].confidence - 0.7).abs() < 1e-3);
+        assert_eq!(fit_temperature::<BoundingBox>(&[], &gts), 1.0);
+    }
+}
@@ -1,5 +1,10 @@
+pub mod calibration;
+pub mod model_config;
 pub mod object_detection_model;
 pub mod object_detection_utils;
 pub mod ort_inference_session;
+pub mod preprocessor;
+pub mod segmentation_model;
 pub mod yolov11_bounding_box;
 pub mod yolov11_pose_estimation;
+pub mod yolov11_segmentation;
@@ -0,0 +1,137 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// The settings needed to construct a model.
+///
+/// Every model takes the same arguments, and passing them positionally makes it easy to swap the
+/// width and height. This names them, and can be stored alongside the model as JSON.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ModelConfig {
+    pub model_path: PathBuf,
+    pub class_names: Vec<String>,
+    pub input_width: usize,
+    pub input_height: usize,
+    pub model_name: String,
+}
+
+/// The per channel mean and standard deviation a model's inputs are standardized with.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct Normalization {
+    pub mean: [f32; 3],
+    pub std: [f32; 3],
+}
+
+/// How the rows of a model's output tensor are laid out.
+///
+/// YOLOv11 exports put the channels first, as (1, 4 + classes, anchors), while some other
+/// exporters put them last, as (1, anchors, 4 + classes).
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq, Serialize)]
+pub enum OutputLayout {
+    #[default]
+    ChannelsFirst,
+    ChannelsLast,
+}
+
+/// Describes a model, read from a JSON file kept next to the .onnx file.
+///
+/// Keeping this with the model means the class names, input size and preprocessing can't drift
+/// out of sync with the weights they belong to. Missing normalization means the model takes raw
+/// [0, 1] values, and a missing output layout defaults to ChannelsFirst.
+#[derive(Clone, Debug, Deserialize, PartialEq, Serialize)]
+pub struct ModelMetadata {
+    pub class_names: Vec<String>,
+    pub input_width: usize,
+    pub input_height: usize,
+    #[serde(default)]
+    pub normalization: Option<Normalization>,
+    #[serde(default)]
+    pub output_layout: OutputLayout,
+}
+
+/// A set of custom errors for more informative error handling.
+#[derive(Debug)]
+pub enum ModelMetadataError {
+    Io(std::io::Error),
+    Parse(serde_json::Error),
+    Ort(ort::Error),
+}
+
+impl fmt::Display for ModelMetadataError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ModelMetadataError::Io(e) => write!(f, "Failed to read model metadata: {}", e),
+            ModelMetadataError::Parse(e) => write!(f, "Failed to parse model metadata: {}", e),
+            ModelMetadataError::Ort(e) => write!(f, "Failed to load model: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ModelMetadataError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ModelMetadataError::Io(e) => Some(e),
+            ModelMetadataError::Parse(e) => Some(e),
+            ModelMetadataError::Ort(e) => Some(e),
+        }
+    }
+}
+
+impl ModelMetadata {
+    /// Reads model metadata from a JSON file.
+    pub fn from_json(path: &Path) -> Result<Self, ModelMetadataError> {
+        let contents = fs::read_to_string(path).map_err(ModelMetadataError::Io)?;
+        serde_json::from_str(&contents).map_err(ModelMetadataError::Parse)
+    }
+
+    /// Builds the config for the model at model_path, named after the model's file stem.
+    pub fn to_config(&self, model_path: &Path) -> ModelConfig {
+        let model_name = model_path
+            .file_stem()
+            .map(|stem| stem.to_string_lossy().to_string())
+            .unwrap_or_default();
+        ModelConfig {
+            model_path: model_path.to_path_buf(),
+            class_names: self.class_names.clone(),
+            input_width: self.input_width,
+            input_height: self.input_height,
+            model_name,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn model_metadata_from_json() {
+        let metadata =
+            ModelMetadata::from_json(Path::new("./data/model_metadata/yolo11n.json")).unwrap();
+        assert_eq!(metadata.class_names.len(), 80);
+        assert_eq!(metadata.class_names[0], "person");
+        assert_eq!((metadata.input_width, metadata.input_height), (640, 640));
+        assert_eq!(metadata.normalization, None);
+        assert_eq!(metadata.output_layout, OutputLayout::ChannelsFirst);
+        let config = metadata.to_config(Path::new("./data/models/yolo11n.onnx"));
+        assert_eq!(config.model_name, "yolo11n");
+    }
+
+    #[test]
+    fn model_metadata_optional_fields() {
+        let metadata: ModelMetadata = serde_json::from_str(
+            r#"{"class_names": ["a"], "input_width": 32, "input_height": 16,
+                "normalization": {"mean": [0.5, 0.5, 0.5], "std": [0.25, 0.25, 0.25]}}"#,
+        )
+        .unwrap();
+        assert_eq!(metadata.normalization.unwrap().std, [0.25, 0.25, 0.25]);
+        assert_eq!(metadata.output_layout, OutputLayout::ChannelsFirst);
+    }
+
+    #[test]
+    fn model_metadata_missing_file() {
+        let result = ModelMetadata::from_json(Path::new("./data/model_metadata/missing.json"));
+        assert!(matches!(result, Err(ModelMetadataError::Io(_))));
+    }
+}
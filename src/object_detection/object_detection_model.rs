@@ -1,7 +1,44 @@
-use crate::annotations::bounding_box::BoundingBoxGeometry;
+use crate::annotations::bounding_box::{BoundingBoxError, BoundingBoxGeometry};
 use crate::annotations::detection::Detection;
 use ndarray::{ArrayBase, Dim, ViewRepr};
-use std::fmt::Display;
+use std::fmt::{self, Display};
+
+/// A set of custom errors for more informative error handling.
+#[derive(Debug)]
+pub enum InferenceError {
+    InputConstruction(ort::Error),
+    SessionRun(ort::Error),
+    OutputExtraction(ort::Error),
+    InvalidBox(BoundingBoxError),
+}
+
+impl fmt::Display for InferenceError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InferenceError::InputConstruction(e) => {
+                write!(f, "Failed to construct the model's inputs: {}", e)
+            }
+            InferenceError::SessionRun(e) => write!(f, "Failed to run the model: {}", e),
+            InferenceError::OutputExtraction(e) => {
+                write!(f, "Failed to read the model's outputs: {}", e)
+            }
+            InferenceError::InvalidBox(e) => {
+                write!(f, "The model produced an invalid box: {}", e)
+            }
+        }
+    }
+}
+
+impl std::error::Error for InferenceError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            InferenceError::InputConstruction(e)
+            | InferenceError::SessionRun(e)
+            | InferenceError::OutputExtraction(e) => Some(e),
+            InferenceError::InvalidBox(e) => Some(e),
+        }
+    }
+}
 
 /// Defines a trait that all object detection models must follow.
 pub trait ObjectDetectionModel<T: BoundingBoxGeometry + Display> {
@@ -15,9 +52,12 @@ pub trait ObjectDetectionModel<T: BoundingBoxGeometry + Display> {
     ///
     /// If you want to reuse this and skip the view making process, changing ViewRepr<f32>
     /// to OwnedRepr<f32> will likely work.
+    ///
+    /// Errors from the model, such as an input of the wrong shape, are returned rather than
+    /// panicking, so one bad tile doesn't bring down a whole batch.
     fn run_inference(
         &self,
         input_array: ArrayBase<ViewRepr<&f32>, Dim<[usize; 4]>>,
         confidence: f32,
-    ) -> Vec<Detection<T>>;
+    ) -> Result<Vec<Detection<T>>, InferenceError>;
 }
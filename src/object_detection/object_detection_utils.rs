@@ -1,9 +1,13 @@
-use crate::annotations::bounding_box::{BoundingBox, BoundingBoxGeometry};
+use crate::annotations::bounding_box::{BoundingBox, BoundingBoxGeometry, Category};
+use crate::annotations::bounding_box_with_keypoint::BoundingBoxWithKeypoint;
 use crate::annotations::detection::Detection;
-use crate::image_utils::tiling::{OverlapProportion, TilingError, tile_image};
-use crate::object_detection::object_detection_model::ObjectDetectionModel;
-use ndarray::{ArrayBase, Dim, OwnedRepr, ViewRepr};
-use std::fmt::Display;
+use crate::annotations::point::Point;
+use crate::image_utils::tiling::{OverlapProportion, TilingError, tile_count, tile_iter};
+use crate::object_detection::object_detection_model::{InferenceError, ObjectDetectionModel};
+use image::Rgb;
+use ndarray::{Array, ArrayBase, Dim, OwnedRepr};
+use std::collections::HashMap;
+use std::fmt::{self, Display};
 use std::fs::File;
 use std::io::{self, BufRead, BufReader};
 use std::path::Path;
@@ -14,19 +18,86 @@ pub fn read_classes_txt_file(filepath: &Path) -> io::Result<Vec<String>> {
     BufReader::new(File::open(filepath)?).lines().collect()
 }
 
+/// A class from a label file that gives each class an explicit id and display color.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClassInfo {
+    pub id: usize,
+    pub name: String,
+    pub color: Rgb<u8>,
+}
+
+/// Reads a label file with one "id,name,#rrggbb" class per line.
+///
+/// Ids don't need to be contiguous or in order. Blank lines and lines starting with '#' are
+/// skipped, and any other line that doesn't parse is an InvalidData error naming its line number.
+pub fn read_classes_csv(filepath: &Path) -> io::Result<Vec<ClassInfo>> {
+    let mut classes: Vec<ClassInfo> = Vec::new();
+    for (line_ix, line) in BufReader::new(File::open(filepath)?).lines().enumerate() {
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let class = parse_class_csv_line(line).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "Failed to read classes, line {} is not \"id,name,#rrggbb\": {}",
+                    line_ix + 1,
+                    line
+                ),
+            )
+        })?;
+        classes.push(class);
+    }
+    Ok(classes)
+}
+
+fn parse_class_csv_line(line: &str) -> Option<ClassInfo> {
+    let [id, name, color] = line.split(',').map(str::trim).collect::<Vec<_>>()[..] else {
+        return None;
+    };
+    let hex = color.strip_prefix('#').filter(|hex| hex.len() == 6)?;
+    let channel = |ix: usize| u8::from_str_radix(hex.get(ix..ix + 2)?, 16).ok();
+    Some(ClassInfo {
+        id: id.parse().ok()?,
+        name: name.to_string(),
+        color: Rgb([channel(0)?, channel(2)?, channel(4)?]),
+    })
+}
+
 /// Non maxmimum suppression is a way of removing duplicate detections.
 pub fn non_maximum_suppression<T: BoundingBoxGeometry + Display>(
+    detections: Vec<Detection<T>>,
+    iou_threshold: f32,
+) -> Vec<Detection<T>> {
+    suppress_overlapping(detections, iou_threshold, true)
+}
+
+/// Non maximum suppression that also removes overlapping boxes of different categories.
+///
+/// Useful when a model's classes are mutually exclusive, so two boxes over the same object with
+/// different labels mean the model was unsure which label to give it.
+pub fn class_agnostic_non_maximum_suppression<T: BoundingBoxGeometry + Display>(
+    detections: Vec<Detection<T>>,
+    iou_threshold: f32,
+) -> Vec<Detection<T>> {
+    suppress_overlapping(detections, iou_threshold, false)
+}
+
+fn suppress_overlapping<T: BoundingBoxGeometry + Display>(
     mut detections: Vec<Detection<T>>,
     iou_threshold: f32,
+    class_aware: bool,
 ) -> Vec<Detection<T>> {
-    detections.sort_by(|a, b| b.confidence.partial_cmp(&a.confidence).unwrap());
+    Detection::sort_by_confidence_desc(&mut detections);
     let mut detections_to_remove: Vec<bool> = vec![false; detections.len()];
     for (current_index, current_det) in detections.iter().enumerate() {
         for (other_index, other_det) in detections[current_index + 1..].iter().enumerate() {
             if detections_to_remove[current_index + other_index + 1] {
                 continue;
             }
-            if current_det.annotation.category() != other_det.annotation.category() {
+            if class_aware && current_det.annotation.category() != other_det.annotation.category() {
                 continue;
             }
             let iou = current_det
@@ -42,43 +113,665 @@ pub fn non_maximum_suppression<T: BoundingBoxGeometry + Display>(
     detections
 }
 
+/// Computes the IoU of every pair of detections, regardless of category.
+///
+/// The matrix is symmetric, and its diagonal is 1 even for boxes with zero area.
+pub fn iou_matrix<T: BoundingBoxGeometry + Display>(
+    dets: &[Detection<T>],
+) -> ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>> {
+    let mut matrix: ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>> = Array::eye(dets.len());
+    for (i, current_det) in dets.iter().enumerate() {
+        for (j, other_det) in dets.iter().enumerate().skip(i + 1) {
+            let iou = current_det
+                .annotation
+                .intersection_over_union(&other_det.annotation);
+            matrix[[i, j]] = iou;
+            matrix[[j, i]] = iou;
+        }
+    }
+    matrix
+}
+
+/// A variant of non maximum suppression that compares box centers instead of overlap.
+///
+/// Among boxes of the same category, the less confident box is removed when the two centers are
+/// closer than min_center_distance. This suits small boxes laid out on a grid (checkboxes, digit
+/// cells), where two detections of one cell can have a low IoU while neighboring cells overlap.
+pub fn non_maximum_suppression_by_distance<T: BoundingBoxGeometry + Display>(
+    mut detections: Vec<Detection<T>>,
+    min_center_distance: f32,
+) -> Vec<Detection<T>> {
+    Detection::sort_by_confidence_desc(&mut detections);
+    let mut detections_to_remove: Vec<bool> = vec![false; detections.len()];
+    for (current_index, current_det) in detections.iter().enumerate() {
+        if detections_to_remove[current_index] {
+            continue;
+        }
+        let (current_x, current_y) = current_det.annotation.center();
+        for (other_index, other_det) in detections[current_index + 1..].iter().enumerate() {
+            if current_det.annotation.category() != other_det.annotation.category() {
+                continue;
+            }
+            let (other_x, other_y) = other_det.annotation.center();
+            let distance = (current_x - other_x).hypot(current_y - other_y);
+            if distance < min_center_distance {
+                detections_to_remove[current_index + other_index + 1] = true;
+            }
+        }
+    }
+    let mut drop_iter = detections_to_remove.iter();
+    detections.retain(|_| !drop_iter.next().unwrap());
+    detections
+}
+
+/// Drops low confidence detections and keeps at most max_keep of the rest.
+///
+/// Detections below min_confidence are removed, then if max_keep is given only the max_keep
+/// most confident remain. The result is sorted by descending confidence. Running this before NMS
+/// or matching keeps busy pages from flooding them with unlikely boxes.
+pub fn filter_detections<T: BoundingBoxGeometry + Display>(
+    mut dets: Vec<Detection<T>>,
+    min_confidence: f32,
+    max_keep: Option<usize>,
+) -> Vec<Detection<T>> {
+    dets.retain(|det| det.confidence >= min_confidence);
+    Detection::sort_by_confidence_desc(&mut dets);
+    if let Some(max_keep) = max_keep {
+        dets.truncate(max_keep);
+    }
+    dets
+}
+
+/// Keeps only the detections for which the predicate returns true.
+///
+/// This is the building block for applying domain constraints, such as a plausible box size or
+/// a region of the page, before matching.
+pub fn retain_detections<T: BoundingBoxGeometry + Display>(
+    mut dets: Vec<Detection<T>>,
+    predicate: impl Fn(&Detection<T>) -> bool,
+) -> Vec<Detection<T>> {
+    dets.retain(|det| predicate(det));
+    dets
+}
+
+/// Keeps only the detections whose box area lies within [min_area, max_area].
+///
+/// A box covering half of the page, or one only a few pixels across, is almost surely a false
+/// positive.
+pub fn retain_by_area<T: BoundingBoxGeometry + Display>(
+    dets: Vec<Detection<T>>,
+    min_area: f32,
+    max_area: f32,
+) -> Vec<Detection<T>> {
+    retain_detections(dets, |det| {
+        let area = det.annotation.area();
+        min_area <= area && area <= max_area
+    })
+}
+
+/// Keeps only the detections whose center lies within an image of the original size.
+///
+/// Images padded to fit the tiling grid can produce phantom boxes along the edge of the fill.
+/// These are dropped rather than clipped, since a box whose center is in the padding belongs to
+/// nothing on the page.
+pub fn retain_within_image<T: BoundingBoxGeometry + Display>(
+    dets: Vec<Detection<T>>,
+    original_width: u32,
+    original_height: u32,
+) -> Vec<Detection<T>> {
+    retain_detections(dets, |det| {
+        let (x, y) = det.annotation.center();
+        (0_f32..=original_width as f32).contains(&x)
+            && (0_f32..=original_height as f32).contains(&y)
+    })
+}
+
+/// Maps detections found on an image of from_size onto the same image at to_size.
+///
+/// Detecting on a downscaled copy is much faster, and this brings the results back to full
+/// resolution. Sizes are (width, height), and keypoints are scaled along with their boxes.
+pub fn rescale_detections<T: BoundingBoxGeometry + Display>(
+    mut dets: Vec<Detection<T>>,
+    from_size: (u32, u32),
+    to_size: (u32, u32),
+) -> Vec<Detection<T>> {
+    let sx = to_size.0 as f32 / from_size.0 as f32;
+    let sy = to_size.1 as f32 / from_size.1 as f32;
+    for det in dets.iter_mut() {
+        det.annotation.scale(sx, sy);
+    }
+    dets
+}
+
+/// Picks the category of a cluster of detections by a confidence weighted vote.
+///
+/// When boxes from several tiles are fused into one, a minority of them can be mislabeled, so
+/// the fused box should take the category with the most total confidence rather than the label
+/// of whichever box came first. Ties go to the category seen first. Returns None for an empty
+/// cluster.
+pub fn majority_category<T: BoundingBoxGeometry + Display>(
+    dets: &[Detection<T>],
+) -> Option<T::Category> {
+    let mut votes: Vec<(&T::Category, f32)> = Vec::new();
+    for det in dets {
+        let category = det.annotation.category();
+        match votes.iter_mut().find(|(voted, _)| *voted == category) {
+            Some((_, total)) => *total += det.confidence,
+            None => votes.push((category, det.confidence)),
+        }
+    }
+    votes
+        .into_iter()
+        .reduce(|best, vote| if vote.1 > best.1 { vote } else { best })
+        .map(|(category, _)| category.clone())
+}
+
+/// Splits detections into buckets keyed by the category of their annotation.
+pub fn group_by_category<T: BoundingBoxGeometry + Display>(
+    dets: Vec<Detection<T>>,
+) -> HashMap<T::Category, Vec<Detection<T>>> {
+    let mut groups: HashMap<T::Category, Vec<Detection<T>>> = HashMap::new();
+    for det in dets {
+        groups
+            .entry(det.annotation.category().clone())
+            .or_default()
+            .push(det);
+    }
+    groups
+}
+
+/// Renames detection categories using a mapping, leaving unmapped categories untouched.
+///
+/// Different models reuse the same class ids for different things, so categories should be
+/// namespaced with this before merging detections from several models.
+pub fn remap_categories<T: BoundingBoxGeometry + Display>(
+    dets: &mut [Detection<T>],
+    mapping: &HashMap<T::Category, T::Category>,
+) {
+    for det in dets.iter_mut() {
+        if let Some(new_category) = mapping.get(det.annotation.category()) {
+            *det.annotation.category_mut() = new_category.clone();
+        }
+    }
+}
+
+/// How close (in pixels) a box's edge must be to a tile boundary to be considered cut by it.
+const SEAM_TOLERANCE: f32 = 1_f32;
+
+/// Checks whether two detections are the two halves of an object cut by a tile seam.
+///
+/// Each detection comes with the (row_ix, col_ix) of the tile it was found in. The halves must
+/// come from different tiles, the first tile being left of (or above) the second, and be cut by
+/// their own tiles: the first box must end at its tile's far edge and the second must start at
+/// its tile's near edge. The facing edges must meet or overlap, and the boxes must overlap along
+/// the other axis. Neighbouring objects seen whole in the same tile are never merged.
+fn are_split_by_seam<T: BoundingBoxGeometry + Display>(
+    (first, (first_row, first_col)): &(Detection<T>, (usize, usize)),
+    (second, (second_row, second_col)): &(Detection<T>, (usize, usize)),
+    stride: u32,
+    tile_size: u32,
+) -> bool {
+    if first.annotation.category() != second.annotation.category() {
+        return false;
+    }
+    let tile_start = |ix: usize| (ix as u32 * stride) as f32;
+    let tile_end = |ix: usize| (ix as u32 * stride + tile_size) as f32;
+    let a = &first.annotation;
+    let b = &second.annotation;
+    let overlap_vertically = a.top() < b.bottom() && b.top() < a.bottom();
+    let overlap_horizontally = a.left() < b.right() && b.left() < a.right();
+    let split_horizontally = overlap_vertically
+        && first_col < second_col
+        && (a.right() - tile_end(*first_col)).abs() <= SEAM_TOLERANCE
+        && (b.left() - tile_start(*second_col)).abs() <= SEAM_TOLERANCE
+        && b.left() <= a.right() + SEAM_TOLERANCE;
+    let split_vertically = overlap_horizontally
+        && first_row < second_row
+        && (a.bottom() - tile_end(*first_row)).abs() <= SEAM_TOLERANCE
+        && (b.top() - tile_start(*second_row)).abs() <= SEAM_TOLERANCE
+        && b.top() <= a.bottom() + SEAM_TOLERANCE;
+    split_horizontally || split_vertically
+}
+
+/// Finds the representative of a detection's group, flattening the path to it along the way.
+fn find_group(parents: &mut [usize], mut ix: usize) -> usize {
+    while parents[ix] != ix {
+        parents[ix] = parents[parents[ix]];
+        ix = parents[ix];
+    }
+    ix
+}
+
+/// Stitches together detections of a single object that was cut by tile seams.
+///
+/// When an object straddles the edge of a tile, the tile only sees part of it, and an adjacent
+/// tile may see the rest. The two partial boxes have a low IoU, so non maximum suppression will
+/// not merge them. Each detection is given along with the (row_ix, col_ix) of the tile it was
+/// found in, and pairs of same-category boxes cut by their tiles' shared seam (see
+/// are_split_by_seam) are grouped, so objects cut by several seams are fully reassembled. Each
+/// group becomes its union box, with the confidence and category of its most confident member.
+pub fn merge_boundary_detections<T: BoundingBoxGeometry + Display>(
+    detections: Vec<(Detection<T>, (usize, usize))>,
+    stride: u32,
+    tile_size: u32,
+) -> Vec<Detection<T>> {
+    let mut parents: Vec<usize> = (0..detections.len()).collect();
+    for first_ix in 0..detections.len() {
+        for second_ix in 0..detections.len() {
+            if first_ix != second_ix
+                && are_split_by_seam(
+                    &detections[first_ix],
+                    &detections[second_ix],
+                    stride,
+                    tile_size,
+                )
+            {
+                let first_group = find_group(&mut parents, first_ix);
+                let second_group = find_group(&mut parents, second_ix);
+                parents[second_group] = first_group;
+            }
+        }
+    }
+    let mut merged: Vec<Option<Detection<T>>> = (0..detections.len()).map(|_| None).collect();
+    for (ix, (det, _)) in detections.into_iter().enumerate() {
+        let group = find_group(&mut parents, ix);
+        merged[group] = Some(match merged[group].take() {
+            None => det,
+            Some(other) => {
+                let (mut kept, dropped) = if det.confidence > other.confidence {
+                    (det, other)
+                } else {
+                    (other, det)
+                };
+                let kept_box = &mut kept.annotation;
+                *kept_box.left_mut() = kept_box.left().min(dropped.annotation.left());
+                *kept_box.top_mut() = kept_box.top().min(dropped.annotation.top());
+                *kept_box.right_mut() = kept_box.right().max(dropped.annotation.right());
+                *kept_box.bottom_mut() = kept_box.bottom().max(dropped.annotation.bottom());
+                kept
+            }
+        });
+    }
+    merged.into_iter().flatten().collect()
+}
+
+/// Fuses overlapping pose detections of the same category instead of suppressing them.
+///
+/// Non maximum suppression keeps only the most confident of a set of duplicate detections, which
+/// throws away the other tiles' estimates of the keypoint. Here, each detection joins the cluster
+/// of the first more confident detection of its category it overlaps by more than iou_threshold.
+/// Each cluster becomes one detection whose box edges and keypoint are the confidence weighted
+/// means of its members, and whose confidence is that of its most confident member.
+pub fn fuse_keypoint_detections<C: Category>(
+    mut detections: Vec<Detection<BoundingBoxWithKeypoint<C>>>,
+    iou_threshold: f32,
+) -> Vec<Detection<BoundingBoxWithKeypoint<C>>> {
+    Detection::sort_by_confidence_desc(&mut detections);
+    let mut clusters: Vec<Vec<Detection<BoundingBoxWithKeypoint<C>>>> = Vec::new();
+    for det in detections {
+        let cluster = clusters.iter_mut().find(|cluster| {
+            let leader = &cluster[0].annotation;
+            leader.category() == det.annotation.category()
+                && leader.intersection_over_union(&det.annotation) > iou_threshold
+        });
+        match cluster {
+            Some(cluster) => cluster.push(det),
+            None => clusters.push(vec![det]),
+        }
+    }
+    clusters.into_iter().map(fuse_keypoint_cluster).collect()
+}
+
+fn fuse_keypoint_cluster<C: Category>(
+    cluster: Vec<Detection<BoundingBoxWithKeypoint<C>>>,
+) -> Detection<BoundingBoxWithKeypoint<C>> {
+    let total_weight: f32 = cluster.iter().map(|det| det.confidence).sum();
+    if cluster.len() == 1 || total_weight <= 0.0 {
+        return cluster.into_iter().next().unwrap();
+    }
+    let weighted_mean = |value: &dyn Fn(&BoundingBoxWithKeypoint<C>) -> f32| -> f32 {
+        cluster
+            .iter()
+            .map(|det| det.confidence * value(&det.annotation))
+            .sum::<f32>()
+            / total_weight
+    };
+    // Rounding must not turn a box of zero width or height inside out, so the far edges are
+    // kept at or past the near ones.
+    let left = weighted_mean(&|a| a.left());
+    let top = weighted_mean(&|a| a.top());
+    let right = weighted_mean(&|a| a.right()).max(left);
+    let bottom = weighted_mean(&|a| a.bottom()).max(top);
+    let annotation = BoundingBoxWithKeypoint::new(
+        left,
+        top,
+        right,
+        bottom,
+        weighted_mean(&|a| a.get_keypoint_x()),
+        weighted_mean(&|a| a.get_keypoint_y()),
+        cluster[0].annotation.category().clone(),
+    )
+    .expect("The far edges are clamped to the near ones, so the box is valid.");
+    Detection {
+        annotation,
+        confidence: cluster[0].confidence,
+    }
+}
+
+/// How detections sharing a category are collapsed into a single centroid.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DedupStrategy {
+    /// Uses the center of the detection with the highest confidence.
+    HighestConfidence,
+    /// Uses the mean of the centers of every detection.
+    MeanCenter,
+    /// Uses the mean of the centers of every detection, weighted by confidence.
+    ConfidenceWeighted,
+}
+
+/// Averages the centers of the detections' boxes, weighting each by its confidence.
+///
+/// Near duplicate detections of one object jitter around its true location, and weighting by
+/// confidence keeps the estimate close to the detections the model is sure of. Takes anything
+/// that iterates over detections, such as a slice or a vector of references. Returns None if
+/// there are no detections or their confidences sum to zero.
+pub fn confidence_weighted_center<'a, T: BoundingBoxGeometry + Display + 'a>(
+    dets: impl IntoIterator<Item = &'a Detection<T>>,
+) -> Option<Point> {
+    let mut weighted_x: f32 = 0.0;
+    let mut weighted_y: f32 = 0.0;
+    let mut total_weight: f32 = 0.0;
+    for det in dets {
+        let (x, y) = det.annotation.center();
+        weighted_x += det.confidence * x;
+        weighted_y += det.confidence * y;
+        total_weight += det.confidence;
+    }
+    if total_weight <= 0.0 {
+        return None;
+    }
+    Some(Point {
+        x: weighted_x / total_weight,
+        y: weighted_y / total_weight,
+    })
+}
+
+/// Reduces a set of detections to one centroid per category.
+///
+/// This is used to build the maps from landmark name to location that the registration
+/// algorithms take, for instance from detections on the clean chart.
+pub fn detections_to_centroids<T: BoundingBoxGeometry + Display>(
+    dets: &[Detection<T>],
+    dedup: DedupStrategy,
+) -> HashMap<T::Category, Point> {
+    match dedup {
+        DedupStrategy::HighestConfidence => {
+            let mut best: HashMap<T::Category, &Detection<T>> = HashMap::new();
+            for det in dets {
+                let category = det.annotation.category();
+                match best.get(category) {
+                    Some(current) if current.confidence >= det.confidence => {}
+                    _ => {
+                        best.insert(category.clone(), det);
+                    }
+                }
+            }
+            best.into_iter()
+                .map(|(category, det)| {
+                    let (x, y) = det.annotation.center();
+                    (category, Point { x, y })
+                })
+                .collect()
+        }
+        DedupStrategy::MeanCenter => {
+            let mut sums: HashMap<T::Category, (f32, f32, usize)> = HashMap::new();
+            for det in dets {
+                let (x, y) = det.annotation.center();
+                let entry = sums
+                    .entry(det.annotation.category().clone())
+                    .or_insert((0_f32, 0_f32, 0));
+                entry.0 += x;
+                entry.1 += y;
+                entry.2 += 1;
+            }
+            sums.into_iter()
+                .map(|(category, (x, y, count))| {
+                    let count = count as f32;
+                    (
+                        category,
+                        Point {
+                            x: x / count,
+                            y: y / count,
+                        },
+                    )
+                })
+                .collect()
+        }
+        DedupStrategy::ConfidenceWeighted => {
+            let mut groups: HashMap<T::Category, Vec<&Detection<T>>> = HashMap::new();
+            for det in dets {
+                groups
+                    .entry(det.annotation.category().clone())
+                    .or_default()
+                    .push(det);
+            }
+            groups
+                .into_iter()
+                .filter_map(|(category, group)| {
+                    Some((category, confidence_weighted_center(group)?))
+                })
+                .collect()
+        }
+    }
+}
+
+/// Lowers the confidence threshold of tile_and_predict for detections cut by a tile's edge.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct EdgeConfidenceRelief {
+    /// How much lower the threshold is for detections touching an edge.
+    pub relief: f32,
+    /// How close (in pixels) a box must come to a tile's edge to be considered touching it.
+    pub edge_margin: f32,
+}
+
+/// Checks whether a box (in tile coordinates) comes within edge_margin of an edge of the tile
+/// that lies inside the image, rather than on the image's border.
+fn touches_interior_tile_edge<T: BoundingBoxGeometry>(
+    annotation: &T,
+    tile_size: u32,
+    edge_margin: f32,
+    (row_ix, col_ix): (usize, usize),
+    (num_rows, num_columns): (usize, usize),
+) -> bool {
+    let far_edge = tile_size as f32 - edge_margin;
+    (col_ix > 0 && annotation.left() <= edge_margin)
+        || (row_ix > 0 && annotation.top() <= edge_margin)
+        || (col_ix + 1 < num_columns && annotation.right() >= far_edge)
+        || (row_ix + 1 < num_rows && annotation.bottom() >= far_edge)
+}
+
+/// A set of custom errors for more informative error handling.
+#[derive(Debug)]
+pub enum TileAndPredictError {
+    Tiling(TilingError),
+    Inference(InferenceError),
+}
+
+impl fmt::Display for TileAndPredictError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TileAndPredictError::Tiling(e) => write!(f, "{}", e),
+            TileAndPredictError::Inference(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl std::error::Error for TileAndPredictError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TileAndPredictError::Tiling(e) => Some(e),
+            TileAndPredictError::Inference(e) => Some(e),
+        }
+    }
+}
+
+impl From<TilingError> for TileAndPredictError {
+    fn from(e: TilingError) -> Self {
+        TileAndPredictError::Tiling(e)
+    }
+}
+
+impl From<InferenceError> for TileAndPredictError {
+    fn from(e: InferenceError) -> Self {
+        TileAndPredictError::Inference(e)
+    }
+}
+
 /// Predicts small objects on an image using image tiling.
 ///
-/// Tiles an image, predicts on each tile, then corrects the detection's coordinates and
-/// applies NMS to them.
+/// Tiles an image, predicts on each tile, then corrects the detection's coordinates, stitches
+/// together objects cut by tile seams and applies NMS to them.
+///
+/// Objects cut by a tile's edge are scored lower than whole ones. With edge_confidence_relief,
+/// detections within its edge_margin of an edge shared with another tile only need a confidence
+/// of confidence - relief, since they are merged with the same object seen in the neighboring
+/// tile.
 pub fn tile_and_predict<T: BoundingBoxGeometry + Display, U: ObjectDetectionModel<T>>(
     model: &U,
-    image_array: ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>>,
+    image_array: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>>,
     tile_size: u32,
     overlap_proportion: OverlapProportion,
     confidence: f32,
     nms_iou_threshold: f32,
-) -> Result<Vec<Detection<T>>, TilingError> {
-    let tiles: Vec<Vec<ArrayBase<ViewRepr<&f32>, Dim<[usize; 4]>>>> =
-        tile_image(&image_array, tile_size, overlap_proportion)?;
+    edge_confidence_relief: Option<EdgeConfidenceRelief>,
+) -> Result<Vec<Detection<T>>, TileAndPredictError> {
+    let (num_rows, num_columns) = tile_count(
+        image_array.shape()[3] as u32,
+        image_array.shape()[2] as u32,
+        tile_size,
+        overlap_proportion,
+    )?;
+    let tiles = tile_iter(image_array, tile_size, overlap_proportion)?;
     let stride: u32 = (tile_size * overlap_proportion.numerator) / overlap_proportion.denominator;
-    let mut detections: Vec<Detection<T>> = Vec::new();
-    for (row_ix, row_of_tiles) in tiles.iter().enumerate() {
-        for (col_ix, tile) in row_of_tiles.iter().enumerate() {
-            let preds = model.run_inference(*tile, confidence);
-            for mut pred in preds {
-                let x_correction = ((col_ix as u32) * stride) as f32;
-                let y_correction = ((row_ix as u32) * stride) as f32;
-                *pred.annotation.left_mut() += x_correction;
-                *pred.annotation.top_mut() += y_correction;
-                *pred.annotation.right_mut() += x_correction;
-                *pred.annotation.bottom_mut() += y_correction;
-                detections.push(pred);
+    let edge_confidence = match edge_confidence_relief {
+        Some(edge_relief) => confidence - edge_relief.relief,
+        None => confidence,
+    };
+    let mut tiled_detections: Vec<(Detection<T>, (usize, usize))> = Vec::new();
+    for (row_ix, col_ix, tile) in tiles {
+        let preds = model.run_inference(tile, edge_confidence)?;
+        for mut pred in preds {
+            let passes_threshold = pred.confidence >= confidence
+                || edge_confidence_relief.is_some_and(|edge_relief| {
+                    touches_interior_tile_edge(
+                        &pred.annotation,
+                        tile_size,
+                        edge_relief.edge_margin,
+                        (row_ix, col_ix),
+                        (num_rows as usize, num_columns as usize),
+                    )
+                });
+            if !passes_threshold {
+                continue;
             }
+            let x_correction = ((col_ix as u32) * stride) as f32;
+            let y_correction = ((row_ix as u32) * stride) as f32;
+            pred.annotation.translate(x_correction, y_correction);
+            tiled_detections.push((pred, (row_ix, col_ix)));
         }
     }
-    detections = non_maximum_suppression(detections, nms_iou_threshold);
-    Ok(detections)
+    let detections = merge_boundary_detections(tiled_detections, stride, tile_size);
+    Ok(non_maximum_suppression(detections, nms_iou_threshold))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ndarray::ViewRepr;
+
+    /// A model that finds a single fixed box in any tile containing a nonzero pixel.
+    struct TestingModel;
+
+    impl ObjectDetectionModel<BoundingBox> for TestingModel {
+        fn run_inference(
+            &self,
+            input_array: ArrayBase<ViewRepr<&f32>, Dim<[usize; 4]>>,
+            _confidence: f32,
+        ) -> Result<Vec<Detection<BoundingBox>>, InferenceError> {
+            if input_array.iter().all(|v| *v == 0_f32) {
+                return Ok(Vec::new());
+            }
+            Ok(vec![Detection {
+                annotation: BoundingBox::new(1_f32, 1_f32, 3_f32, 3_f32, "test".to_string())
+                    .unwrap(),
+                confidence: 0.9_f32,
+            }])
+        }
+    }
+
+    /// A model that finds a faint box two pixels from the left edge of every tile.
+    struct EdgeModel;
+
+    impl ObjectDetectionModel<BoundingBox> for EdgeModel {
+        fn run_inference(
+            &self,
+            _input_array: ArrayBase<ViewRepr<&f32>, Dim<[usize; 4]>>,
+            confidence: f32,
+        ) -> Result<Vec<Detection<BoundingBox>>, InferenceError> {
+            let det = Detection {
+                annotation: BoundingBox::new(2_f32, 4_f32, 4_f32, 5_f32, "test".to_string())
+                    .unwrap(),
+                confidence: 0.4_f32,
+            };
+            if det.confidence < confidence {
+                return Ok(Vec::new());
+            }
+            Ok(vec![det])
+        }
+    }
+
+    /// A pose model that finds a box with its keypoint at (2, 2) in every tile.
+    struct KeypointModel;
+
+    impl ObjectDetectionModel<BoundingBoxWithKeypoint> for KeypointModel {
+        fn run_inference(
+            &self,
+            _input_array: ArrayBase<ViewRepr<&f32>, Dim<[usize; 4]>>,
+            _confidence: f32,
+        ) -> Result<Vec<Detection<BoundingBoxWithKeypoint>>, InferenceError> {
+            Ok(vec![Detection {
+                annotation: BoundingBoxWithKeypoint::new(
+                    1_f32,
+                    1_f32,
+                    3_f32,
+                    3_f32,
+                    2_f32,
+                    2_f32,
+                    "test".to_string(),
+                )
+                .unwrap(),
+                confidence: 0.9_f32,
+            }])
+        }
+    }
+
+    /// A model whose every tile produces a box turned inside out.
+    struct FailingModel;
+
+    impl ObjectDetectionModel<BoundingBox> for FailingModel {
+        fn run_inference(
+            &self,
+            _input_array: ArrayBase<ViewRepr<&f32>, Dim<[usize; 4]>>,
+            _confidence: f32,
+        ) -> Result<Vec<Detection<BoundingBox>>, InferenceError> {
+            let bbox = BoundingBox::new(3_f32, 1_f32, 1_f32, 3_f32, "test".to_string())
+                .map_err(InferenceError::InvalidBox)?;
+            Ok(vec![Detection {
+                annotation: bbox,
+                confidence: 0.9_f32,
+            }])
+        }
+    }
 
     #[test]
     fn nms_no_overlap() {
@@ -145,6 +838,26 @@ mod tests {
         assert_eq!(true_dets, nms_result);
     }
 
+    #[test]
+    fn class_agnostic_nms_removes_different_classes() {
+        let dets: Vec<Detection<BoundingBox>> = vec![
+            Detection {
+                annotation: BoundingBox::new(0_f32, 0_f32, 4_f32, 4_f32, "test".to_string())
+                    .unwrap(),
+                confidence: 0.8_f32,
+            },
+            Detection {
+                annotation: BoundingBox::new(0_f32, 0_f32, 4_f32, 5_f32, "other".to_string())
+                    .unwrap(),
+                confidence: 0.6_f32,
+            },
+        ];
+        assert_eq!(non_maximum_suppression(dets.clone(), 0.5_f32).len(), 2);
+        let nms_result = class_agnostic_non_maximum_suppression(dets, 0.5_f32);
+        assert_eq!(nms_result.len(), 1);
+        assert_eq!(nms_result[0].annotation.category(), "test");
+    }
+
     #[test]
     fn nms_overlap_but_different_classes() {
         let dets: Vec<Detection<BoundingBox>> = vec![
@@ -201,4 +914,636 @@ mod tests {
         ];
         assert_eq!(true_dets, nms_result);
     }
+
+    fn create_testing_tiled_detection(
+        (left, top, right, bottom): (f32, f32, f32, f32),
+        category: &str,
+        confidence: f32,
+        tile: (usize, usize),
+    ) -> (Detection<BoundingBox>, (usize, usize)) {
+        (
+            Detection {
+                annotation: BoundingBox::new(left, top, right, bottom, category.to_string())
+                    .unwrap(),
+                confidence,
+            },
+            tile,
+        )
+    }
+
+    #[test]
+    fn merge_boundary_detections_across_seam() {
+        // With tiles of size 10 and no overlap, there is a seam at x = 10.
+        let dets = vec![
+            create_testing_tiled_detection((6.0, 2.0, 10.0, 6.0), "test", 0.7, (0, 0)),
+            create_testing_tiled_detection((10.0, 2.5, 14.0, 6.0), "test", 0.8, (0, 1)),
+        ];
+        let merged = merge_boundary_detections(dets, 10, 10);
+        let true_dets: Vec<Detection<BoundingBox>> = vec![Detection {
+            annotation: BoundingBox::new(6_f32, 2_f32, 14_f32, 6_f32, "test".to_string()).unwrap(),
+            confidence: 0.8_f32,
+        }];
+        assert_eq!(true_dets, merged);
+    }
+
+    #[test]
+    fn merge_boundary_detections_across_several_seams() {
+        // The object spans the whole middle tile, so all three pieces make up one box.
+        let dets = vec![
+            create_testing_tiled_detection((20.0, 2.0, 23.0, 6.0), "test", 0.6, (0, 2)),
+            create_testing_tiled_detection((6.0, 2.0, 10.0, 6.0), "test", 0.7, (0, 0)),
+            create_testing_tiled_detection((10.0, 2.0, 20.0, 6.0), "test", 0.8, (0, 1)),
+        ];
+        let merged = merge_boundary_detections(dets, 10, 10);
+        let true_dets: Vec<Detection<BoundingBox>> = vec![Detection {
+            annotation: BoundingBox::new(6_f32, 2_f32, 23_f32, 6_f32, "test".to_string()).unwrap(),
+            confidence: 0.8_f32,
+        }];
+        assert_eq!(true_dets, merged);
+    }
+
+    #[test]
+    fn merge_boundary_detections_ignores_boxes_off_seams() {
+        let dets = vec![
+            create_testing_tiled_detection((4.0, 2.0, 8.0, 6.0), "test", 0.7, (0, 0)),
+            create_testing_tiled_detection((8.0, 2.0, 12.0, 6.0), "test", 0.8, (0, 1)),
+            create_testing_tiled_detection((10.0, 2.0, 14.0, 6.0), "other", 0.8, (0, 1)),
+        ];
+        let merged = merge_boundary_detections(dets, 10, 10);
+        assert_eq!(merged.len(), 3);
+    }
+
+    #[test]
+    fn merge_boundary_detections_keeps_neighbours_apart() {
+        // Tiles of size 10 with a stride of 5. The boxes touch at x = 5, where the second tile
+        // starts, but both were seen whole in the first tile.
+        let same_tile = vec![
+            create_testing_tiled_detection((1.0, 2.0, 5.0, 6.0), "7", 0.7, (0, 0)),
+            create_testing_tiled_detection((5.0, 2.0, 9.0, 6.0), "7", 0.8, (0, 0)),
+        ];
+        assert_eq!(merge_boundary_detections(same_tile, 5, 10).len(), 2);
+        // Seen from the second tile, the right box starts at the tile's edge, but the left box
+        // isn't cut by the first tile, which ends at x = 10.
+        let different_tiles = vec![
+            create_testing_tiled_detection((1.0, 2.0, 5.0, 6.0), "7", 0.7, (0, 0)),
+            create_testing_tiled_detection((5.0, 2.0, 9.0, 6.0), "7", 0.8, (0, 1)),
+        ];
+        assert_eq!(merge_boundary_detections(different_tiles, 5, 10).len(), 2);
+    }
+
+    fn create_testing_shared_category_detections() -> Vec<Detection<BoundingBox>> {
+        vec![
+            Detection {
+                annotation: BoundingBox::new(0_f32, 0_f32, 2_f32, 2_f32, "start".to_string())
+                    .unwrap(),
+                confidence: 0.6_f32,
+            },
+            Detection {
+                annotation: BoundingBox::new(4_f32, 4_f32, 6_f32, 6_f32, "start".to_string())
+                    .unwrap(),
+                confidence: 0.9_f32,
+            },
+            Detection {
+                annotation: BoundingBox::new(10_f32, 0_f32, 12_f32, 2_f32, "end".to_string())
+                    .unwrap(),
+                confidence: 0.5_f32,
+            },
+        ]
+    }
+
+    #[test]
+    fn detections_to_centroids_highest_confidence() {
+        let dets = create_testing_shared_category_detections();
+        let centroids = detections_to_centroids(&dets, DedupStrategy::HighestConfidence);
+        let true_centroids = HashMap::from([
+            ("start".to_string(), Point { x: 5_f32, y: 5_f32 }),
+            (
+                "end".to_string(),
+                Point {
+                    x: 11_f32,
+                    y: 1_f32,
+                },
+            ),
+        ]);
+        assert_eq!(true_centroids, centroids);
+    }
+
+    #[test]
+    fn detections_to_centroids_mean_center() {
+        let dets = create_testing_shared_category_detections();
+        let centroids = detections_to_centroids(&dets, DedupStrategy::MeanCenter);
+        let true_centroids = HashMap::from([
+            ("start".to_string(), Point { x: 3_f32, y: 3_f32 }),
+            (
+                "end".to_string(),
+                Point {
+                    x: 11_f32,
+                    y: 1_f32,
+                },
+            ),
+        ]);
+        assert_eq!(true_centroids, centroids);
+    }
+
+    #[test]
+    fn tile_and_predict_borrows_image() {
+        let mut image_array: ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> =
+            Array::zeros((1, 3, 10, 10));
+        image_array[[0, 0, 7, 2]] = 1_f32;
+        let no_overlap = OverlapProportion {
+            numerator: 1_u32,
+            denominator: 1_u32,
+        };
+        let dets = tile_and_predict(
+            &TestingModel,
+            &image_array,
+            5,
+            no_overlap,
+            0.5_f32,
+            0.5_f32,
+            None,
+        )
+        .unwrap();
+        let true_dets: Vec<Detection<BoundingBox>> = vec![Detection {
+            annotation: BoundingBox::new(1_f32, 6_f32, 3_f32, 8_f32, "test".to_string()).unwrap(),
+            confidence: 0.9_f32,
+        }];
+        assert_eq!(true_dets, dets);
+        // The image was only borrowed, so it can be predicted on again without a clone.
+        let dets_again = tile_and_predict(
+            &TestingModel,
+            &image_array,
+            5,
+            no_overlap,
+            0.5_f32,
+            0.5_f32,
+            None,
+        )
+        .unwrap();
+        assert_eq!(dets, dets_again);
+    }
+
+    fn create_testing_confidences(confidences: &[f32]) -> Vec<Detection<BoundingBox>> {
+        confidences
+            .iter()
+            .enumerate()
+            .map(|(ix, confidence)| Detection {
+                annotation: BoundingBox::new(
+                    ix as f32,
+                    0_f32,
+                    ix as f32 + 1_f32,
+                    1_f32,
+                    "test".to_string(),
+                )
+                .unwrap(),
+                confidence: *confidence,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn filter_detections_drops_below_threshold() {
+        let dets = create_testing_confidences(&[0.2_f32, 0.6_f32, 0.5_f32, 0.1_f32]);
+        let filtered = filter_detections(dets, 0.5_f32, None);
+        let confidences: Vec<f32> = filtered.iter().map(|det| det.confidence).collect();
+        assert_eq!(confidences, vec![0.6_f32, 0.5_f32]);
+    }
+
+    #[test]
+    fn filter_detections_keeps_top_k() {
+        let dets = create_testing_confidences(&[0.7_f32, 0.9_f32, 0.3_f32, 0.8_f32]);
+        let filtered = filter_detections(dets, 0_f32, Some(2));
+        let true_dets = vec![
+            Detection {
+                annotation: BoundingBox::new(1_f32, 0_f32, 2_f32, 1_f32, "test".to_string())
+                    .unwrap(),
+                confidence: 0.9_f32,
+            },
+            Detection {
+                annotation: BoundingBox::new(3_f32, 0_f32, 4_f32, 1_f32, "test".to_string())
+                    .unwrap(),
+                confidence: 0.8_f32,
+            },
+        ];
+        assert_eq!(true_dets, filtered);
+    }
+
+    #[test]
+    fn remap_categories_preserves_unmapped() {
+        let mut dets: Vec<Detection<BoundingBox>> = vec![
+            Detection {
+                annotation: BoundingBox::new(0_f32, 0_f32, 1_f32, 1_f32, "0".to_string()).unwrap(),
+                confidence: 0.9_f32,
+            },
+            Detection {
+                annotation: BoundingBox::new(0_f32, 0_f32, 1_f32, 1_f32, "1".to_string()).unwrap(),
+                confidence: 0.9_f32,
+            },
+        ];
+        let mapping = HashMap::from([("0".to_string(), "landmark_top_left".to_string())]);
+        remap_categories(&mut dets, &mapping);
+        assert_eq!(dets[0].annotation.category(), "landmark_top_left");
+        assert_eq!(dets[1].annotation.category(), "1");
+    }
+
+    #[test]
+    fn non_maximum_suppression_by_distance_merges_coincident_centers() {
+        let dets: Vec<Detection<BoundingBox>> = vec![
+            Detection {
+                annotation: BoundingBox::new(0_f32, 4_f32, 10_f32, 6_f32, "box".to_string())
+                    .unwrap(),
+                confidence: 0.8_f32,
+            },
+            Detection {
+                annotation: BoundingBox::new(4_f32, 0_f32, 6_f32, 10_f32, "box".to_string())
+                    .unwrap(),
+                confidence: 0.9_f32,
+            },
+            Detection {
+                annotation: BoundingBox::new(20_f32, 0_f32, 30_f32, 10_f32, "box".to_string())
+                    .unwrap(),
+                confidence: 0.7_f32,
+            },
+        ];
+        assert!(
+            dets[0]
+                .annotation
+                .intersection_over_union(&dets[1].annotation)
+                < 0.5
+        );
+        let kept = non_maximum_suppression_by_distance(dets, 3_f32);
+        assert_eq!(kept.len(), 2);
+        assert_eq!(kept[0].confidence, 0.9_f32);
+        assert_eq!(kept[1].confidence, 0.7_f32);
+    }
+
+    #[test]
+    fn group_by_category_buckets_detections() {
+        let dets: Vec<Detection<BoundingBox>> = ["systolic", "diastolic", "systolic", "heart_rate"]
+            .iter()
+            .map(|category| Detection {
+                annotation: BoundingBox::new(0_f32, 0_f32, 1_f32, 1_f32, category.to_string())
+                    .unwrap(),
+                confidence: 0.9_f32,
+            })
+            .collect();
+        let groups = group_by_category(dets);
+        assert_eq!(groups.len(), 3);
+        assert_eq!(groups["systolic"].len(), 2);
+        assert_eq!(groups["diastolic"].len(), 1);
+        assert_eq!(groups["heart_rate"].len(), 1);
+        assert!(
+            groups["systolic"]
+                .iter()
+                .all(|det| det.annotation.category() == "systolic")
+        );
+    }
+
+    #[test]
+    fn retain_by_area_drops_implausible_boxes() {
+        let dets: Vec<Detection<BoundingBox>> = [1_f32, 10_f32, 100_f32]
+            .iter()
+            .map(|size| Detection {
+                annotation: BoundingBox::new(0_f32, 0_f32, *size, *size, size.to_string()).unwrap(),
+                confidence: 0.9_f32,
+            })
+            .collect();
+        let kept = retain_by_area(dets, 25_f32, 400_f32);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].annotation.category(), "10");
+    }
+
+    #[test]
+    fn retain_within_image_drops_padding_detections() {
+        let dets: Vec<Detection<BoundingBox>> = vec![
+            Detection {
+                annotation: BoundingBox::new(10_f32, 10_f32, 20_f32, 20_f32, "kept".to_string())
+                    .unwrap(),
+                confidence: 0.9_f32,
+            },
+            Detection {
+                annotation: BoundingBox::new(
+                    95_f32,
+                    40_f32,
+                    115_f32,
+                    50_f32,
+                    "padding".to_string(),
+                )
+                .unwrap(),
+                confidence: 0.9_f32,
+            },
+        ];
+        let kept = retain_within_image(dets, 100, 100);
+        assert_eq!(kept.len(), 1);
+        assert_eq!(kept[0].annotation.category(), "kept");
+    }
+
+    #[test]
+    fn rescale_detections_doubles_boxes() {
+        let dets = vec![Detection {
+            annotation: BoundingBoxWithKeypoint::new(
+                10_f32,
+                20_f32,
+                30_f32,
+                60_f32,
+                15_f32,
+                25_f32,
+                "test".to_string(),
+            )
+            .unwrap(),
+            confidence: 0.9_f32,
+        }];
+        let rescaled = rescale_detections(dets, (320, 320), (640, 640));
+        assert_eq!(
+            rescaled[0].annotation.as_xyxy(),
+            (20_f32, 40_f32, 60_f32, 120_f32)
+        );
+        assert_eq!(rescaled[0].annotation.width(), 40_f32);
+        assert_eq!(rescaled[0].annotation.get_keypoint_x(), 30_f32);
+        assert_eq!(rescaled[0].annotation.get_keypoint_y(), 50_f32);
+        assert_eq!(rescaled[0].confidence, 0.9_f32);
+    }
+
+    #[test]
+    fn majority_category_weighs_confidence() {
+        let dets: Vec<Detection<BoundingBox>> =
+            [("diastolic", 0.9), ("systolic", 0.6), ("systolic", 0.5)]
+                .iter()
+                .map(|(category, confidence)| Detection {
+                    annotation: BoundingBox::new(0_f32, 0_f32, 2_f32, 2_f32, category.to_string())
+                        .unwrap(),
+                    confidence: *confidence,
+                })
+                .collect();
+        assert_eq!(majority_category(&dets), Some("systolic".to_string()));
+        assert_eq!(majority_category(&dets[..2]), Some("diastolic".to_string()));
+        assert_eq!(majority_category::<BoundingBox>(&[]), None);
+    }
+
+    #[test]
+    fn retain_detections_applies_predicate() {
+        let dets = create_testing_confidences(&[0.2_f32, 0.6_f32, 0.9_f32]);
+        let kept = retain_detections(dets, |det| det.confidence > 0.5_f32);
+        assert_eq!(kept.len(), 2);
+    }
+
+    #[test]
+    fn confidence_weighted_center_favors_confident_detection() {
+        let dets: Vec<Detection<BoundingBox>> = vec![
+            Detection {
+                annotation: BoundingBox::new(0_f32, 0_f32, 2_f32, 2_f32, "start".to_string())
+                    .unwrap(),
+                confidence: 0.9_f32,
+            },
+            Detection {
+                annotation: BoundingBox::new(10_f32, 0_f32, 12_f32, 2_f32, "start".to_string())
+                    .unwrap(),
+                confidence: 0.1_f32,
+            },
+        ];
+        let center = confidence_weighted_center(&dets).unwrap();
+        assert!(center.approx_eq(&Point { x: 2_f32, y: 1_f32 }, 1e-5));
+        let centroids = detections_to_centroids(&dets, DedupStrategy::ConfidenceWeighted);
+        assert!(centroids["start"].approx_eq(&center, 1e-5));
+        assert_eq!(confidence_weighted_center(&dets[..0]), None);
+    }
+
+    #[test]
+    fn tile_and_predict_edge_confidence_relief() {
+        let image_array: ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> = Array::ones((1, 3, 20, 20));
+        let no_overlap = OverlapProportion {
+            numerator: 1_u32,
+            denominator: 1_u32,
+        };
+        let predict = |edge_confidence_relief: Option<EdgeConfidenceRelief>| {
+            tile_and_predict(
+                &EdgeModel,
+                &image_array,
+                10,
+                no_overlap,
+                0.5_f32,
+                0.5_f32,
+                edge_confidence_relief,
+            )
+            .unwrap()
+        };
+        assert!(predict(None).is_empty());
+        // The box is two pixels from the edge, so a one pixel margin doesn't count it as cut.
+        let narrow_margin = EdgeConfidenceRelief {
+            relief: 0.2_f32,
+            edge_margin: 1_f32,
+        };
+        assert!(predict(Some(narrow_margin)).is_empty());
+        let with_relief = predict(Some(EdgeConfidenceRelief {
+            relief: 0.2_f32,
+            edge_margin: 3_f32,
+        }));
+        // Only the right column of tiles has its left edge inside the image.
+        assert_eq!(with_relief.len(), 2);
+        assert!(
+            with_relief
+                .iter()
+                .all(|det| det.annotation.left() == 12_f32)
+        );
+    }
+
+    #[test]
+    fn tile_and_predict_landscape_image() {
+        // One row of two tiles, so only the right tile has an edge shared with another tile.
+        let image_array: ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> = Array::ones((1, 3, 10, 20));
+        let no_overlap = OverlapProportion {
+            numerator: 1_u32,
+            denominator: 1_u32,
+        };
+        let dets = tile_and_predict(
+            &EdgeModel,
+            &image_array,
+            10,
+            no_overlap,
+            0.5_f32,
+            0.5_f32,
+            Some(EdgeConfidenceRelief {
+                relief: 0.2_f32,
+                edge_margin: 3_f32,
+            }),
+        )
+        .unwrap();
+        assert_eq!(dets.len(), 1);
+        assert_eq!(dets[0].annotation.left(), 12_f32);
+        assert_eq!(dets[0].annotation.top(), 4_f32);
+    }
+
+    #[test]
+    fn tile_and_predict_shifts_keypoints() {
+        let image_array: ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> = Array::ones((1, 3, 10, 10));
+        let no_overlap = OverlapProportion {
+            numerator: 1_u32,
+            denominator: 1_u32,
+        };
+        let dets = tile_and_predict(
+            &KeypointModel,
+            &image_array,
+            5,
+            no_overlap,
+            0.5_f32,
+            0.5_f32,
+            None,
+        )
+        .unwrap();
+        assert_eq!(dets.len(), 4);
+        let bottom_right = dets
+            .iter()
+            .find(|det| det.annotation.left() == 6_f32 && det.annotation.top() == 6_f32)
+            .unwrap();
+        assert_eq!(bottom_right.annotation.get_keypoint_x(), 7_f32);
+        assert_eq!(bottom_right.annotation.get_keypoint_y(), 7_f32);
+    }
+
+    #[test]
+    fn iou_matrix_pairwise() {
+        let dets: Vec<Detection<BoundingBox>> = [
+            (0_f32, 0_f32, 2_f32, 2_f32),
+            (1_f32, 0_f32, 3_f32, 2_f32),
+            (0_f32, 0_f32, 1_f32, 1_f32),
+        ]
+        .iter()
+        .map(|(l, t, r, b)| Detection {
+            annotation: BoundingBox::new(*l, *t, *r, *b, "test".to_string()).unwrap(),
+            confidence: 0.9_f32,
+        })
+        .collect();
+        let true_matrix = Array::from_shape_vec(
+            (3, 3),
+            vec![
+                1_f32,
+                1_f32 / 3_f32,
+                0.25_f32,
+                1_f32 / 3_f32,
+                1_f32,
+                0_f32,
+                0.25_f32,
+                0_f32,
+                1_f32,
+            ],
+        )
+        .unwrap();
+        assert!(iou_matrix(&dets).abs_diff_eq(&true_matrix, 1e-6));
+    }
+
+    #[test]
+    fn tile_and_predict_propagates_inference_errors() {
+        let image_array: ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> = Array::ones((1, 3, 10, 10));
+        let no_overlap = OverlapProportion {
+            numerator: 1_u32,
+            denominator: 1_u32,
+        };
+        let result = tile_and_predict(
+            &FailingModel,
+            &image_array,
+            5,
+            no_overlap,
+            0.5_f32,
+            0.5_f32,
+            None,
+        );
+        assert!(matches!(
+            result,
+            Err(TileAndPredictError::Inference(InferenceError::InvalidBox(
+                _
+            )))
+        ));
+    }
+
+    #[test]
+    fn fuse_keypoint_detections_averages_keypoints() {
+        let create_det = |left: f32, keypoint_x: f32, category: &str, confidence: f32| Detection {
+            annotation: BoundingBoxWithKeypoint::new(
+                left,
+                0_f32,
+                left + 10_f32,
+                10_f32,
+                keypoint_x,
+                5_f32,
+                category.to_string(),
+            )
+            .unwrap(),
+            confidence,
+        };
+        let dets = vec![
+            create_det(1_f32, 4_f32, "systolic", 0.25),
+            create_det(0_f32, 6_f32, "systolic", 0.75),
+            create_det(0_f32, 5_f32, "diastolic", 0.5),
+        ];
+        let fused = fuse_keypoint_detections(dets, 0.5);
+        assert_eq!(fused.len(), 2);
+        assert_eq!(fused[0].annotation.category(), "systolic");
+        assert_eq!(fused[0].confidence, 0.75);
+        assert_eq!(fused[0].annotation.get_keypoint_x(), 5.5);
+        assert_eq!(fused[0].annotation.get_keypoint_y(), 5.0);
+        assert_eq!(fused[0].annotation.left(), 0.25);
+        assert_eq!(fused[1].annotation.get_keypoint_x(), 5.0);
+    }
+
+    #[test]
+    fn fuse_keypoint_cluster_zero_width_boxes() {
+        let create_det = |left: f32, confidence: f32| Detection {
+            annotation: BoundingBoxWithKeypoint::new(
+                left,
+                0.1_f32,
+                left,
+                0.1_f32,
+                left,
+                0.1_f32,
+                "systolic".to_string(),
+            )
+            .unwrap(),
+            confidence,
+        };
+        let dets = vec![
+            create_det(0.3_f32, 0.7),
+            create_det(0.3_f32, 0.2),
+            create_det(0.3_f32, 0.1),
+        ];
+        let fused = fuse_keypoint_cluster(dets);
+        let annotation = &fused.annotation;
+        assert!(annotation.left() <= annotation.right());
+        assert!(annotation.top() <= annotation.bottom());
+    }
+
+    #[test]
+    fn read_classes_csv_with_sparse_ids() {
+        let filepath = std::env::temp_dir().join("chart_extractor_read_classes_csv.csv");
+        std::fs::write(
+            &filepath,
+            "# id,name,color\n0,landmark_tl,#ff0000\n\n7, landmark_br ,#00FF80\n3,checkbox,#0000ff\n",
+        )
+        .unwrap();
+        let classes = read_classes_csv(&filepath).unwrap();
+        assert_eq!(
+            classes,
+            vec![
+                ClassInfo {
+                    id: 0,
+                    name: "landmark_tl".to_string(),
+                    color: Rgb([255, 0, 0]),
+                },
+                ClassInfo {
+                    id: 7,
+                    name: "landmark_br".to_string(),
+                    color: Rgb([0, 255, 128]),
+                },
+                ClassInfo {
+                    id: 3,
+                    name: "checkbox".to_string(),
+                    color: Rgb([0, 0, 255]),
+                },
+            ]
+        );
+
+        std::fs::write(&filepath, "0,landmark_tl,#ff0000\n1,landmark_tr,red\n").unwrap();
+        let error = read_classes_csv(&filepath).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains("line 2"));
+        std::fs::remove_file(&filepath).unwrap();
+    }
 }
@@ -1,4 +1,5 @@
 use ort::session::Session;
+use ort::value::ValueType;
 use std::path::Path;
 
 /// An onnxruntime inference session.
@@ -15,4 +16,63 @@ impl OrtInferenceSession {
         let session = Session::builder()?.commit_from_file(model_path)?;
         Ok(Self { session })
     }
+
+    /// Reads the shape of the model's first input from the ONNX graph.
+    ///
+    /// Returns None if the input is not a rank 4 tensor, or if any of its dimensions are dynamic.
+    pub fn input_shape(&self) -> Option<[usize; 4]> {
+        let input = self.session.inputs.first()?;
+        let ValueType::Tensor { dimensions, .. } = &input.input_type else {
+            return None;
+        };
+        let dimensions: Vec<usize> = dimensions
+            .iter()
+            .map(|d| usize::try_from(*d).ok())
+            .collect::<Option<Vec<usize>>>()?;
+        dimensions.try_into().ok()
+    }
+
+    /// Checks that the dimensions supplied for a model agree with those in its ONNX graph.
+    ///
+    /// Inference on an input of the wrong size does not fail, it silently produces bad
+    /// detections, so a mismatch is an error here instead. Models with dynamic input dimensions
+    /// are not checked.
+    pub fn check_input_size(
+        &self,
+        input_width: usize,
+        input_height: usize,
+        model_name: &str,
+    ) -> ort::Result<()> {
+        match self.input_shape() {
+            Some([_, _, graph_height, graph_width])
+                if graph_width != input_width || graph_height != input_height =>
+            {
+                Err(ort::Error::new(format!(
+                    "{} was given an input size of {}x{}, but its graph expects {}x{}.",
+                    model_name, input_width, input_height, graph_width, graph_height
+                )))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn input_shape_bundled_model() {
+        let ort_session =
+            OrtInferenceSession::new(Path::new("./data/models/yolo11n.onnx")).unwrap();
+        assert_eq!(ort_session.input_shape(), Some([1, 3, 640, 640]));
+    }
+
+    #[test]
+    fn check_input_size_bundled_model() {
+        let ort_session =
+            OrtInferenceSession::new(Path::new("./data/models/yolo11n.onnx")).unwrap();
+        assert!(ort_session.check_input_size(640, 640, "yolo11n").is_ok());
+        assert!(ort_session.check_input_size(320, 640, "yolo11n").is_err());
+    }
 }
@@ -0,0 +1,178 @@
+use ndarray::{Array, ArrayBase, Axis, Dim, OwnedRepr, ViewRepr};
+
+/// Defines a trait for the preprocessing a model expects before inference.
+///
+/// Tiles arrive as (1, 3, rows, columns) arrays of RGB values in [0, 1], as produced by
+/// convert_rgb_image_to_owned_array. Each model holds a preprocessor and applies it to every
+/// tile inside run_inference, so a model's preprocessing is explicit and can be tested alone.
+/// The model then maps what it found back onto the tile with to_original.
+pub trait Preprocessor {
+    fn prepare(
+        &self,
+        tile: ArrayBase<ViewRepr<&f32>, Dim<[usize; 4]>>,
+    ) -> ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>>;
+
+    /// Maps a point on the prepared input back onto a tile of the given size.
+    ///
+    /// Defaults to leaving the point as it is, for preprocessors that keep the tile's geometry.
+    fn to_original(&self, x: f32, y: f32, _tile_width: usize, _tile_height: usize) -> (f32, f32) {
+        (x, y)
+    }
+
+    /// Maps a point on a tile of the given size onto the prepared input, the inverse of
+    /// to_original.
+    fn to_prepared(&self, x: f32, y: f32, _tile_width: usize, _tile_height: usize) -> (f32, f32) {
+        (x, y)
+    }
+}
+
+/// Passes tiles through unchanged. This is what the YOLO models were trained with.
+pub struct IdentityPreprocessor;
+
+impl Preprocessor for IdentityPreprocessor {
+    fn prepare(
+        &self,
+        tile: ArrayBase<ViewRepr<&f32>, Dim<[usize; 4]>>,
+    ) -> ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> {
+        tile.to_owned()
+    }
+}
+
+/// Standardizes each channel as (value - mean) / std.
+///
+/// Models with ImageNet backbones typically expect mean [0.485, 0.456, 0.406] and
+/// std [0.229, 0.224, 0.225].
+pub struct NormalizePreprocessor {
+    pub mean: [f32; 3],
+    pub std: [f32; 3],
+}
+
+impl Preprocessor for NormalizePreprocessor {
+    fn prepare(
+        &self,
+        tile: ArrayBase<ViewRepr<&f32>, Dim<[usize; 4]>>,
+    ) -> ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> {
+        let mut prepared = tile.to_owned();
+        for (channel_ix, mut channel) in prepared.axis_iter_mut(Axis(1)).enumerate() {
+            let mean = self.mean[channel_ix];
+            let std = self.std[channel_ix];
+            channel.mapv_inplace(|value| (value - mean) / std);
+        }
+        prepared
+    }
+}
+
+/// Resizes tiles to fit the model's input while keeping their aspect ratio, padding the rest.
+///
+/// The tile is scaled with nearest neighbor sampling so it just fits inside (width, height),
+/// centered, and the border is set to fill_value. Boxes found on the letterboxed input can be
+/// mapped back onto the tile with to_original.
+pub struct LetterboxPreprocessor {
+    pub width: usize,
+    pub height: usize,
+    pub fill_value: f32,
+}
+
+impl LetterboxPreprocessor {
+    /// Computes the scale and the (x, y) padding applied to a tile of the given size.
+    fn geometry(&self, tile_width: usize, tile_height: usize) -> (f32, f32, f32) {
+        let scale =
+            (self.width as f32 / tile_width as f32).min(self.height as f32 / tile_height as f32);
+        let scaled_width = (tile_width as f32 * scale).round();
+        let scaled_height = (tile_height as f32 * scale).round();
+        let pad_x = ((self.width as f32 - scaled_width) / 2.0).floor();
+        let pad_y = ((self.height as f32 - scaled_height) / 2.0).floor();
+        (scale, pad_x, pad_y)
+    }
+}
+
+impl Preprocessor for LetterboxPreprocessor {
+    fn prepare(
+        &self,
+        tile: ArrayBase<ViewRepr<&f32>, Dim<[usize; 4]>>,
+    ) -> ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> {
+        let [batch, channels, tile_height, tile_width] = *tile.shape() else {
+            unreachable!("The tile array always has four dimensions.");
+        };
+        let (scale, pad_x, pad_y) = self.geometry(tile_width, tile_height);
+        let mut prepared: ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> =
+            Array::from_elem((batch, channels, self.height, self.width), self.fill_value);
+        for y in 0..self.height {
+            let source_y = ((y as f32 - pad_y + 0.5) / scale).floor();
+            if source_y < 0.0 || source_y >= tile_height as f32 {
+                continue;
+            }
+            for x in 0..self.width {
+                let source_x = ((x as f32 - pad_x + 0.5) / scale).floor();
+                if source_x < 0.0 || source_x >= tile_width as f32 {
+                    continue;
+                }
+                for b in 0..batch {
+                    for c in 0..channels {
+                        prepared[[b, c, y, x]] = tile[[b, c, source_y as usize, source_x as usize]];
+                    }
+                }
+            }
+        }
+        prepared
+    }
+
+    fn to_original(&self, x: f32, y: f32, tile_width: usize, tile_height: usize) -> (f32, f32) {
+        let (scale, pad_x, pad_y) = self.geometry(tile_width, tile_height);
+        ((x - pad_x) / scale, (y - pad_y) / scale)
+    }
+
+    fn to_prepared(&self, x: f32, y: f32, tile_width: usize, tile_height: usize) -> (f32, f32) {
+        let (scale, pad_x, pad_y) = self.geometry(tile_width, tile_height);
+        (x * scale + pad_x, y * scale + pad_y)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_testing_tile() -> ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> {
+        // A 2x4 tile whose value encodes its position, so sampling can be checked exactly.
+        Array::from_shape_fn((1, 3, 2, 4), |(_, c, y, x)| (c * 100 + y * 10 + x) as f32)
+    }
+
+    #[test]
+    fn identity_preprocessor_copies_tile() {
+        let tile = create_testing_tile();
+        assert_eq!(IdentityPreprocessor.prepare(tile.view()), tile);
+        assert_eq!(IdentityPreprocessor.to_original(3.0, 1.5, 4, 2), (3.0, 1.5));
+    }
+
+    #[test]
+    fn normalize_preprocessor_standardizes_channels() {
+        let tile: ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> = Array::ones((1, 3, 2, 2));
+        let preprocessor = NormalizePreprocessor {
+            mean: [0.5, 1.0, 0.0],
+            std: [0.5, 1.0, 2.0],
+        };
+        let prepared = preprocessor.prepare(tile.view());
+        assert_eq!(prepared[[0, 0, 1, 1]], 1.0);
+        assert_eq!(prepared[[0, 1, 1, 1]], 0.0);
+        assert_eq!(prepared[[0, 2, 1, 1]], 0.5);
+    }
+
+    #[test]
+    fn letterbox_preprocessor_pads_and_scales() {
+        let tile = create_testing_tile();
+        let preprocessor = LetterboxPreprocessor {
+            width: 8,
+            height: 8,
+            fill_value: -1.0,
+        };
+        let prepared = preprocessor.prepare(tile.view());
+        assert_eq!(prepared.dim(), (1, 3, 8, 8));
+        // The 2x4 tile is doubled to 4x8, leaving two padded rows above and below.
+        assert_eq!(prepared[[0, 0, 0, 0]], -1.0);
+        assert_eq!(prepared[[0, 0, 7, 7]], -1.0);
+        assert_eq!(prepared[[0, 0, 2, 0]], 0.0);
+        assert_eq!(prepared[[0, 1, 5, 7]], 113.0);
+        assert_eq!(preprocessor.to_original(8.0, 6.0, 4, 2), (4.0, 2.0));
+        assert_eq!(preprocessor.to_prepared(4.0, 2.0, 4, 2), (8.0, 6.0));
+    }
+}
@@ -0,0 +1,24 @@
+use crate::annotations::bounding_box::BoundingBoxGeometry;
+use crate::annotations::detection::Detection;
+use crate::object_detection::object_detection_model::InferenceError;
+use ndarray::{ArrayBase, Dim, OwnedRepr, ViewRepr};
+use std::fmt::Display;
+
+/// A binary mask with dimensions (height, width).
+pub type SegmentationMask = ArrayBase<OwnedRepr<bool>, Dim<[usize; 2]>>;
+
+/// Defines a trait that all instance segmentation models must follow.
+///
+/// Segmentation models are detection models that additionally produce a binary mask for each
+/// detection. The mask has the same dimensions as the model's input, with every pixel outside of
+/// the detection's box set to false.
+pub trait SegmentationModel<T: BoundingBoxGeometry + Display> {
+    /// Like ObjectDetectionModel::run_inference, this takes a view into an array so that tiles
+    /// can be passed in without making copies of the image, and returns errors from the model
+    /// rather than panicking.
+    fn run_inference(
+        &self,
+        input_array: ArrayBase<ViewRepr<&f32>, Dim<[usize; 4]>>,
+        confidence: f32,
+    ) -> Result<Vec<(Detection<T>, SegmentationMask)>, InferenceError>;
+}
@@ -1,8 +1,17 @@
 use crate::annotations::bounding_box::{BoundingBox, BoundingBoxGeometry};
 use crate::annotations::detection::Detection;
-use crate::object_detection::object_detection_model::ObjectDetectionModel;
+use crate::object_detection::model_config::{
+    ModelConfig, ModelMetadata, ModelMetadataError, OutputLayout,
+};
+use crate::object_detection::object_detection_model::{InferenceError, ObjectDetectionModel};
+use crate::object_detection::object_detection_utils::{
+    class_agnostic_non_maximum_suppression, non_maximum_suppression,
+};
 use crate::object_detection::ort_inference_session::OrtInferenceSession;
-use ndarray::{ArrayBase, Axis, Dim, ViewRepr};
+use crate::object_detection::preprocessor::{
+    IdentityPreprocessor, NormalizePreprocessor, Preprocessor,
+};
+use ndarray::{ArrayBase, Axis, Dim, IxDyn, ViewRepr};
 use ort::{inputs, session::SessionOutputs};
 use std::fmt::Display;
 use std::path::Path;
@@ -13,6 +22,10 @@ pub struct Yolov11BoundingBox {
     input_width: usize,
     input_height: usize,
     model_name: String,
+    preprocessor: Box<dyn Preprocessor>,
+    output_layout: OutputLayout,
+    nms_iou_threshold: f32,
+    class_aware_nms: bool,
 }
 
 impl Yolov11BoundingBox {
@@ -23,15 +36,73 @@ impl Yolov11BoundingBox {
         input_height: usize,
         model_name: String,
     ) -> ort::Result<Self> {
-        let ort_session = OrtInferenceSession::new(model_path)?;
-        Ok(Yolov11BoundingBox {
-            ort_session,
+        Self::from_config(ModelConfig {
+            model_path: model_path.to_path_buf(),
             class_names,
             input_width,
             input_height,
             model_name,
         })
     }
+
+    pub fn from_config(config: ModelConfig) -> ort::Result<Self> {
+        let ort_session = OrtInferenceSession::new(&config.model_path)?;
+        ort_session.check_input_size(
+            config.input_width,
+            config.input_height,
+            &config.model_name,
+        )?;
+        Ok(Yolov11BoundingBox {
+            ort_session,
+            class_names: config.class_names,
+            input_width: config.input_width,
+            input_height: config.input_height,
+            model_name: config.model_name,
+            preprocessor: Box::new(IdentityPreprocessor),
+            output_layout: OutputLayout::ChannelsFirst,
+            nms_iou_threshold: 0.5,
+            class_aware_nms: true,
+        })
+    }
+
+    /// Sets the non maximum suppression run on each tile's detections, which defaults to a
+    /// class aware IoU threshold of 0.5.
+    ///
+    /// With class_aware false, overlapping boxes are suppressed even when their categories
+    /// differ.
+    pub fn with_nms(mut self, iou_threshold: f32, class_aware: bool) -> Self {
+        self.nms_iou_threshold = iou_threshold;
+        self.class_aware_nms = class_aware;
+        self
+    }
+
+    /// Loads a model along with the metadata JSON describing it.
+    ///
+    /// The model is named after its file stem, and the metadata's normalization, if any, becomes
+    /// the model's preprocessor.
+    pub fn from_onnx_with_metadata(
+        model_path: &Path,
+        metadata_path: &Path,
+    ) -> Result<Self, ModelMetadataError> {
+        let metadata = ModelMetadata::from_json(metadata_path)?;
+        let mut model =
+            Self::from_config(metadata.to_config(model_path)).map_err(ModelMetadataError::Ort)?;
+        model.output_layout = metadata.output_layout;
+        if let Some(normalization) = metadata.normalization {
+            model = model.with_preprocessor(Box::new(NormalizePreprocessor {
+                mean: normalization.mean,
+                std: normalization.std,
+            }));
+        }
+        Ok(model)
+    }
+
+    /// Replaces the preprocessing applied to each tile before inference, which defaults to
+    /// IdentityPreprocessor.
+    pub fn with_preprocessor(mut self, preprocessor: Box<dyn Preprocessor>) -> Self {
+        self.preprocessor = preprocessor;
+        self
+    }
 }
 
 impl ObjectDetectionModel<BoundingBox> for Yolov11BoundingBox {
@@ -39,14 +110,30 @@ impl ObjectDetectionModel<BoundingBox> for Yolov11BoundingBox {
         &self,
         input_array: ArrayBase<ViewRepr<&f32>, Dim<[usize; 4]>>,
         confidence: f32,
-    ) -> Vec<Detection<BoundingBox>> {
+    ) -> Result<Vec<Detection<BoundingBox>>, InferenceError> {
+        let [_, _, tile_height, tile_width] = *input_array.shape() else {
+            unreachable!("The tile array always has four dimensions.");
+        };
+        let input_array = self.preprocessor.prepare(input_array);
+        let inputs =
+            inputs!["images" => input_array.view()].map_err(InferenceError::InputConstruction)?;
         let outputs: SessionOutputs = self
             .ort_session
             .session
-            .run(inputs!["images" => input_array].unwrap())
-            .unwrap();
-        let output = outputs["output0"].try_extract_tensor::<f32>().unwrap();
-        let output = output.t();
+            .run(inputs)
+            .map_err(InferenceError::SessionRun)?;
+        let output = outputs
+            .get("output0")
+            .ok_or_else(|| {
+                InferenceError::OutputExtraction(ort::Error::new("The model has no output0."))
+            })?
+            .try_extract_tensor::<f32>()
+            .map_err(InferenceError::OutputExtraction)?;
+        // Puts one anchor per row, as (anchors, 4 + classes, 1).
+        let output = match self.output_layout {
+            OutputLayout::ChannelsFirst => output.t(),
+            OutputLayout::ChannelsLast => output.view().permuted_axes(IxDyn(&[1, 2, 0])),
+        };
         let mut detections: Vec<Detection<BoundingBox>> = Vec::new();
         for row in output.axis_iter(Axis(0)) {
             let row: Vec<f32> = row.iter().copied().collect();
@@ -56,7 +143,11 @@ impl ObjectDetectionModel<BoundingBox> for Yolov11BoundingBox {
                 .enumerate()
                 .map(|(index, value)| (index, *value))
                 .reduce(|accum, row| if row.1 > accum.1 { row } else { accum })
-                .unwrap();
+                .ok_or_else(|| {
+                    InferenceError::OutputExtraction(ort::Error::new(
+                        "The model's output rows have no class scores.",
+                    ))
+                })?;
             if prob < confidence {
                 continue;
             }
@@ -68,18 +159,201 @@ impl ObjectDetectionModel<BoundingBox> for Yolov11BoundingBox {
             let y = row[1];
             let w = row[2];
             let h = row[3];
-            let bbox = BoundingBox::new(
+            let (left, top) = self.preprocessor.to_original(
                 x - (w / 2.0),
                 y - (h / 2.0),
+                tile_width,
+                tile_height,
+            );
+            let (right, bottom) = self.preprocessor.to_original(
                 x + (w / 2.0),
                 y + (h / 2.0),
-                label.to_string(),
+                tile_width,
+                tile_height,
             );
+            let bbox = BoundingBox::new(left, top, right, bottom, label.to_string())
+                .map_err(InferenceError::InvalidBox)?;
             detections.push(Detection {
-                annotation: bbox.unwrap(),
+                annotation: bbox,
                 confidence: prob,
             });
         }
-        detections
+        if self.class_aware_nms {
+            Ok(non_maximum_suppression(detections, self.nms_iou_threshold))
+        } else {
+            Ok(class_agnostic_non_maximum_suppression(
+                detections,
+                self.nms_iou_threshold,
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image_utils::image_io::read_image_as_array4_resized;
+    use crate::image_utils::tiling::OverlapProportion;
+    use crate::object_detection::object_detection_utils::{
+        iou_matrix, read_classes_txt_file, tile_and_predict,
+    };
+    use crate::object_detection::preprocessor::LetterboxPreprocessor;
+    use image::imageops::FilterType;
+    use ndarray::OwnedRepr;
+
+    #[test]
+    fn from_config_bundled_model() {
+        let config = ModelConfig {
+            model_path: Path::new("./data/models/yolo11n.onnx").to_path_buf(),
+            class_names: read_classes_txt_file(Path::new("./data/model_metadata/coco-classes.txt"))
+                .unwrap(),
+            input_width: 640,
+            input_height: 640,
+            model_name: "yolov11n onnx".to_string(),
+        };
+        let model = Yolov11BoundingBox::from_config(config.clone()).unwrap();
+        assert_eq!(model.input_width, 640);
+        assert_eq!(model.input_height, 640);
+        assert_eq!(model.class_names.len(), 80);
+        // The graph's input is fixed at 640x640, so any other size is rejected.
+        let mismatched = ModelConfig {
+            input_height: 480,
+            ..config
+        };
+        assert!(Yolov11BoundingBox::from_config(mismatched).is_err());
+    }
+
+    #[test]
+    fn from_onnx_with_metadata_bundled_model() {
+        let model = Yolov11BoundingBox::from_onnx_with_metadata(
+            Path::new("./data/models/yolo11n.onnx"),
+            Path::new("./data/model_metadata/yolo11n.json"),
+        )
+        .unwrap();
+        assert_eq!(model.input_width, 640);
+        assert_eq!(model.input_height, 640);
+        assert_eq!(model.class_names.len(), 80);
+        assert_eq!(model.model_name, "yolo11n");
+        assert_eq!(model.output_layout, OutputLayout::ChannelsFirst);
+    }
+
+    #[test]
+    fn with_nms_bundled_model() {
+        let model = Yolov11BoundingBox::from_onnx_with_metadata(
+            Path::new("./data/models/yolo11n.onnx"),
+            Path::new("./data/model_metadata/yolo11n.json"),
+        )
+        .unwrap();
+        assert_eq!(model.nms_iou_threshold, 0.5);
+        assert!(model.class_aware_nms);
+        let model = model.with_nms(0.3, false);
+        assert_eq!(model.nms_iou_threshold, 0.3);
+        assert!(!model.class_aware_nms);
+        let image: ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> = read_image_as_array4_resized(
+            Path::new("./data/images/people_on_street.jpg"),
+            640,
+            640,
+            FilterType::Triangle,
+        )
+        .unwrap();
+        let dets = model.run_inference(image.view(), 0.25).unwrap();
+        let ious = iou_matrix(&dets);
+        for i in 0..dets.len() {
+            for j in i + 1..dets.len() {
+                assert!(ious[[i, j]] <= 0.3);
+            }
+        }
+    }
+
+    #[test]
+    fn run_inference_wrong_shape_bundled_model() {
+        let model = Yolov11BoundingBox::from_onnx_with_metadata(
+            Path::new("./data/models/yolo11n.onnx"),
+            Path::new("./data/model_metadata/yolo11n.json"),
+        )
+        .unwrap();
+        let image: ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> =
+            ndarray::Array::zeros((1, 1, 64, 64));
+        assert!(matches!(
+            model.run_inference(image.view(), 0.25),
+            Err(InferenceError::SessionRun(_))
+        ));
+    }
+
+    #[test]
+    fn tile_and_predict_end_to_end_bundled_model() {
+        let model = Yolov11BoundingBox::from_onnx_with_metadata(
+            Path::new("./data/models/yolo11n.onnx"),
+            Path::new("./data/model_metadata/yolo11n.json"),
+        )
+        .unwrap();
+        let image: ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> = read_image_as_array4_resized(
+            Path::new("./data/images/people_on_street.jpg"),
+            1280,
+            1280,
+            FilterType::Triangle,
+        )
+        .unwrap();
+        let dets = tile_and_predict::<BoundingBox, Yolov11BoundingBox>(
+            &model,
+            &image,
+            640,
+            OverlapProportion::new(1, 2).unwrap(),
+            0.5,
+            0.5,
+            None,
+        )
+        .unwrap();
+        assert!(dets.iter().any(|det| det.annotation.category() == "person"));
+        for det in dets.iter() {
+            assert!(det.confidence >= 0.5);
+            let (left, top, right, bottom) = det.annotation.as_xyxy();
+            assert!(left >= -1.0 && top >= -1.0 && right <= 1281.0 && bottom <= 1281.0);
+        }
+    }
+
+    #[test]
+    fn letterboxed_tiles_end_to_end_bundled_model() {
+        let model = Yolov11BoundingBox::from_onnx_with_metadata(
+            Path::new("./data/models/yolo11n.onnx"),
+            Path::new("./data/model_metadata/yolo11n.json"),
+        )
+        .unwrap();
+        let image: ArrayBase<OwnedRepr<f32>, Dim<[usize; 4]>> = read_image_as_array4_resized(
+            Path::new("./data/images/people_on_street.jpg"),
+            640,
+            640,
+            FilterType::Triangle,
+        )
+        .unwrap();
+        let direct_dets = model.run_inference(image.view(), 0.5).unwrap();
+        // The 320 pixel tiles are doubled to fill the model input, so their boxes only land on
+        // the image if they are mapped back onto the tile before the tile offsets are added.
+        let model = model.with_preprocessor(Box::new(LetterboxPreprocessor {
+            width: 640,
+            height: 640,
+            fill_value: 0.5,
+        }));
+        let tiled_dets = tile_and_predict::<BoundingBox, Yolov11BoundingBox>(
+            &model,
+            &image,
+            320,
+            OverlapProportion::new(1, 2).unwrap(),
+            0.5,
+            0.5,
+            None,
+        )
+        .unwrap();
+        assert!(!tiled_dets.is_empty());
+        for det in tiled_dets.iter() {
+            let (left, top, right, bottom) = det.annotation.as_xyxy();
+            assert!(left >= -1.0 && top >= -1.0 && right <= 641.0 && bottom <= 641.0);
+        }
+        assert!(tiled_dets.iter().any(|tiled| {
+            direct_dets.iter().any(|direct| {
+                direct.annotation.category() == tiled.annotation.category()
+                    && direct.annotation.intersection_over_union(&tiled.annotation) > 0.5
+            })
+        }));
     }
 }
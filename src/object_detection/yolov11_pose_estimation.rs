@@ -1,8 +1,10 @@
 use crate::annotations::bounding_box::BoundingBoxGeometry;
 use crate::annotations::bounding_box_with_keypoint::BoundingBoxWithKeypoint;
 use crate::annotations::detection::Detection;
-use crate::object_detection::object_detection_model::ObjectDetectionModel;
+use crate::object_detection::model_config::ModelConfig;
+use crate::object_detection::object_detection_model::{InferenceError, ObjectDetectionModel};
 use crate::object_detection::ort_inference_session::OrtInferenceSession;
+use crate::object_detection::preprocessor::{IdentityPreprocessor, Preprocessor};
 use ndarray::{ArrayBase, Axis, Dim, ViewRepr};
 use ort::{inputs, session::SessionOutputs};
 use std::fmt::Display;
@@ -14,6 +16,7 @@ pub struct Yolov11PoseEstimation {
     input_width: usize,
     input_height: usize,
     model_name: String,
+    preprocessor: Box<dyn Preprocessor>,
 }
 
 impl Yolov11PoseEstimation {
@@ -24,15 +27,38 @@ impl Yolov11PoseEstimation {
         input_height: usize,
         model_name: String,
     ) -> ort::Result<Self> {
-        let ort_session = OrtInferenceSession::new(model_path)?;
-        Ok(Yolov11PoseEstimation {
-            ort_session,
+        Self::from_config(ModelConfig {
+            model_path: model_path.to_path_buf(),
             class_names,
             input_width,
             input_height,
             model_name,
         })
     }
+
+    pub fn from_config(config: ModelConfig) -> ort::Result<Self> {
+        let ort_session = OrtInferenceSession::new(&config.model_path)?;
+        ort_session.check_input_size(
+            config.input_width,
+            config.input_height,
+            &config.model_name,
+        )?;
+        Ok(Yolov11PoseEstimation {
+            ort_session,
+            class_names: config.class_names,
+            input_width: config.input_width,
+            input_height: config.input_height,
+            model_name: config.model_name,
+            preprocessor: Box::new(IdentityPreprocessor),
+        })
+    }
+
+    /// Replaces the preprocessing applied to each tile before inference, which defaults to
+    /// IdentityPreprocessor.
+    pub fn with_preprocessor(mut self, preprocessor: Box<dyn Preprocessor>) -> Self {
+        self.preprocessor = preprocessor;
+        self
+    }
 }
 
 impl ObjectDetectionModel<BoundingBoxWithKeypoint> for Yolov11PoseEstimation {
@@ -40,18 +66,35 @@ impl ObjectDetectionModel<BoundingBoxWithKeypoint> for Yolov11PoseEstimation {
         &self,
         input_array: ArrayBase<ViewRepr<&f32>, Dim<[usize; 4]>>,
         confidence: f32,
-    ) -> Vec<Detection<BoundingBoxWithKeypoint>> {
+    ) -> Result<Vec<Detection<BoundingBoxWithKeypoint>>, InferenceError> {
+        let [_, _, tile_height, tile_width] = *input_array.shape() else {
+            unreachable!("The tile array always has four dimensions.");
+        };
+        let input_array = self.preprocessor.prepare(input_array);
+        let inputs =
+            inputs!["images" => input_array.view()].map_err(InferenceError::InputConstruction)?;
         let outputs: SessionOutputs = self
             .ort_session
             .session
-            .run(inputs!["images" => input_array].unwrap())
-            .unwrap();
-        let output = outputs["output0"].try_extract_tensor::<f32>().unwrap();
+            .run(inputs)
+            .map_err(InferenceError::SessionRun)?;
+        let output = outputs
+            .get("output0")
+            .ok_or_else(|| {
+                InferenceError::OutputExtraction(ort::Error::new("The model has no output0."))
+            })?
+            .try_extract_tensor::<f32>()
+            .map_err(InferenceError::OutputExtraction)?;
         let output = output.t();
         let mut detections: Vec<Detection<BoundingBoxWithKeypoint>> = Vec::new();
         for row in output.axis_iter(Axis(0)) {
             let row: Vec<f32> = row.iter().copied().collect();
             println!("Row: {:?}", row);
+            if row.len() < 8 {
+                return Err(InferenceError::OutputExtraction(ort::Error::new(
+                    "The model's output rows are too short for a box and keypoint.",
+                )));
+            }
             let class_id = 0;
             let prob = row[4];
 
@@ -70,20 +113,29 @@ impl ObjectDetectionModel<BoundingBoxWithKeypoint> for Yolov11PoseEstimation {
             let kpy = row[6];
             let _ = row[7]; //Keypoint probability.
 
-            let bbox_wkp = BoundingBoxWithKeypoint::new(
+            let (left, top) = self.preprocessor.to_original(
                 x - (w / 2.0),
                 y - (h / 2.0),
+                tile_width,
+                tile_height,
+            );
+            let (right, bottom) = self.preprocessor.to_original(
                 x + (w / 2.0),
                 y + (h / 2.0),
-                kpx,
-                kpy,
-                label.to_string(),
+                tile_width,
+                tile_height,
             );
+            let (kpx, kpy) = self
+                .preprocessor
+                .to_original(kpx, kpy, tile_width, tile_height);
+            let bbox_wkp =
+                BoundingBoxWithKeypoint::new(left, top, right, bottom, kpx, kpy, label.to_string())
+                    .map_err(InferenceError::InvalidBox)?;
             detections.push(Detection {
-                annotation: bbox_wkp.unwrap(),
+                annotation: bbox_wkp,
                 confidence: prob,
             });
         }
-        detections
+        Ok(detections)
     }
 }
@@ -0,0 +1,397 @@
+use crate::annotations::bounding_box::{BoundingBox, BoundingBoxGeometry};
+use crate::annotations::detection::Detection;
+use crate::object_detection::model_config::ModelConfig;
+use crate::object_detection::object_detection_model::InferenceError;
+use crate::object_detection::object_detection_utils::non_maximum_suppression;
+use crate::object_detection::ort_inference_session::OrtInferenceSession;
+use crate::object_detection::preprocessor::{IdentityPreprocessor, Preprocessor};
+use crate::object_detection::segmentation_model::{SegmentationMask, SegmentationModel};
+use ndarray::{Array, ArrayBase, Axis, Dim, Ix4, ViewRepr};
+use ort::{inputs, session::SessionOutputs};
+use std::path::Path;
+
+pub struct Yolov11Segmentation {
+    ort_session: OrtInferenceSession,
+    class_names: Vec<String>,
+    input_width: usize,
+    input_height: usize,
+    model_name: String,
+    preprocessor: Box<dyn Preprocessor>,
+    nms_iou_threshold: f32,
+}
+
+impl Yolov11Segmentation {
+    pub fn new(
+        model_path: &Path,
+        class_names: Vec<String>,
+        input_width: usize,
+        input_height: usize,
+        model_name: String,
+    ) -> ort::Result<Self> {
+        Self::from_config(ModelConfig {
+            model_path: model_path.to_path_buf(),
+            class_names,
+            input_width,
+            input_height,
+            model_name,
+        })
+    }
+
+    pub fn from_config(config: ModelConfig) -> ort::Result<Self> {
+        let ort_session = OrtInferenceSession::new(&config.model_path)?;
+        ort_session.check_input_size(
+            config.input_width,
+            config.input_height,
+            &config.model_name,
+        )?;
+        Ok(Yolov11Segmentation {
+            ort_session,
+            class_names: config.class_names,
+            input_width: config.input_width,
+            input_height: config.input_height,
+            model_name: config.model_name,
+            preprocessor: Box::new(IdentityPreprocessor),
+            nms_iou_threshold: 0.5,
+        })
+    }
+
+    /// Replaces the preprocessing applied to each tile before inference, which defaults to
+    /// IdentityPreprocessor.
+    pub fn with_preprocessor(mut self, preprocessor: Box<dyn Preprocessor>) -> Self {
+        self.preprocessor = preprocessor;
+        self
+    }
+
+    /// Sets the IoU threshold of the class aware non maximum suppression run before masks are
+    /// decoded, which defaults to 0.5.
+    pub fn with_nms(mut self, iou_threshold: f32) -> Self {
+        self.nms_iou_threshold = iou_threshold;
+        self
+    }
+}
+
+impl SegmentationModel<BoundingBox> for Yolov11Segmentation {
+    fn run_inference(
+        &self,
+        input_array: ArrayBase<ViewRepr<&f32>, Dim<[usize; 4]>>,
+        confidence: f32,
+    ) -> Result<Vec<(Detection<BoundingBox>, SegmentationMask)>, InferenceError> {
+        let [_, _, tile_height, tile_width] = *input_array.shape() else {
+            unreachable!("The tile array always has four dimensions.");
+        };
+        let input_array = self.preprocessor.prepare(input_array);
+        let inputs =
+            inputs!["images" => input_array.view()].map_err(InferenceError::InputConstruction)?;
+        let outputs: SessionOutputs = self
+            .ort_session
+            .session
+            .run(inputs)
+            .map_err(InferenceError::SessionRun)?;
+        let output = outputs
+            .get("output0")
+            .ok_or_else(|| {
+                InferenceError::OutputExtraction(ort::Error::new("The model has no output0."))
+            })?
+            .try_extract_tensor::<f32>()
+            .map_err(InferenceError::OutputExtraction)?;
+        let output = output.t();
+        // The prototypes have shape (1, num_masks, mask_height, mask_width).
+        let prototypes = outputs
+            .get("output1")
+            .ok_or_else(|| {
+                InferenceError::OutputExtraction(ort::Error::new("The model has no output1."))
+            })?
+            .try_extract_tensor::<f32>()
+            .map_err(InferenceError::OutputExtraction)?;
+        let prototypes = prototypes
+            .view()
+            .into_dimensionality::<Ix4>()
+            .ok()
+            .filter(|prototypes| prototypes.dim().0 == 1)
+            .ok_or_else(|| {
+                InferenceError::OutputExtraction(ort::Error::new(
+                    "The model's mask prototypes are not (1, masks, height, width).",
+                ))
+            })?;
+        let prototypes = prototypes.index_axis(Axis(0), 0);
+        let num_masks = prototypes.dim().0;
+        let row_len = output.len_of(Axis(1));
+        let num_classes = row_len.checked_sub(4 + num_masks).ok_or_else(|| {
+            InferenceError::OutputExtraction(ort::Error::new(
+                "The model's output rows are too short for a box and the mask coefficients.",
+            ))
+        })?;
+        let mut candidates: Vec<(Detection<BoundingBox>, Vec<f32>)> = Vec::new();
+        for row in output.axis_iter(Axis(0)) {
+            let row: Vec<f32> = row.iter().copied().collect();
+            let (class_id, prob) = row
+                .iter()
+                .skip(4) // skips bounding box coords.
+                .take(num_classes) // skips mask coefficients.
+                .enumerate()
+                .map(|(index, value)| (index, *value))
+                .reduce(|accum, row| if row.1 > accum.1 { row } else { accum })
+                .ok_or_else(|| {
+                    InferenceError::OutputExtraction(ort::Error::new(
+                        "The model's output rows have no class scores.",
+                    ))
+                })?;
+            if prob < confidence {
+                continue;
+            }
+            let label = match self.class_names.get(class_id) {
+                Some(v) => v,
+                None => &class_id.to_string(),
+            };
+            let x = row[0];
+            let y = row[1];
+            let w = row[2];
+            let h = row[3];
+            let bbox = BoundingBox::new(
+                x - (w / 2.0),
+                y - (h / 2.0),
+                x + (w / 2.0),
+                y + (h / 2.0),
+                label.to_string(),
+            )
+            .map_err(InferenceError::InvalidBox)?;
+            candidates.push((
+                Detection {
+                    annotation: bbox,
+                    confidence: prob,
+                },
+                row[4 + num_classes..].to_vec(),
+            ));
+        }
+        suppress_and_decode_masks(
+            candidates,
+            prototypes,
+            self.nms_iou_threshold,
+            self.input_width,
+            self.input_height,
+        )
+        .into_iter()
+        .map(|(det, mask)| {
+            map_to_tile(
+                det,
+                &mask,
+                self.preprocessor.as_ref(),
+                tile_width,
+                tile_height,
+            )
+        })
+        .collect()
+    }
+}
+
+/// Maps a detection and its mask from the prepared input back onto a tile of the given size.
+///
+/// The box's corners go through the preprocessor's to_original, and each pixel of the tile's
+/// mask is read from the pixel of the input mask its center lands on, so padding added by the
+/// preprocessor is cropped away and the mask is resized to the tile.
+fn map_to_tile(
+    det: Detection<BoundingBox>,
+    mask: &SegmentationMask,
+    preprocessor: &dyn Preprocessor,
+    tile_width: usize,
+    tile_height: usize,
+) -> Result<(Detection<BoundingBox>, SegmentationMask), InferenceError> {
+    let bbox = &det.annotation;
+    let (left, top) = preprocessor.to_original(bbox.left(), bbox.top(), tile_width, tile_height);
+    let (right, bottom) =
+        preprocessor.to_original(bbox.right(), bbox.bottom(), tile_width, tile_height);
+    let annotation = BoundingBox::new(left, top, right, bottom, bbox.category().clone())
+        .map_err(InferenceError::InvalidBox)?;
+    let (mask_height, mask_width) = mask.dim();
+    let tile_mask = Array::from_shape_fn((tile_height, tile_width), |(y, x)| {
+        let (input_x, input_y) =
+            preprocessor.to_prepared(x as f32 + 0.5, y as f32 + 0.5, tile_width, tile_height);
+        let (input_x, input_y) = (input_x.floor(), input_y.floor());
+        input_x >= 0.0
+            && input_y >= 0.0
+            && (input_x as usize) < mask_width
+            && (input_y as usize) < mask_height
+            && mask[[input_y as usize, input_x as usize]]
+    });
+    Ok((
+        Detection {
+            annotation,
+            confidence: det.confidence,
+        },
+        tile_mask,
+    ))
+}
+
+/// Runs non maximum suppression on the candidate detections, then decodes the masks of the
+/// detections that survive.
+///
+/// A model proposes many overlapping boxes for each object, and each mask is as large as the
+/// model's input, so decoding only the survivors' masks saves most of the work and memory.
+fn suppress_and_decode_masks(
+    candidates: Vec<(Detection<BoundingBox>, Vec<f32>)>,
+    prototypes: ArrayBase<ViewRepr<&f32>, Dim<[usize; 3]>>,
+    iou_threshold: f32,
+    input_width: usize,
+    input_height: usize,
+) -> Vec<(Detection<BoundingBox>, SegmentationMask)> {
+    let dets: Vec<Detection<BoundingBox>> = candidates.iter().map(|(det, _)| det.clone()).collect();
+    non_maximum_suppression(dets, iou_threshold)
+        .into_iter()
+        .filter_map(|det| {
+            let (_, coefficients) = candidates.iter().find(|(candidate, _)| *candidate == det)?;
+            let mask = decode_mask(
+                coefficients,
+                prototypes,
+                &det.annotation,
+                input_width,
+                input_height,
+            );
+            Some((det, mask))
+        })
+        .collect()
+}
+
+/// Reconstructs a binary mask from a detection's mask coefficients.
+///
+/// YOLO segmentation models do not output masks directly. Instead, they output a small set of
+/// prototype masks shared by every detection, and each detection has a vector of coefficients.
+/// The detection's mask is the linear combination of the prototypes weighted by the coefficients,
+/// passed through a sigmoid and thresholded at 0.5. Since sigmoid(x) > 0.5 exactly when x > 0,
+/// we threshold the linear combination at 0 directly.
+///
+/// The prototypes are at a lower resolution than the model input, so the mask is upsampled with
+/// nearest neighbor interpolation to (input_height, input_width), then every pixel whose center
+/// lies outside of the bounding box is set to false.
+pub fn decode_mask(
+    coefficients: &[f32],
+    prototypes: ArrayBase<ViewRepr<&f32>, Dim<[usize; 3]>>,
+    bbox: &BoundingBox,
+    input_width: usize,
+    input_height: usize,
+) -> SegmentationMask {
+    let (_, prototype_height, prototype_width) = prototypes.dim();
+    let mut combined = Array::zeros((prototype_height, prototype_width));
+    for (coefficient, prototype) in coefficients.iter().zip(prototypes.axis_iter(Axis(0))) {
+        combined.scaled_add(*coefficient, &prototype);
+    }
+    Array::from_shape_fn((input_height, input_width), |(y, x)| {
+        let pixel_center_x = x as f32 + 0.5;
+        let pixel_center_y = y as f32 + 0.5;
+        let inside_box = pixel_center_x >= bbox.left()
+            && pixel_center_x < bbox.right()
+            && pixel_center_y >= bbox.top()
+            && pixel_center_y < bbox.bottom();
+        let prototype_y = y * prototype_height / input_height;
+        let prototype_x = x * prototype_width / input_width;
+        inside_box && combined[[prototype_y, prototype_x]] > 0.0
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::object_detection::preprocessor::LetterboxPreprocessor;
+    use ndarray::s;
+
+    #[test]
+    fn decode_mask_synthetic() {
+        // Prototype 0 covers the left half, prototype 1 covers the top row. With coefficients
+        // (1, -2) the mask is the left half without the top row.
+        let prototypes = Array::from_shape_vec(
+            (2, 4, 4),
+            vec![
+                1_f32, 1_f32, 0_f32, 0_f32, 1_f32, 1_f32, 0_f32, 0_f32, 1_f32, 1_f32, 0_f32, 0_f32,
+                1_f32, 1_f32, 0_f32, 0_f32, 1_f32, 1_f32, 1_f32, 1_f32, 0_f32, 0_f32, 0_f32, 0_f32,
+                0_f32, 0_f32, 0_f32, 0_f32, 0_f32, 0_f32, 0_f32, 0_f32,
+            ],
+        )
+        .unwrap();
+        // The box excludes the bottom row, so the mask is cropped there too.
+        let bbox = BoundingBox::new(0_f32, 0_f32, 4_f32, 3_f32, "fluids".to_string()).unwrap();
+        let mask = decode_mask(&[1_f32, -2_f32], prototypes.view(), &bbox, 4, 4);
+        let true_mask = Array::from_shape_vec(
+            (4, 4),
+            vec![
+                false, false, false, false, true, true, false, false, true, true, false, false,
+                false, false, false, false,
+            ],
+        )
+        .unwrap();
+        assert_eq!(mask, true_mask);
+    }
+
+    #[test]
+    fn decode_mask_upsamples_prototypes() {
+        let prototypes =
+            Array::from_shape_vec((1, 2, 2), vec![1_f32, -1_f32, -1_f32, 1_f32]).unwrap();
+        let bbox = BoundingBox::new(0_f32, 0_f32, 4_f32, 4_f32, "fluids".to_string()).unwrap();
+        let mask = decode_mask(&[1_f32], prototypes.view(), &bbox, 4, 4);
+        let true_mask = Array::from_shape_vec(
+            (4, 4),
+            vec![
+                true, true, false, false, true, true, false, false, false, false, true, true,
+                false, false, true, true,
+            ],
+        )
+        .unwrap();
+        assert_eq!(mask, true_mask);
+    }
+
+    #[test]
+    fn suppress_and_decode_masks_only_decodes_survivors() {
+        let prototypes = Array::from_shape_vec((2, 1, 1), vec![1_f32, -1_f32]).unwrap();
+        let create_candidate = |left: f32, confidence: f32, coefficients: Vec<f32>| {
+            let bbox = BoundingBox::new(left, 0.0, left + 4.0, 4.0, "fluids".to_string()).unwrap();
+            (
+                Detection {
+                    annotation: bbox,
+                    confidence,
+                },
+                coefficients,
+            )
+        };
+        // The first two boxes overlap, so the less confident one, whose mask would be empty, is
+        // suppressed before its mask is decoded.
+        let candidates = vec![
+            create_candidate(0.0, 0.6, vec![0.0, 1.0]),
+            create_candidate(0.5, 0.9, vec![1.0, 0.0]),
+            create_candidate(4.0, 0.8, vec![1.0, 0.0]),
+        ];
+        let results = suppress_and_decode_masks(candidates, prototypes.view(), 0.5, 8, 4);
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0].0.confidence, 0.9);
+        assert_eq!(results[1].0.confidence, 0.8);
+        assert!(results.iter().all(|(_, mask)| mask.dim() == (4, 8)));
+        assert_eq!(results[0].1.iter().filter(|&&pixel| pixel).count(), 16);
+        assert_eq!(results[1].1.iter().filter(|&&pixel| pixel).count(), 16);
+    }
+
+    #[test]
+    fn map_to_tile_undoes_letterbox() {
+        // A 2x4 tile is letterboxed into an 8x8 input, doubled in size with two padded rows
+        // above it. The detection covers the left half of the tile's content.
+        let preprocessor = LetterboxPreprocessor {
+            width: 8,
+            height: 8,
+            fill_value: 0.0,
+        };
+        let det = Detection {
+            annotation: BoundingBox::new(0_f32, 2_f32, 4_f32, 6_f32, "fluids".to_string()).unwrap(),
+            confidence: 0.9_f32,
+        };
+        let mut input_mask: SegmentationMask = Array::from_elem((8, 8), false);
+        input_mask.slice_mut(s![2..6, 0..4]).fill(true);
+        let (tile_det, tile_mask) = map_to_tile(det, &input_mask, &preprocessor, 4, 2).unwrap();
+        assert_eq!(
+            tile_det.annotation,
+            BoundingBox::new(0_f32, 0_f32, 2_f32, 2_f32, "fluids".to_string()).unwrap()
+        );
+        assert_eq!(tile_det.confidence, 0.9_f32);
+        let true_mask = Array::from_shape_vec(
+            (2, 4),
+            vec![true, true, false, false, true, true, false, false],
+        )
+        .unwrap();
+        assert_eq!(tile_mask, true_mask);
+    }
+}
@@ -1,13 +1,84 @@
 extern crate openblas_src;
 
+use crate::annotations::bounding_box::{BoundingBox, BoundingBoxGeometry};
+use crate::annotations::detection::Detection;
 use crate::annotations::point::Point;
 use itertools::Itertools;
-use ndarray::{Array, ArrayBase, Axis, Dim, OwnedRepr, s, stack};
-use ndarray_linalg::Solve;
+use ndarray::{Array, ArrayBase, Axis, Dim, OwnedRepr, ViewRepr, Zip, s, stack};
+use ndarray_linalg::{Eigh, Solve, UPLO};
+use std::collections::HashMap;
 use std::f32::EPSILON;
 use std::f32::consts::PI;
+use std::fmt::Display;
 
-struct CoherentPointDriftTransform {
+/// The largest usable weight of the uniform distribution. The expectation step divides by
+/// 1 - weight, so a weight of 1 would fill the probability matrix with infinities.
+const MAX_WEIGHT_OF_UNIFORM_DIST: f32 = 0.999;
+
+/// The Gaussian kernel approximated by its top eigenvectors and eigenvalues.
+///
+/// The kernel is symmetric, so G ~= Q * diag(S) * Q^T, where the columns of Q are the eigenvectors
+/// belonging to the largest eigenvalues S.
+struct LowRankKernel {
+    eigenvectors: ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>,
+    eigenvalues: ArrayBase<OwnedRepr<f32>, Dim<[usize; 1]>>,
+}
+
+/// A rotation and uniform scale about source_mean, followed by a move onto target_mean.
+struct RigidAlignment {
+    rotation: ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>,
+    scale: f32,
+    source_mean: ArrayBase<OwnedRepr<f32>, Dim<[usize; 1]>>,
+    target_mean: ArrayBase<OwnedRepr<f32>, Dim<[usize; 1]>>,
+}
+
+impl RigidAlignment {
+    fn apply(
+        &self,
+        points: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>,
+    ) -> ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>> {
+        self.scale * (points - &self.source_mean).dot(&self.rotation.t()) + &self.target_mean
+    }
+}
+
+/// The settings of a registration that have sensible defaults.
+#[derive(Clone, Debug, PartialEq)]
+pub struct CpdOptions {
+    /// The weight of the uniform distribution, which accounts for outliers. Must be in [0, 1),
+    /// and values outside of it are clamped to [0, 0.999]. Defaults to 0.
+    pub weight_of_uniform_dist: f32,
+    /// Registration stops once the variance changes by less than this, defaults to 0.001.
+    pub tolerance: f32,
+    /// Defaults to 100.
+    pub max_iterations: u32,
+    /// Approximates the Gaussian kernel by its top low_rank eigenvectors, which is much faster
+    /// for large point sets. Defaults to None, using the full kernel.
+    pub low_rank: Option<usize>,
+    /// Whether to remove gross rotation, scale and translation with a rigid fit before the
+    /// non-rigid registration, defaults to false.
+    pub prealign: bool,
+    /// Source points outside this box are left out of the registration. Defaults to None,
+    /// keeping every source point.
+    pub region_of_interest: Option<BoundingBox>,
+    /// Whether to record the transformed points at every iteration, defaults to false.
+    pub debug: bool,
+}
+
+impl Default for CpdOptions {
+    fn default() -> Self {
+        CpdOptions {
+            weight_of_uniform_dist: 0.0,
+            tolerance: 0.001,
+            max_iterations: 100,
+            low_rank: None,
+            prealign: false,
+            region_of_interest: None,
+            debug: false,
+        }
+    }
+}
+
+pub struct CoherentPointDriftTransform {
     /// The points to try to move the source towards.
     target_points: ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>,
     /// The points to move towards the target points. May contain outliers or
@@ -24,8 +95,8 @@ struct CoherentPointDriftTransform {
     /// A parameter that can end the iteration process early if the change in variance
     /// is less than the tolerance.
     tolerance: f32,
-    /// The weight of the uniform distribution. Must be between 0 and 1.
-    /// See the coherent point drift paper for more details.
+    /// The weight of the uniform distribution. Must be in [0, 1), as a weight of 1 means
+    /// every point is noise. See the coherent point drift paper for more details.
     weight_of_uniform_dist: f32,
     /// The maximum number of iterations to perform.
     max_iterations: u32,
@@ -42,19 +113,50 @@ struct CoherentPointDriftTransform {
     history: Vec<String>,
     /// Whether or not to record the history of the transformed points.
     debug: bool,
+    /// The Gaussian kernel between every pair of source points. It only depends on the source
+    /// points and beta, so it is computed once on construction.
+    gaussian_kernel: ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>,
+    /// Used in place of the full Gaussian kernel when a low rank approximation is requested.
+    low_rank_kernel: Option<LowRankKernel>,
+    /// The number of iterations performed by the last call to register.
+    iterations: u32,
+    /// Whether to remove gross rotation, scale and translation with a rigid fit before the
+    /// non-rigid registration.
+    prealign: bool,
+    /// The rigid fit found by prealignment, which moved the original source points onto the
+    /// current source points.
+    prealignment: Option<RigidAlignment>,
+    /// The index among the source points passed in of each row of source_points. Differs from
+    /// the row index when points were left out by the region of interest.
+    kept_source_indices: Vec<usize>,
+    /// The indices of the source points passed in that fell outside the region of interest.
+    excluded_source_indices: Vec<usize>,
 }
 
 impl CoherentPointDriftTransform {
+    /// Sets up a registration of the source points onto the target points.
+    ///
+    /// Source points outside the options' region_of_interest, if one is given, are left out of
+    /// the registration entirely. Matchings still refer to source points by their index in
+    /// source_points, and the left out indices are given by excluded_source_indices. If the region
+    /// leaves out every source point, there is nothing to register, so register does nothing and
+    /// apply leaves points where they are.
     pub fn new(
         target_points: ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>,
         source_points: ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>,
         lambda: f32,
         beta: f32,
-        weight_of_uniform_dist: Option<f32>,
-        tolerance: Option<f32>,
-        max_iterations: Option<u32>,
-        debug: Option<bool>,
+        options: CpdOptions,
     ) -> CoherentPointDriftTransform {
+        let (kept_source_indices, excluded_source_indices): (Vec<usize>, Vec<usize>) =
+            (0..source_points.dim().0).partition(|&ix| match &options.region_of_interest {
+                Some(roi) => {
+                    let (x, y) = (source_points[[ix, 0]], source_points[[ix, 1]]);
+                    roi.left() <= x && x <= roi.right() && roi.top() <= y && y <= roi.bottom()
+                }
+                None => true,
+            });
+        let source_points = source_points.select(Axis(0), &kept_source_indices);
         let num_target_points: usize = target_points.dim().0;
         let dimensions: usize = target_points.dim().1;
         let num_source_points: usize = source_points.dim().0;
@@ -64,6 +166,14 @@ impl CoherentPointDriftTransform {
                 dimensions as f32 * num_target_points as f32 * num_source_points as f32;
             sum_sq_dists / denominator
         };
+        let weight_of_uniform_dist = options
+            .weight_of_uniform_dist
+            .clamp(0.0, MAX_WEIGHT_OF_UNIFORM_DIST);
+        let gaussian_kernel = compute_gaussian_kernel(&source_points, &source_points, beta);
+        let low_rank_kernel = options
+            .low_rank
+            .filter(|_| num_source_points > 0)
+            .map(|rank| compute_low_rank_kernel(&gaussian_kernel, rank));
         CoherentPointDriftTransform {
             target_points: target_points,
             source_points: source_points.clone(),
@@ -71,14 +181,21 @@ impl CoherentPointDriftTransform {
             beta: beta,
             transformed_points: source_points,
             variance: initial_variance,
-            tolerance: tolerance.unwrap_or(0.001),
-            weight_of_uniform_dist: weight_of_uniform_dist.unwrap_or(0.0),
-            max_iterations: max_iterations.unwrap_or(100),
+            tolerance: options.tolerance,
+            weight_of_uniform_dist,
+            max_iterations: options.max_iterations,
             change_in_variance: f32::MAX,
             probability_of_match: Array::zeros((num_source_points, num_target_points)),
             w_coefs: Array::zeros((num_source_points, dimensions)),
             history: Vec::new(),
-            debug: debug.unwrap_or(false),
+            debug: options.debug,
+            gaussian_kernel,
+            low_rank_kernel,
+            iterations: 0,
+            prealign: options.prealign,
+            prealignment: None,
+            kept_source_indices,
+            excluded_source_indices,
         }
     }
 
@@ -87,10 +204,7 @@ impl CoherentPointDriftTransform {
         source_points: Vec<Point>,
         lambda: f32,
         beta: f32,
-        weight_of_uniform_dist: Option<f32>,
-        tolerance: Option<f32>,
-        max_iterations: Option<u32>,
-        debug: Option<bool>,
+        options: CpdOptions,
     ) -> CoherentPointDriftTransform {
         let target_point_array: ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>> = {
             let mut flattened_point_vec = Vec::new();
@@ -113,18 +227,68 @@ impl CoherentPointDriftTransform {
             source_point_array,
             lambda,
             beta,
-            weight_of_uniform_dist,
-            tolerance,
-            max_iterations,
-            debug,
+            options,
         )
     }
 
+    /// Registers detections against a set of named centroids.
+    ///
+    /// The center of each detection's box is used as a source point, and the centroids are the
+    /// target points. After registration, the matching is returned as a map from the index of a
+    /// source detection to the name of the centroid it was matched with. Names are usually the
+    /// category of the landmark, so any type can be used, such as a Category enum.
+    pub fn from_detections<T: BoundingBoxGeometry + Display, N: Clone>(
+        target_centroids: &[(N, Point)],
+        source_detections: &[Detection<T>],
+        lambda: f32,
+        beta: f32,
+        options: CpdOptions,
+    ) -> (CoherentPointDriftTransform, HashMap<usize, N>) {
+        let target_points: Vec<Point> = target_centroids.iter().map(|(_, p)| *p).collect();
+        let source_points: Vec<Point> = source_detections
+            .iter()
+            .map(|det| {
+                let (x, y) = det.annotation.center();
+                Point { x, y }
+            })
+            .collect();
+        let mut transform = CoherentPointDriftTransform::from_point_vectors(
+            target_points,
+            source_points,
+            lambda,
+            beta,
+            options,
+        );
+        transform.register();
+        let matched_names: HashMap<usize, N> = transform
+            .generate_matching()
+            .into_iter()
+            .map(|(source_ix, target_ix)| (source_ix, target_centroids[target_ix].0.clone()))
+            .collect();
+        (transform, matched_names)
+    }
+
     pub fn register(&mut self) {
-        let gaussian_kernel =
-            compute_gaussian_kernel(&self.source_points, &self.source_points, self.beta);
-        self.transformed_points =
-            compute_transformed_point_cloud(&self.source_points, &gaussian_kernel, &self.w_coefs);
+        // Without source points the variance is undefined, and the identity is the only answer.
+        if self.source_points.is_empty() {
+            return;
+        }
+        if self.prealign {
+            self.rigid_prealignment();
+        }
+        self.transformed_points = match &self.low_rank_kernel {
+            Some(kernel) => compute_low_rank_transformed_point_cloud(
+                &self.source_points,
+                &kernel.eigenvectors,
+                &kernel.eigenvalues,
+                &self.w_coefs,
+            ),
+            None => compute_transformed_point_cloud(
+                &self.source_points,
+                &self.gaussian_kernel,
+                &self.w_coefs,
+            ),
+        };
         let mut iteration = 0;
         while iteration < self.max_iterations && self.change_in_variance > self.tolerance {
             if self.debug {
@@ -138,6 +302,182 @@ impl CoherentPointDriftTransform {
             self.maximization();
             iteration += 1;
         }
+        self.iterations = iteration;
+    }
+
+    /// Pairs source points with target points using the probability of match matrix.
+    ///
+    /// Greedily takes the most probable remaining (source, target) pair until either set of points
+    /// is exhausted, so each point appears in at most one pair. Returns (source_ix, target_ix)
+    /// pairs in order of decreasing probability.
+    pub fn generate_matching(&self) -> Vec<(usize, usize)> {
+        self.generate_matching_with_scores()
+            .into_iter()
+            .map(|(pair, _)| pair)
+            .collect()
+    }
+
+    /// Pairs source points with target points like generate_matching, along with each pair's
+    /// probability of match.
+    ///
+    /// The probability lets callers drop matches the registration was unsure of. Pairs are in
+    /// order of decreasing probability.
+    pub fn generate_matching_with_scores(&self) -> Vec<((usize, usize), f32)> {
+        self.generate_row_matching_with_scores()
+            .into_iter()
+            .map(|((row_ix, target_ix), probability)| {
+                ((self.kept_source_indices[row_ix], target_ix), probability)
+            })
+            .collect()
+    }
+
+    /// The greedy matching, with sources given by their row in source_points.
+    fn generate_row_matching_with_scores(&self) -> Vec<((usize, usize), f32)> {
+        let mut candidates: Vec<(usize, usize, f32)> = self
+            .probability_of_match
+            .indexed_iter()
+            .map(|((source_ix, target_ix), probability)| (source_ix, target_ix, *probability))
+            .collect();
+        candidates.sort_by(|a, b| b.2.total_cmp(&a.2));
+        let mut source_is_matched = vec![false; self.probability_of_match.dim().0];
+        let mut target_is_matched = vec![false; self.probability_of_match.dim().1];
+        let mut matching: Vec<((usize, usize), f32)> = Vec::new();
+        for (source_ix, target_ix, probability) in candidates {
+            if source_is_matched[source_ix] || target_is_matched[target_ix] {
+                continue;
+            }
+            source_is_matched[source_ix] = true;
+            target_is_matched[target_ix] = true;
+            matching.push(((source_ix, target_ix), probability));
+        }
+        matching
+    }
+
+    /// Pairs source points with target points that are each other's most probable match.
+    ///
+    /// A pair is kept only if the target is the source's most probable target and the source is
+    /// also the target's most probable source. This is stricter than generate_matching, which
+    /// pairs every point it can, so it drops points whose match was only left over. Pairs are in
+    /// order of decreasing probability.
+    pub fn mutual_matches(&self) -> Vec<(usize, usize)> {
+        let argmax = |lane: ArrayBase<ViewRepr<&f32>, Dim<[usize; 1]>>| {
+            lane.iter()
+                .enumerate()
+                .max_by(|a, b| a.1.total_cmp(b.1).then_with(|| b.0.cmp(&a.0)))
+                .map(|(ix, _)| ix)
+        };
+        let best_source_per_target: Vec<Option<usize>> = self
+            .probability_of_match
+            .columns()
+            .into_iter()
+            .map(argmax)
+            .collect();
+        let mut matches: Vec<(usize, usize)> = self
+            .probability_of_match
+            .rows()
+            .into_iter()
+            .enumerate()
+            .filter_map(|(source_ix, row)| {
+                let target_ix = argmax(row)?;
+                (best_source_per_target[target_ix] == Some(source_ix))
+                    .then_some((source_ix, target_ix))
+            })
+            .collect();
+        matches.sort_by(|a, b| {
+            self.probability_of_match[*b].total_cmp(&self.probability_of_match[*a])
+        });
+        matches
+            .into_iter()
+            .map(|(row_ix, target_ix)| (self.kept_source_indices[row_ix], target_ix))
+            .collect()
+    }
+
+    /// Pairs source points with target points like generate_matching, but drops any pair whose
+    /// transformed source point ended up farther than max_residual from its target.
+    ///
+    /// A pair with a large residual is usually a detection that was matched only because every
+    /// point has to go somewhere, so it should not be trusted even if its class agrees.
+    pub fn generate_matching_within(&self, max_residual: f32) -> Vec<(usize, usize)> {
+        self.generate_row_matching_with_scores()
+            .into_iter()
+            .filter(|&((row_ix, target_ix), _)| {
+                let transformed = self.transformed_points.row(row_ix);
+                let target = self.target_points.row(target_ix);
+                let residual = (transformed[0] - target[0]).hypot(transformed[1] - target[1]);
+                residual <= max_residual
+            })
+            .map(|((row_ix, target_ix), _)| (self.kept_source_indices[row_ix], target_ix))
+            .collect()
+    }
+
+    /// Moves arbitrary points with the displacement field found by register.
+    ///
+    /// Each point is first moved by the rigid prealignment, if one was run, then by the Gaussian
+    /// weighted sum of the source points' coefficients. Applied to the original source points,
+    /// this gives the transformed points. The exact kernel is used even when registering with a low rank
+    /// kernel, so the result then differs slightly from the transformed points.
+    pub fn apply(&self, points: &[Point]) -> Vec<Point> {
+        let flattened_point_vec: Vec<f32> = points.iter().flat_map(|p| [p.x, p.y]).collect();
+        let mut point_array: ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>> =
+            Array::from_shape_vec((points.len(), 2), flattened_point_vec).unwrap();
+        if let Some(alignment) = &self.prealignment {
+            point_array = alignment.apply(&point_array);
+        }
+        let kernel = compute_gaussian_kernel(&self.source_points, &point_array, self.beta);
+        array_to_points(&(&point_array + &kernel.dot(&self.w_coefs)))
+    }
+
+    /// Returns the indices of the source points left out because they were outside the region of
+    /// interest. Matchings never include them.
+    pub fn excluded_source_indices(&self) -> &[usize] {
+        &self.excluded_source_indices
+    }
+
+    /// Returns the source points after registration, without those outside the region of
+    /// interest.
+    pub fn transformed_points_as_vec(&self) -> Vec<Point> {
+        array_to_points(&self.transformed_points)
+    }
+
+    pub fn target_points_as_vec(&self) -> Vec<Point> {
+        array_to_points(&self.target_points)
+    }
+
+    /// Returns the source points, which are already rigidly aligned if prealignment was run.
+    pub fn source_points_as_vec(&self) -> Vec<Point> {
+        array_to_points(&self.source_points)
+    }
+
+    /// Runs rigid coherent point drift, then makes the aligned points the new source points.
+    ///
+    /// The rigid fit only has a rotation, a uniform scale and a translation, so it can recover
+    /// from poses far too different for the non-rigid fit, such as a rotated photo. The kernels
+    /// depend on the source points, so they are recomputed from the aligned points.
+    fn rigid_prealignment(&mut self) {
+        let mut iteration = 0;
+        while iteration < self.max_iterations && self.change_in_variance > self.tolerance {
+            self.expectation();
+            let previous_variance = self.variance;
+            let alignment;
+            (alignment, self.variance) = compute_rigid_alignment(
+                &self.target_points,
+                &self.source_points,
+                &self.probability_of_match,
+                self.tolerance,
+            );
+            self.transformed_points = alignment.apply(&self.source_points);
+            self.prealignment = Some(alignment);
+            self.change_in_variance = (self.variance - previous_variance).abs();
+            iteration += 1;
+        }
+        self.source_points = self.transformed_points.clone();
+        self.gaussian_kernel =
+            compute_gaussian_kernel(&self.source_points, &self.source_points, self.beta);
+        if let Some(kernel) = &self.low_rank_kernel {
+            let rank = kernel.eigenvalues.len();
+            self.low_rank_kernel = Some(compute_low_rank_kernel(&self.gaussian_kernel, rank));
+        }
+        self.change_in_variance = f32::MAX;
     }
 
     fn expectation(&mut self) {
@@ -168,19 +508,40 @@ impl CoherentPointDriftTransform {
         // TODO: Test whether this is necessary.
         let sum_of_probability_columns = self.probability_of_match.sum_axis(Axis(0));
         let PX = self.probability_of_match.dot(&self.target_points);
-        let gaussian_kernel =
-            compute_gaussian_kernel(&self.source_points, &self.source_points, self.beta);
-
-        self.w_coefs = compute_updated_transform(
-            &self.source_points,
-            &sum_of_probability_rows,
-            &PX,
-            &gaussian_kernel,
-            self.lambda,
-            self.variance,
-        );
-        self.transformed_points =
-            compute_transformed_point_cloud(&self.source_points, &gaussian_kernel, &self.w_coefs);
+        match &self.low_rank_kernel {
+            Some(kernel) => {
+                self.w_coefs = compute_low_rank_updated_transform(
+                    &self.source_points,
+                    &sum_of_probability_rows,
+                    &PX,
+                    &kernel.eigenvectors,
+                    &kernel.eigenvalues,
+                    self.lambda,
+                    self.variance,
+                );
+                self.transformed_points = compute_low_rank_transformed_point_cloud(
+                    &self.source_points,
+                    &kernel.eigenvectors,
+                    &kernel.eigenvalues,
+                    &self.w_coefs,
+                );
+            }
+            None => {
+                self.w_coefs = compute_updated_transform(
+                    &self.source_points,
+                    &sum_of_probability_rows,
+                    &PX,
+                    &self.gaussian_kernel,
+                    self.lambda,
+                    self.variance,
+                );
+                self.transformed_points = compute_transformed_point_cloud(
+                    &self.source_points,
+                    &self.gaussian_kernel,
+                    &self.w_coefs,
+                );
+            }
+        }
         (self.variance, self.change_in_variance) = update_variance(
             &self.target_points,
             &self.transformed_points,
@@ -193,22 +554,123 @@ impl CoherentPointDriftTransform {
     }
 }
 
+/// What to do with a matched detection whose category disagrees with its centroid's name.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MismatchPolicy {
+    /// Discard the detection.
+    Drop,
+    /// Keep the detection, overwriting its category with the centroid's name.
+    Relabel,
+    /// Keep the detection as it is.
+    Keep,
+}
+
+/// Keeps the detections that were matched to a centroid by from_detections.
+///
+/// Unmatched detections are always dropped. A matched detection whose category differs from the
+/// name of its centroid is handled by class_mismatch, since for review it can be more useful to
+/// keep it under the centroid's authoritative label than to throw it away.
+pub fn filter_matched_detections<T: BoundingBoxGeometry + Display>(
+    dets: Vec<Detection<T>>,
+    matched_names: &HashMap<usize, T::Category>,
+    class_mismatch: MismatchPolicy,
+) -> Vec<Detection<T>> {
+    dets.into_iter()
+        .enumerate()
+        .filter_map(|(ix, mut det)| {
+            let name = matched_names.get(&ix)?;
+            if det.annotation.category() == name {
+                return Some(det);
+            }
+            match class_mismatch {
+                MismatchPolicy::Drop => None,
+                MismatchPolicy::Relabel => {
+                    *det.annotation.category_mut() = name.clone();
+                    Some(det)
+                }
+                MismatchPolicy::Keep => Some(det),
+            }
+        })
+        .collect()
+}
+
+/// Finds the rotation, uniform scale and translation that best move the source points onto the
+/// target points, weighted by the probability of match matrix.
+///
+/// This is the maximization step of rigid coherent point drift, specialized to two dimensions
+/// where the optimal rotation has a closed form. Returns the alignment and the new variance.
+fn compute_rigid_alignment(
+    target_points: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>,
+    source_points: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>,
+    probability_of_match: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>,
+    tolerance: f32,
+) -> (RigidAlignment, f32) {
+    let sum_of_probability_rows = probability_of_match.sum_axis(Axis(1));
+    let sum_of_probability_columns = probability_of_match.sum_axis(Axis(0));
+    let total_probability = sum_of_probability_rows.sum().max(f32::EPSILON);
+    let target_mean = sum_of_probability_columns.dot(target_points) / total_probability;
+    let source_mean = sum_of_probability_rows.dot(source_points) / total_probability;
+    let centered_targets = target_points - &target_mean;
+    let centered_sources = source_points - &source_mean;
+    let a = centered_targets
+        .t()
+        .dot(&probability_of_match.t().dot(&centered_sources));
+    let angle = (a[[1, 0]] - a[[0, 1]]).atan2(a[[0, 0]] + a[[1, 1]]);
+    let (sin, cos) = angle.sin_cos();
+    let rotation = Array::from_shape_vec((2, 2), vec![cos, -sin, sin, cos]).unwrap();
+    let trace_a_r = (&a * &rotation).sum();
+    let source_spread = sum_of_probability_rows.dot(&centered_sources.powi(2).sum_axis(Axis(1)));
+    let scale = trace_a_r / source_spread.max(f32::EPSILON);
+    let target_spread = sum_of_probability_columns.dot(&centered_targets.powi(2).sum_axis(Axis(1)));
+    let dimensions = target_points.dim().1 as f32;
+    let mut variance = (target_spread - scale * trace_a_r) / (total_probability * dimensions);
+    if variance <= 0.0 {
+        variance = tolerance / 10.0;
+    }
+    (
+        RigidAlignment {
+            rotation,
+            scale,
+            source_mean,
+            target_mean,
+        },
+        variance,
+    )
+}
+
 /// Computes the squared euclidean distance between all vectors in A and B.
+///
+/// Row i of the result holds the distances from the i-th vector of B to every vector of A. Rows
+/// are filled one at a time, so no temporaries of size M x N x d are allocated, and with the
+/// parallel feature the rows are filled in parallel.
 fn compute_squared_distance(
     matrix_a: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>,
     matrix_b: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>,
 ) -> ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>> {
-    let matrix_a_3d: ArrayBase<OwnedRepr<f32>, Dim<[usize; 3]>> = Array::from_shape_vec(
-        (1, matrix_a.dim().0, matrix_a.dim().1),
-        matrix_a.clone().into_raw_vec_and_offset().0,
-    )
-    .unwrap();
-    let matrix_b_3d: ArrayBase<OwnedRepr<f32>, Dim<[usize; 3]>> = Array::from_shape_vec(
-        (matrix_b.dim().0, 1, matrix_b.dim().1),
-        matrix_b.clone().into_raw_vec_and_offset().0,
-    )
-    .unwrap();
-    (matrix_a_3d - matrix_b_3d).powi(2).sum_axis(Axis(2))
+    let mut distances: ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>> =
+        Array::zeros((matrix_b.dim().0, matrix_a.dim().0));
+    let fill_row = |mut row: ArrayBase<ViewRepr<&mut f32>, Dim<[usize; 1]>>,
+                    b_vector: ArrayBase<ViewRepr<&f32>, Dim<[usize; 1]>>| {
+        for (distance, a_vector) in row.iter_mut().zip(matrix_a.rows()) {
+            *distance = a_vector
+                .iter()
+                .zip(b_vector.iter())
+                .map(|(a, b)| (a - b).powi(2))
+                .sum();
+        }
+    };
+    let rows = Zip::from(distances.rows_mut()).and(matrix_b.rows());
+    #[cfg(feature = "parallel")]
+    rows.par_for_each(fill_row);
+    #[cfg(not(feature = "parallel"))]
+    rows.for_each(fill_row);
+    distances
+}
+
+#[cfg(test)]
+thread_local! {
+    /// Counts calls to compute_gaussian_kernel so tests can check that the kernel is cached.
+    static GAUSSIAN_KERNEL_CALLS: std::cell::Cell<usize> = const { std::cell::Cell::new(0) };
 }
 
 /// Computes the gaussian kernel for CPD.
@@ -217,6 +679,8 @@ fn compute_gaussian_kernel(
     matrix_b: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>,
     beta: f32,
 ) -> ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>> {
+    #[cfg(test)]
+    GAUSSIAN_KERNEL_CALLS.with(|calls| calls.set(calls.get() + 1));
     let sum_sq_dists = compute_squared_distance(matrix_a, matrix_b);
     (-sum_sq_dists / (2.0 * beta.powi(2))).exp()
 }
@@ -267,6 +731,56 @@ fn compute_updated_transform(
     solve_matrices(&matrix_a, &matrix_b)
 }
 
+/// Approximates the Gaussian kernel with its rank largest eigenvalues and their eigenvectors.
+fn compute_low_rank_kernel(
+    gaussian_kernel: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>,
+    rank: usize,
+) -> LowRankKernel {
+    let num_points = gaussian_kernel.dim().0;
+    let rank = rank.clamp(1, num_points);
+    // Eigenvalues are returned in ascending order, so the largest are at the end.
+    let (eigenvalues, eigenvectors) = gaussian_kernel.eigh(UPLO::Lower).unwrap();
+    LowRankKernel {
+        eigenvectors: eigenvectors.slice(s![.., num_points - rank..]).to_owned(),
+        eigenvalues: eigenvalues.slice(s![num_points - rank..]).to_owned(),
+    }
+}
+
+fn compute_low_rank_transformed_point_cloud(
+    source_points: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>,
+    eigenvectors: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>,
+    eigenvalues: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 1]>>,
+    w_coefs: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>,
+) -> ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>> {
+    let projected = eigenvectors.t().dot(w_coefs) * eigenvalues.view().insert_axis(Axis(1));
+    source_points + eigenvectors.dot(&projected)
+}
+
+/// Solves for the transform with the low rank kernel.
+///
+/// Uses the Woodbury identity so that the only system solved is rank x rank instead of M x M:
+/// W = (F - dP*Q * (lambda*variance*S^-1 + Q^T*dP*Q)^-1 * Q^T*F) / (lambda*variance),
+/// where F = PX - dP*Y and dP is the diagonal matrix of summed probability rows. Below, dP*Q is
+/// eigen_projection and F is residual.
+fn compute_low_rank_updated_transform(
+    source_points: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>,
+    sum_of_probability_rows: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 1]>>,
+    weighted_targets: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>,
+    eigenvectors: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>,
+    eigenvalues: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 1]>>,
+    lambda: f32,
+    variance: f32,
+) -> ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>> {
+    let regularization = lambda * variance;
+    let sum_of_probability_rows = sum_of_probability_rows.view().insert_axis(Axis(1));
+    let eigen_projection = eigenvectors * &sum_of_probability_rows;
+    let residual = weighted_targets - source_points * &sum_of_probability_rows;
+    let matrix_a = Array::from_diag(&eigenvalues.mapv(|v| regularization / v))
+        + eigenvectors.t().dot(&eigen_projection);
+    let matrix_b = eigenvectors.t().dot(&residual);
+    (residual - eigen_projection.dot(&solve_matrices(&matrix_a, &matrix_b))) / regularization
+}
+
 fn update_variance(
     target_points: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>,
     transformed_points: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>,
@@ -294,6 +808,18 @@ fn update_variance(
     (new_variance, change_in_variance)
 }
 
+/// Converts an (N, 2) array into a vector of points, one per row.
+fn array_to_points(array: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>) -> Vec<Point> {
+    array
+        .rows()
+        .into_iter()
+        .map(|row| Point {
+            x: row[0],
+            y: row[1],
+        })
+        .collect()
+}
+
 /// A helper function for converting a 2d array into a string representation.
 ///
 /// Used for debugging CoherentPointDriftTransform. When debug is set to true,
@@ -317,3 +843,623 @@ fn array_to_json_string(array: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>) -> S
     array_str.push_str("]");
     array_str
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::annotations::bounding_box::BoundingBox;
+    use crate::object_detection::object_detection_utils::{
+        DedupStrategy, detections_to_centroids, non_maximum_suppression,
+    };
+
+    fn create_testing_centroids() -> Vec<(String, Point)> {
+        vec![
+            ("top_left".to_string(), Point { x: 0_f32, y: 0_f32 }),
+            (
+                "top_right".to_string(),
+                Point {
+                    x: 10_f32,
+                    y: 0_f32,
+                },
+            ),
+            (
+                "bottom_left".to_string(),
+                Point {
+                    x: 0_f32,
+                    y: 10_f32,
+                },
+            ),
+            (
+                "bottom_right".to_string(),
+                Point {
+                    x: 10_f32,
+                    y: 10_f32,
+                },
+            ),
+            ("middle".to_string(), Point { x: 4_f32, y: 6_f32 }),
+        ]
+    }
+
+    fn create_testing_detections() -> Vec<Detection<BoundingBox>> {
+        // Each detection is a slightly shifted copy of a centroid, in a different order.
+        let centers: Vec<(f32, f32)> = vec![
+            (10.5, 10.5),
+            (0.5, 0.5),
+            (4.5, 6.5),
+            (0.5, 10.5),
+            (10.5, 0.5),
+        ];
+        centers
+            .iter()
+            .map(|(x, y)| Detection {
+                annotation: BoundingBox::new(x - 1.0, y - 1.0, x + 1.0, y + 1.0, "".to_string())
+                    .unwrap(),
+                confidence: 0.9_f32,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn from_detections_matches_generate_matching() {
+        let centroids = create_testing_centroids();
+        let detections = create_testing_detections();
+        let (_, matched_names) = CoherentPointDriftTransform::from_detections(
+            &centroids,
+            &detections,
+            2.0,
+            2.0,
+            CpdOptions::default(),
+        );
+
+        let mut manual_transform = CoherentPointDriftTransform::from_point_vectors(
+            centroids.iter().map(|(_, p)| *p).collect(),
+            detections
+                .iter()
+                .map(|det| {
+                    let (x, y) = det.annotation.center();
+                    Point { x, y }
+                })
+                .collect(),
+            2.0,
+            2.0,
+            CpdOptions::default(),
+        );
+        manual_transform.register();
+        let manual_names: HashMap<usize, String> = manual_transform
+            .generate_matching()
+            .into_iter()
+            .map(|(source_ix, target_ix)| (source_ix, centroids[target_ix].0.clone()))
+            .collect();
+
+        assert_eq!(matched_names, manual_names);
+        assert_eq!(matched_names[&0], "bottom_right");
+        assert_eq!(matched_names[&1], "top_left");
+        assert_eq!(matched_names[&2], "middle");
+        assert_eq!(matched_names[&3], "bottom_left");
+        assert_eq!(matched_names[&4], "top_right");
+    }
+
+    fn create_testing_grid(shift_x: f32, shift_y: f32) -> Vec<Point> {
+        let mut points: Vec<Point> = Vec::new();
+        for row in 0..5 {
+            for col in 0..6 {
+                // A small deterministic jitter so the grid is not perfectly regular.
+                let jitter = ((row * 6 + col) % 3) as f32 * 0.3;
+                points.push(Point {
+                    x: col as f32 * 10_f32 + shift_x + jitter,
+                    y: row as f32 * 10_f32 + shift_y - jitter,
+                });
+            }
+        }
+        points
+    }
+
+    #[test]
+    fn low_rank_matches_exact() {
+        let target_points = create_testing_grid(0_f32, 0_f32);
+        let source_points: Vec<Point> = create_testing_grid(1.5_f32, -1_f32)
+            .into_iter()
+            .rev()
+            .collect();
+        let mut exact = CoherentPointDriftTransform::from_point_vectors(
+            target_points.clone(),
+            source_points.clone(),
+            2.0,
+            20.0,
+            CpdOptions::default(),
+        );
+        exact.register();
+        let mut low_rank = CoherentPointDriftTransform::from_point_vectors(
+            target_points,
+            source_points,
+            2.0,
+            20.0,
+            CpdOptions {
+                low_rank: Some(10),
+                ..Default::default()
+            },
+        );
+        low_rank.register();
+        let mut exact_matching = exact.generate_matching();
+        let mut low_rank_matching = low_rank.generate_matching();
+        exact_matching.sort();
+        low_rank_matching.sort();
+        assert_eq!(exact_matching, low_rank_matching);
+        assert_eq!(exact_matching.len(), 30);
+        assert!(exact_matching.iter().all(|(s, t)| s + t == 29));
+        assert!(exact.iterations.abs_diff(low_rank.iterations) <= 2);
+    }
+
+    #[test]
+    fn gaussian_kernel_computed_once() {
+        let calls_before = GAUSSIAN_KERNEL_CALLS.with(|calls| calls.get());
+        let target_points = create_testing_grid(0_f32, 0_f32);
+        let source_points = create_testing_grid(1.5_f32, -1_f32);
+        let mut transform = CoherentPointDriftTransform::from_point_vectors(
+            target_points,
+            source_points,
+            2.0,
+            20.0,
+            CpdOptions::default(),
+        );
+        transform.register();
+        assert!(transform.iterations > 1);
+        let calls_after = GAUSSIAN_KERNEL_CALLS.with(|calls| calls.get());
+        assert_eq!(calls_after - calls_before, 1);
+    }
+
+    #[test]
+    fn cached_gaussian_kernel_matches_recomputing_every_iteration() {
+        let target_points = create_testing_grid(0_f32, 0_f32);
+        let source_points: Vec<Point> = create_testing_grid(1.5_f32, -1_f32)
+            .into_iter()
+            .rev()
+            .collect();
+        let mut cached = CoherentPointDriftTransform::from_point_vectors(
+            target_points.clone(),
+            source_points.clone(),
+            2.0,
+            20.0,
+            CpdOptions::default(),
+        );
+        cached.register();
+
+        // Registers the way it was done before the kernel was cached, recomputing the kernel at
+        // the start of every iteration.
+        let mut recomputed = CoherentPointDriftTransform::from_point_vectors(
+            target_points,
+            source_points,
+            2.0,
+            20.0,
+            CpdOptions::default(),
+        );
+        let mut iteration = 0;
+        while iteration < recomputed.max_iterations
+            && recomputed.change_in_variance > recomputed.tolerance
+        {
+            recomputed.gaussian_kernel = compute_gaussian_kernel(
+                &recomputed.source_points,
+                &recomputed.source_points,
+                recomputed.beta,
+            );
+            recomputed.expectation();
+            recomputed.maximization();
+            iteration += 1;
+        }
+
+        assert!(cached.iterations > 1);
+        assert_eq!(cached.iterations, iteration);
+        assert_eq!(cached.transformed_points, recomputed.transformed_points);
+        assert_eq!(cached.generate_matching(), recomputed.generate_matching());
+    }
+
+    #[test]
+    fn generate_matching_within_rejects_displaced_detection() {
+        let centroids = create_testing_centroids();
+        let mut detections = create_testing_detections();
+        // The detection of the middle centroid is moved far outside of the grid.
+        detections[2] = Detection {
+            annotation: BoundingBox::new(39_f32, 39_f32, 41_f32, 41_f32, "".to_string()).unwrap(),
+            confidence: 0.9_f32,
+        };
+        let (transform, _) = CoherentPointDriftTransform::from_detections(
+            &centroids,
+            &detections,
+            2.0,
+            2.0,
+            CpdOptions::default(),
+        );
+        let all_matches = transform.generate_matching();
+        let close_matches = transform.generate_matching_within(2.0);
+        assert!(all_matches.iter().any(|&(source_ix, _)| source_ix == 2));
+        assert!(close_matches.iter().all(|&(source_ix, _)| source_ix != 2));
+        assert!(close_matches.contains(&(1, 0)));
+    }
+
+    #[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+    enum Landmark {
+        TopLeft,
+        TopRight,
+        BottomLeft,
+        BottomRight,
+    }
+
+    impl Display for Landmark {
+        fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+            write!(f, "{:?}", self)
+        }
+    }
+
+    fn create_testing_landmark(
+        x: f32,
+        y: f32,
+        category: Landmark,
+        confidence: f32,
+    ) -> Detection<BoundingBox<Landmark>> {
+        Detection {
+            annotation: BoundingBox::new(x - 1.0, y - 1.0, x + 1.0, y + 1.0, category).unwrap(),
+            confidence,
+        }
+    }
+
+    #[test]
+    fn enum_categories_through_nms_and_matching() {
+        let clean_chart = vec![
+            create_testing_landmark(0.0, 0.0, Landmark::TopLeft, 1.0),
+            create_testing_landmark(10.0, 0.0, Landmark::TopRight, 1.0),
+            create_testing_landmark(0.0, 10.0, Landmark::BottomLeft, 1.0),
+            create_testing_landmark(10.0, 10.0, Landmark::BottomRight, 1.0),
+        ];
+        let centroids: Vec<(Landmark, Point)> =
+            detections_to_centroids(&clean_chart, DedupStrategy::HighestConfidence)
+                .into_iter()
+                .collect();
+        let detections = non_maximum_suppression(
+            vec![
+                create_testing_landmark(10.5, 10.5, Landmark::BottomRight, 0.9),
+                create_testing_landmark(10.6, 10.4, Landmark::BottomRight, 0.5),
+                create_testing_landmark(0.5, 0.5, Landmark::TopLeft, 0.9),
+                create_testing_landmark(10.5, 0.5, Landmark::TopRight, 0.8),
+                create_testing_landmark(0.5, 10.5, Landmark::BottomLeft, 0.7),
+            ],
+            0.5,
+        );
+        assert_eq!(detections.len(), 4);
+        let (_, matched_names) = CoherentPointDriftTransform::from_detections(
+            &centroids,
+            &detections,
+            2.0,
+            2.0,
+            CpdOptions::default(),
+        );
+        assert_eq!(matched_names.len(), 4);
+        for (source_ix, name) in matched_names {
+            assert_eq!(*detections[source_ix].annotation.category(), name);
+        }
+    }
+
+    #[test]
+    fn prealign_recovers_rotated_cloud() {
+        let source_points = create_testing_grid(0_f32, 0_f32);
+        // Rotates the grid by 40 degrees about its center, then shifts it.
+        let (sin, cos) = 40_f32.to_radians().sin_cos();
+        let target_points: Vec<Point> = source_points
+            .iter()
+            .map(|p| {
+                let (x, y) = (p.x - 25_f32, p.y - 20_f32);
+                Point {
+                    x: cos * x - sin * y + 40_f32,
+                    y: sin * x + cos * y + 10_f32,
+                }
+            })
+            .collect();
+        let count_correct = |prealign: bool| {
+            let mut transform = CoherentPointDriftTransform::from_point_vectors(
+                target_points.clone(),
+                source_points.clone(),
+                2.0,
+                2.0,
+                CpdOptions {
+                    prealign,
+                    ..Default::default()
+                },
+            );
+            transform.register();
+            transform
+                .generate_matching()
+                .iter()
+                .filter(|(source_ix, target_ix)| source_ix == target_ix)
+                .count()
+        };
+        assert!(count_correct(false) < source_points.len());
+        assert_eq!(count_correct(true), source_points.len());
+    }
+
+    #[test]
+    fn generate_matching_with_scores_reads_probabilities() {
+        let mut transform = CoherentPointDriftTransform::from_point_vectors(
+            create_testing_grid(0_f32, 0_f32),
+            create_testing_grid(1.5_f32, -1_f32),
+            2.0,
+            2.0,
+            CpdOptions::default(),
+        );
+        transform.register();
+        let scored = transform.generate_matching_with_scores();
+        assert_eq!(scored.len(), 30);
+        for ((source_ix, target_ix), score) in scored.iter() {
+            assert_eq!(
+                *score,
+                transform.probability_of_match[[*source_ix, *target_ix]]
+            );
+        }
+        assert!(scored.windows(2).all(|pair| pair[0].1 >= pair[1].1));
+        let pairs: Vec<(usize, usize)> = scored.into_iter().map(|(pair, _)| pair).collect();
+        assert_eq!(pairs, transform.generate_matching());
+    }
+
+    #[test]
+    fn apply_reproduces_transformed_points() {
+        let source_points = create_testing_grid(0_f32, 0_f32);
+        let target_points: Vec<Point> = source_points
+            .iter()
+            .map(|p| Point {
+                x: 0.9 * p.y + 3_f32,
+                y: -0.9 * p.x + 50_f32,
+            })
+            .collect();
+        for prealign in [false, true] {
+            let mut transform = CoherentPointDriftTransform::from_point_vectors(
+                target_points.clone(),
+                source_points.clone(),
+                2.0,
+                2.0,
+                CpdOptions {
+                    prealign,
+                    ..Default::default()
+                },
+            );
+            transform.register();
+            let applied = transform.apply(&source_points);
+            for (p, row) in applied.iter().zip(transform.transformed_points.rows()) {
+                assert!(p.approx_eq(
+                    &Point {
+                        x: row[0],
+                        y: row[1]
+                    },
+                    0.001
+                ));
+            }
+        }
+    }
+
+    #[test]
+    fn points_as_vec_after_registration() {
+        let target_points: Vec<Point> = create_testing_centroids()
+            .into_iter()
+            .map(|(_, p)| p)
+            .collect();
+        let source_points: Vec<Point> = target_points
+            .iter()
+            .map(|p| Point {
+                x: p.x + 0.5,
+                y: p.y - 0.5,
+            })
+            .collect();
+        let mut transform = CoherentPointDriftTransform::from_point_vectors(
+            target_points.clone(),
+            source_points.clone(),
+            2.0,
+            2.0,
+            CpdOptions::default(),
+        );
+        transform.register();
+        assert_eq!(transform.target_points_as_vec(), target_points);
+        assert_eq!(transform.source_points_as_vec(), source_points);
+        let transformed = transform.transformed_points_as_vec();
+        assert_eq!(transformed.len(), source_points.len());
+        for (ix, p) in transformed.iter().enumerate() {
+            assert_eq!(p.x, transform.transformed_points[[ix, 0]]);
+            assert_eq!(p.y, transform.transformed_points[[ix, 1]]);
+            assert!(p.approx_eq(&target_points[ix], 0.1));
+        }
+    }
+
+    fn create_testing_misclassified_detections()
+    -> (Vec<Detection<BoundingBox>>, HashMap<usize, String>) {
+        let dets: Vec<Detection<BoundingBox>> = ["top_left", "top_left", "middle", "middle"]
+            .iter()
+            .enumerate()
+            .map(|(ix, category)| Detection {
+                annotation: BoundingBox::new(
+                    ix as f32,
+                    0_f32,
+                    ix as f32 + 1_f32,
+                    1_f32,
+                    category.to_string(),
+                )
+                .unwrap(),
+                confidence: 0.9_f32,
+            })
+            .collect();
+        // Detection 1 was matched with top_right but classified as top_left, and detection 3 was
+        // left unmatched.
+        let matched_names = HashMap::from([
+            (0, "top_left".to_string()),
+            (1, "top_right".to_string()),
+            (2, "middle".to_string()),
+        ]);
+        (dets, matched_names)
+    }
+
+    #[test]
+    fn filter_matched_detections_drop_policy() {
+        let (dets, matched_names) = create_testing_misclassified_detections();
+        let kept = filter_matched_detections(dets, &matched_names, MismatchPolicy::Drop);
+        let categories: Vec<&str> = kept
+            .iter()
+            .map(|det| det.annotation.category().as_str())
+            .collect();
+        assert_eq!(categories, vec!["top_left", "middle"]);
+    }
+
+    #[test]
+    fn filter_matched_detections_relabel_policy() {
+        let (dets, matched_names) = create_testing_misclassified_detections();
+        let kept = filter_matched_detections(dets, &matched_names, MismatchPolicy::Relabel);
+        let categories: Vec<&str> = kept
+            .iter()
+            .map(|det| det.annotation.category().as_str())
+            .collect();
+        assert_eq!(categories, vec!["top_left", "top_right", "middle"]);
+        assert_eq!(kept[1].annotation.left(), 1_f32);
+    }
+
+    #[test]
+    fn filter_matched_detections_keep_policy() {
+        let (dets, matched_names) = create_testing_misclassified_detections();
+        let kept = filter_matched_detections(dets, &matched_names, MismatchPolicy::Keep);
+        let categories: Vec<&str> = kept
+            .iter()
+            .map(|det| det.annotation.category().as_str())
+            .collect();
+        assert_eq!(categories, vec!["top_left", "top_left", "middle"]);
+    }
+
+    /// The original implementation of compute_squared_distance, which broadcasts A and B into
+    /// 3D arrays.
+    fn compute_squared_distance_broadcast(
+        matrix_a: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>,
+        matrix_b: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>,
+    ) -> ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>> {
+        let matrix_a_3d: ArrayBase<OwnedRepr<f32>, Dim<[usize; 3]>> = Array::from_shape_vec(
+            (1, matrix_a.dim().0, matrix_a.dim().1),
+            matrix_a.clone().into_raw_vec_and_offset().0,
+        )
+        .unwrap();
+        let matrix_b_3d: ArrayBase<OwnedRepr<f32>, Dim<[usize; 3]>> = Array::from_shape_vec(
+            (matrix_b.dim().0, 1, matrix_b.dim().1),
+            matrix_b.clone().into_raw_vec_and_offset().0,
+        )
+        .unwrap();
+        (matrix_a_3d - matrix_b_3d).powi(2).sum_axis(Axis(2))
+    }
+
+    #[test]
+    fn squared_distance_matches_broadcast() {
+        let to_array = |points: Vec<Point>| {
+            let flattened: Vec<f32> = points.iter().flat_map(|p| [p.x, p.y]).collect();
+            Array::from_shape_vec((points.len(), 2), flattened).unwrap()
+        };
+        let targets = to_array(create_testing_grid(0_f32, 0_f32));
+        let sources = to_array(create_testing_grid(3.5_f32, -1.25_f32)[..17].to_vec());
+        assert_eq!(
+            compute_squared_distance(&targets, &sources),
+            compute_squared_distance_broadcast(&targets, &sources)
+        );
+        let centroids = to_array(
+            create_testing_centroids()
+                .into_iter()
+                .map(|(_, p)| p)
+                .collect(),
+        );
+        assert_eq!(
+            compute_squared_distance(&centroids, &targets),
+            compute_squared_distance_broadcast(&centroids, &targets)
+        );
+    }
+
+    #[test]
+    fn weight_of_uniform_dist_of_one_is_clamped() {
+        let target_points: Vec<Point> = create_testing_centroids()
+            .into_iter()
+            .map(|(_, p)| p)
+            .collect();
+        let mut transform = CoherentPointDriftTransform::from_point_vectors(
+            target_points.clone(),
+            target_points,
+            2.0,
+            2.0,
+            CpdOptions {
+                weight_of_uniform_dist: 1.0,
+                ..Default::default()
+            },
+        );
+        assert!(transform.weight_of_uniform_dist < 1.0);
+        transform.register();
+        assert!(transform.probability_of_match.iter().all(|p| p.is_finite()));
+        assert!(transform.transformed_points.iter().all(|v| v.is_finite()));
+    }
+
+    #[test]
+    fn mutual_matches_rejects_leftover_pair() {
+        let points = vec![
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 1.0, y: 0.0 },
+            Point { x: 0.0, y: 1.0 },
+        ];
+        let mut transform = CoherentPointDriftTransform::from_point_vectors(
+            points.clone(),
+            points,
+            2.0,
+            2.0,
+            CpdOptions::default(),
+        );
+        // Source 1 prefers target 0, which prefers source 0, so source 1 is only matched to
+        // target 1 by the greedy matcher because it is left over. Target 1 prefers source 2.
+        transform.probability_of_match =
+            Array::from_shape_vec((3, 3), vec![0.9, 0.1, 0.0, 0.8, 0.1, 0.05, 0.0, 0.3, 0.7])
+                .unwrap();
+        assert!(transform.generate_matching().contains(&(1, 1)));
+        assert_eq!(transform.mutual_matches(), vec![(0, 0), (2, 2)]);
+    }
+
+    #[test]
+    fn region_of_interest_excludes_far_off_source() {
+        let mut source_points = vec![Point {
+            x: 500_f32,
+            y: -400_f32,
+        }];
+        source_points.extend(create_testing_grid(1.5_f32, -1_f32));
+        let roi = BoundingBox::new(-10.0, -10.0, 70.0, 60.0, "chart".to_string()).unwrap();
+        let mut transform = CoherentPointDriftTransform::from_point_vectors(
+            create_testing_grid(0_f32, 0_f32),
+            source_points,
+            2.0,
+            2.0,
+            CpdOptions {
+                region_of_interest: Some(roi),
+                ..Default::default()
+            },
+        );
+        transform.register();
+        assert_eq!(transform.excluded_source_indices(), &[0]);
+        assert_eq!(transform.source_points_as_vec().len(), 30);
+        let mut matching = transform.generate_matching();
+        matching.sort();
+        let expected: Vec<(usize, usize)> = (0..30).map(|ix| (ix + 1, ix)).collect();
+        assert_eq!(matching, expected);
+    }
+
+    #[test]
+    fn region_of_interest_excluding_every_source_is_identity() {
+        let roi = BoundingBox::new(1000.0, 1000.0, 1100.0, 1100.0, "chart".to_string()).unwrap();
+        let mut transform = CoherentPointDriftTransform::from_point_vectors(
+            create_testing_grid(0_f32, 0_f32),
+            create_testing_grid(1.5_f32, -1_f32),
+            2.0,
+            2.0,
+            CpdOptions {
+                low_rank: Some(5),
+                region_of_interest: Some(roi),
+                ..Default::default()
+            },
+        );
+        transform.register();
+        assert_eq!(transform.excluded_source_indices().len(), 30);
+        assert!(transform.transformed_points_as_vec().is_empty());
+        assert!(transform.generate_matching().is_empty());
+        let points = vec![Point { x: 3.0, y: 4.0 }];
+        assert_eq!(transform.apply(&points), points);
+    }
+}
@@ -1,2 +1,3 @@
 pub mod coherent_point_drift;
+pub mod point_transform;
 pub mod thin_plate_splines;
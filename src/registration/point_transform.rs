@@ -0,0 +1,206 @@
+use crate::annotations::bounding_box::{BoundingBox, BoundingBoxError, BoundingBoxGeometry};
+use crate::annotations::point::{DistanceMetric, Point};
+use crate::registration::coherent_point_drift::CoherentPointDriftTransform;
+use crate::registration::thin_plate_splines::TpsTransform;
+use imageproc::geometric_transformations::Projection;
+
+/// Defines a trait for anything that maps source coordinates to destination coordinates.
+///
+/// Homographies, thin plate splines and coherent point drift all register a chart onto a
+/// template, so holding a Box<dyn PointTransform> lets the registration method be chosen at
+/// runtime.
+pub trait PointTransform {
+    fn transform_point(&self, p: Point) -> Point;
+
+    /// Transforms a box by moving its top left and bottom right corners.
+    ///
+    /// A strong warp can move the corners past each other, in which case this is an error.
+    fn transform_bounding_box(&self, b: &BoundingBox) -> Result<BoundingBox, BoundingBoxError> {
+        let [top_left, _, bottom_right, _] = b.corners();
+        let top_left = self.transform_point(top_left);
+        let bottom_right = self.transform_point(bottom_right);
+        BoundingBox::new(
+            top_left.x,
+            top_left.y,
+            bottom_right.x,
+            bottom_right.y,
+            b.category().clone(),
+        )
+    }
+}
+
+impl PointTransform for TpsTransform {
+    fn transform_point(&self, p: Point) -> Point {
+        TpsTransform::transform_point(self, p)
+    }
+}
+
+impl PointTransform for Projection {
+    fn transform_point(&self, p: Point) -> Point {
+        let (x, y) = self * &(p.x, p.y);
+        Point { x, y }
+    }
+}
+
+impl PointTransform for CoherentPointDriftTransform {
+    fn transform_point(&self, p: Point) -> Point {
+        self.apply(&[p])[0]
+    }
+}
+
+/// Summarizes the distances between transformed source points and their destinations.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ErrorStats {
+    pub mean: f32,
+    pub median: f32,
+    pub max: f32,
+}
+
+/// Measures how closely a transform maps known correspondences onto each other.
+///
+/// Each residual is the Euclidean distance from the transformed source point to its destination,
+/// so fits from different registration methods can be compared on the same landmarks. Every
+/// statistic is NaN when there are no correspondences.
+///
+/// # Panics
+///
+/// Panics if source and destination have different lengths.
+pub fn reprojection_errors(
+    transform: &impl PointTransform,
+    source: &[Point],
+    destination: &[Point],
+) -> ErrorStats {
+    assert_eq!(
+        source.len(),
+        destination.len(),
+        "Every source point needs a destination point."
+    );
+    let mut residuals: Vec<f32> = source
+        .iter()
+        .zip(destination)
+        .map(|(s, d)| {
+            transform
+                .transform_point(*s)
+                .distance(d, DistanceMetric::Euclidean)
+        })
+        .collect();
+    if residuals.is_empty() {
+        return ErrorStats {
+            mean: f32::NAN,
+            median: f32::NAN,
+            max: f32::NAN,
+        };
+    }
+    residuals.sort_by(f32::total_cmp);
+    let middle = residuals.len() / 2;
+    let median = if residuals.len().is_multiple_of(2) {
+        (residuals[middle - 1] + residuals[middle]) / 2.0
+    } else {
+        residuals[middle]
+    };
+    ErrorStats {
+        mean: residuals.iter().sum::<f32>() / residuals.len() as f32,
+        median,
+        max: residuals[residuals.len() - 1],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn create_testing_tps() -> TpsTransform {
+        let source: Vec<Point> = vec![
+            Point { x: 0_f32, y: 0_f32 },
+            Point { x: 2_f32, y: 0_f32 },
+            Point { x: 0_f32, y: 2_f32 },
+            Point { x: 2_f32, y: 2_f32 },
+        ];
+        let destination: Vec<Point> = vec![
+            Point { x: 0_f32, y: 0_f32 },
+            Point { x: 2_f32, y: 0_f32 },
+            Point {
+                x: 0.5_f32,
+                y: 2_f32,
+            },
+            Point {
+                x: 1.5_f32,
+                y: 2_f32,
+            },
+        ];
+        TpsTransform::new(source, destination)
+    }
+
+    #[test]
+    fn boxed_tps_matches_direct_calls() {
+        let tps = create_testing_tps();
+        let boxed: Box<dyn PointTransform> = Box::new(create_testing_tps());
+        for p in [
+            Point { x: 0.5, y: 0.5 },
+            Point { x: 1.0, y: 1.5 },
+            Point { x: 3.0, y: -1.0 },
+        ] {
+            assert_eq!(boxed.transform_point(p), tps.transform_point(p));
+        }
+        let b = BoundingBox::new(0.5, 0.5, 1.0, 1.5, "part".to_string()).unwrap();
+        let direct = tps
+            .transform_bounding_boxes(vec![b.clone()])
+            .remove(0)
+            .unwrap();
+        assert!(
+            boxed
+                .transform_bounding_box(&b)
+                .unwrap()
+                .approx_eq(&direct, 0.0001)
+        );
+    }
+
+    #[test]
+    fn projection_transform_point() {
+        let boxed: Box<dyn PointTransform> = Box::new(Projection::translate(2.0, -1.0));
+        assert!(
+            boxed
+                .transform_point(Point { x: 1.0, y: 1.0 })
+                .approx_eq(&Point { x: 3.0, y: 0.0 }, 0.0001)
+        );
+    }
+
+    #[test]
+    fn reprojection_errors_of_identity_are_zero() {
+        let points = vec![
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 4.0, y: 1.0 },
+            Point { x: 2.0, y: 7.0 },
+        ];
+        let stats = reprojection_errors(&Projection::translate(0.0, 0.0), &points, &points);
+        assert_eq!(
+            stats,
+            ErrorStats {
+                mean: 0.0,
+                median: 0.0,
+                max: 0.0
+            }
+        );
+    }
+
+    #[test]
+    fn reprojection_errors_of_offset_transform() {
+        let source = vec![
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 4.0, y: 1.0 },
+            Point { x: 2.0, y: 7.0 },
+            Point { x: 5.0, y: 5.0 },
+        ];
+        // Shifted by 1 along x, then off by 0, 1, 2 and 5 along y.
+        let destination = vec![
+            Point { x: 1.0, y: 0.0 },
+            Point { x: 5.0, y: 2.0 },
+            Point { x: 3.0, y: 9.0 },
+            Point { x: 6.0, y: 10.0 },
+        ];
+        let stats = reprojection_errors(&Projection::translate(1.0, 0.0), &source, &destination);
+        assert!((stats.mean - 2.0).abs() < 1e-5);
+        assert!((stats.median - 1.5).abs() < 1e-5);
+        assert!((stats.max - 5.0).abs() < 1e-5);
+    }
+}
@@ -1,10 +1,70 @@
 extern crate openblas_src;
 
+use crate::annotations::bounding_box::{BoundingBox, BoundingBoxError, BoundingBoxGeometry};
+use crate::annotations::detection::Detection;
 use crate::annotations::point::Point;
-use ndarray::{Array, ArrayBase, Axis, Dim, OwnedRepr, concatenate, stack};
+use ndarray::{Array, ArrayBase, Axis, Dim, OwnedRepr, concatenate, s, stack};
 use ndarray_linalg::Solve;
+use std::collections::HashMap;
+use std::fmt;
 use std::iter::zip;
 
+/// Control points closer together than this make the L matrix singular.
+const MIN_CONTROL_POINT_SEPARATION: f32 = 1e-3;
+/// The smoothing of a robust fit, relative to the mean magnitude of the kernel.
+const ROBUST_REGULARIZATION: f32 = 0.1;
+/// The Huber threshold in units of the residuals' robust standard deviation. 1.345 gives 95%
+/// efficiency when the residuals are normally distributed.
+const HUBER_THRESHOLD: f32 = 1.345;
+
+/// A set of custom errors for more informative error handling.
+#[derive(Debug, PartialEq)]
+pub enum TpsError {
+    TooFewControlPoints { found: usize },
+    CoincidentControlPoints { first: Point, second: Point },
+    MismatchedPointCounts { source: usize, destination: usize },
+    CollinearControlPoints,
+}
+
+impl fmt::Display for TpsError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TpsError::TooFewControlPoints { found } => {
+                write!(
+                    f,
+                    "Failed to create thin plate spline transform, at least 3 control points are required but {} were found.",
+                    found
+                )
+            }
+            TpsError::CoincidentControlPoints { first, second } => {
+                write!(
+                    f,
+                    "Failed to create thin plate spline transform, control points ({}, {}) and ({}, {}) coincide.",
+                    first.x, first.y, second.x, second.y
+                )
+            }
+            TpsError::MismatchedPointCounts {
+                source,
+                destination,
+            } => {
+                write!(
+                    f,
+                    "Failed to create thin plate spline transform, {} source points were given but {} destination points.",
+                    source, destination
+                )
+            }
+            TpsError::CollinearControlPoints => {
+                write!(
+                    f,
+                    "Failed to create thin plate spline transform, the source control points all lie on one line."
+                )
+            }
+        }
+    }
+}
+
+impl std::error::Error for TpsError {}
+
 pub struct TpsTransform {
     source: Vec<Point>,
     destination: Vec<Point>,
@@ -21,6 +81,106 @@ impl TpsTransform {
         }
     }
 
+    /// Creates a transform, first checking that the control points give a solvable system.
+    ///
+    /// new assumes the points are valid, and mismatched or degenerate inputs make it panic or
+    /// return garbage. This instead requires matching point counts and at least 3 distinct,
+    /// non-collinear source points.
+    pub fn try_new(source: Vec<Point>, destination: Vec<Point>) -> Result<TpsTransform, TpsError> {
+        validate_control_points(&source, &destination)?;
+        Ok(TpsTransform::new(source, destination))
+    }
+
+    /// Creates a transform mapping source detections onto destination detections of the same
+    /// category.
+    ///
+    /// When a model fires twice for one landmark, only the most confident detection of each
+    /// category is used and the others are silently discarded. Categories missing from
+    /// either side are skipped. Control points are ordered by their first appearance in
+    /// source_dets, so the result doesn't depend on hashing order.
+    pub fn from_detections<T: BoundingBoxGeometry + fmt::Display>(
+        source_dets: &[Detection<T>],
+        destination_dets: &[Detection<T>],
+    ) -> Result<TpsTransform, TpsError> {
+        let source_centers = most_confident_center_per_category(source_dets);
+        let destination_centers: HashMap<T::Category, Point> =
+            most_confident_center_per_category(destination_dets)
+                .into_iter()
+                .collect();
+        let (source, destination): (Vec<Point>, Vec<Point>) = source_centers
+            .into_iter()
+            .filter_map(|(category, source_point)| {
+                destination_centers
+                    .get(&category)
+                    .map(|destination_point| (source_point, *destination_point))
+            })
+            .unzip();
+        validate_control_points(&source, &destination)?;
+        check_control_point_separation(&destination)?;
+        Ok(TpsTransform::new(source, destination))
+    }
+
+    /// Fits a smoothing spline that down-weights correspondences with large residuals.
+    ///
+    /// A plain fit passes through every control point, so one bad match drags the surface
+    /// around it. Here each point is given a Huber weight from its residual under the previous
+    /// fit, and the spline is regularized by the inverse of that weight at each point, for the
+    /// given number of reweighting iterations. Good points keep the base smoothing, so the fit
+    /// no longer interpolates them exactly.
+    pub fn fit_robust(
+        source: Vec<Point>,
+        destination: Vec<Point>,
+        iterations: usize,
+    ) -> TpsTransform {
+        let k_matrix = create_k_matrix(&source, &destination);
+        let base_regularization =
+            ROBUST_REGULARIZATION * k_matrix.mapv(f32::abs).mean().unwrap_or(0_f32);
+        let mut regularization = vec![base_regularization; source.len()];
+        let mut transform = TpsTransform {
+            w_matrix: solve_for_regularized_w_matrix(&source, &destination, &regularization),
+            source,
+            destination,
+        };
+        for _ in 0..iterations {
+            let residuals: Vec<f32> = zip(
+                transform.transform_points(&transform.source),
+                &transform.destination,
+            )
+            .map(|(fitted, target)| euclidean_distance(&fitted, target))
+            .collect();
+            let Some(weights) = huber_weights(&residuals) else {
+                break;
+            };
+            regularization = weights
+                .iter()
+                .map(|weight| base_regularization / weight)
+                .collect();
+            transform.w_matrix = solve_for_regularized_w_matrix(
+                &transform.source,
+                &transform.destination,
+                &regularization,
+            );
+        }
+        transform
+    }
+
+    /// Creates a new transform with one more control point, such as a manually corrected
+    /// landmark.
+    ///
+    /// The system is solved again with the augmented set of points. Errors with
+    /// CoincidentControlPoints when the added source point coincides with an existing one.
+    pub fn with_added_correspondence(
+        &self,
+        source: Point,
+        destination: Point,
+    ) -> Result<TpsTransform, TpsError> {
+        let mut new_source = self.source.clone();
+        let mut new_destination = self.destination.clone();
+        new_source.push(source);
+        new_destination.push(destination);
+        TpsTransform::try_new(new_source, new_destination)
+    }
+
     pub fn transform_point(&self, p: Point) -> Point {
         let mut kernel_vec = vec![];
         for dest_point in self.destination.iter() {
@@ -35,6 +195,200 @@ impl TpsTransform {
         let new_y = out.index_axis(Axis(1), 1).to_vec()[0];
         Point { x: new_x, y: new_y }
     }
+
+    /// Transforms many points with a single matrix multiplication.
+    ///
+    /// Gives the same result as calling transform_point on each point, but builds the kernel
+    /// for every point at once, which is much faster for large numbers of points.
+    pub fn transform_points(&self, points: &[Point]) -> Vec<Point> {
+        let num_columns = self.destination.len() + 3;
+        let mut kernel_values: Vec<f32> = Vec::with_capacity(points.len() * num_columns);
+        for p in points.iter() {
+            for dest_point in self.destination.iter() {
+                kernel_values.push(kernel(dest_point, p));
+            }
+            kernel_values.push(1.0);
+            kernel_values.push(p.x);
+            kernel_values.push(p.y);
+        }
+        let kernel_matrix =
+            Array::from_shape_vec((points.len(), num_columns), kernel_values).unwrap();
+        kernel_matrix
+            .dot(&self.w_matrix)
+            .rows()
+            .into_iter()
+            .map(|row| Point {
+                x: row[0],
+                y: row[1],
+            })
+            .collect()
+    }
+
+    /// Transforms boxes by moving their top left and bottom right corners.
+    ///
+    /// A strong warp can move the corners past each other, in which case the box for that entry
+    /// is an error.
+    pub fn transform_bounding_boxes(
+        &self,
+        boxes: Vec<BoundingBox>,
+    ) -> Vec<Result<BoundingBox, BoundingBoxError>> {
+        let corners: Vec<Point> = boxes
+            .iter()
+            .flat_map(|b| {
+                let [top_left, _, bottom_right, _] = b.corners();
+                [top_left, bottom_right]
+            })
+            .collect();
+        let transformed_corners = self.transform_points(&corners);
+        boxes
+            .into_iter()
+            .zip(transformed_corners.chunks(2))
+            .map(|(b, corners)| {
+                BoundingBox::new(
+                    corners[0].x,
+                    corners[0].y,
+                    corners[1].x,
+                    corners[1].y,
+                    b.category().clone(),
+                )
+            })
+            .collect()
+    }
+
+    /// Transforms the box of every detection, keeping each detection's confidence.
+    ///
+    /// Like transform_bounding_boxes, a detection whose box is turned inside out by the warp is
+    /// an error, so callers can tell which detections could not be placed on the chart.
+    pub fn transform_detections(
+        &self,
+        dets: Vec<Detection<BoundingBox>>,
+    ) -> Vec<Result<Detection<BoundingBox>, BoundingBoxError>> {
+        let confidences: Vec<f32> = dets.iter().map(|det| det.confidence).collect();
+        let boxes: Vec<BoundingBox> = dets.into_iter().map(|det| det.annotation).collect();
+        zip(self.transform_bounding_boxes(boxes), confidences)
+            .map(|(transformed, confidence)| {
+                transformed.map(|annotation| Detection {
+                    annotation,
+                    confidence,
+                })
+            })
+            .collect()
+    }
+
+    /// Computes the bending energy of the transform, trace(W^T K W).
+    ///
+    /// W is the non-affine part of the solution (every row but the last three) and K is the
+    /// kernel between every pair of source control points. An affine mapping has zero bending
+    /// energy, while a large value means the warp is heavily contorted, which usually points to
+    /// bad landmark correspondences.
+    pub fn bending_energy(&self) -> f32 {
+        let num_points = self.destination.len();
+        let non_affine = self.w_matrix.slice(s![..num_points, ..]);
+        let k_matrix = create_k_matrix(&self.source, &self.source);
+        non_affine.t().dot(&k_matrix).dot(&non_affine).diag().sum()
+    }
+}
+
+/// Finds the center of the most confident detection of each category.
+///
+/// Categories are returned in order of their first appearance.
+fn most_confident_center_per_category<T: BoundingBoxGeometry + fmt::Display>(
+    dets: &[Detection<T>],
+) -> Vec<(T::Category, Point)> {
+    let mut order: Vec<T::Category> = Vec::new();
+    let mut most_confident: HashMap<T::Category, &Detection<T>> = HashMap::new();
+    for det in dets {
+        let category = det.annotation.category();
+        match most_confident.get(category) {
+            None => {
+                order.push(category.clone());
+                most_confident.insert(category.clone(), det);
+            }
+            Some(kept) => {
+                if det.confidence > kept.confidence {
+                    most_confident.insert(category.clone(), det);
+                }
+            }
+        }
+    }
+    order
+        .into_iter()
+        .map(|category| {
+            let (x, y) = most_confident[&category].annotation.center();
+            (category, Point { x, y })
+        })
+        .collect()
+}
+
+/// Computes Huber weights for residuals, scaled by their median absolute deviation.
+///
+/// Returns None when the residuals are all essentially zero, as there is nothing to reweight.
+fn huber_weights(residuals: &[f32]) -> Option<Vec<f32>> {
+    let mut sorted = residuals.to_vec();
+    sorted.sort_by(f32::total_cmp);
+    let median = sorted.get(sorted.len() / 2)?;
+    let threshold = HUBER_THRESHOLD * 1.4826 * median;
+    if threshold <= f32::EPSILON {
+        return None;
+    }
+    Some(
+        residuals
+            .iter()
+            .map(|residual| {
+                if *residual <= threshold {
+                    1_f32
+                } else {
+                    threshold / residual
+                }
+            })
+            .collect(),
+    )
+}
+
+fn validate_control_points(source: &[Point], destination: &[Point]) -> Result<(), TpsError> {
+    if source.len() != destination.len() {
+        return Err(TpsError::MismatchedPointCounts {
+            source: source.len(),
+            destination: destination.len(),
+        });
+    }
+    if source.len() < 3 {
+        return Err(TpsError::TooFewControlPoints {
+            found: source.len(),
+        });
+    }
+    check_control_point_separation(source)?;
+    check_control_points_span_plane(source)
+}
+
+/// Checks that some point lies off the line through the first two, which are known to be apart.
+fn check_control_points_span_plane(points: &[Point]) -> Result<(), TpsError> {
+    let (origin, along) = (points[0], points[1]);
+    let (dx, dy) = (along.x - origin.x, along.y - origin.y);
+    let length = (dx * dx + dy * dy).sqrt();
+    let spans_plane = points[2..].iter().any(|p| {
+        let cross = dx * (p.y - origin.y) - dy * (p.x - origin.x);
+        (cross / length).abs() >= MIN_CONTROL_POINT_SEPARATION
+    });
+    if spans_plane {
+        Ok(())
+    } else {
+        Err(TpsError::CollinearControlPoints)
+    }
+}
+
+fn check_control_point_separation(points: &[Point]) -> Result<(), TpsError> {
+    for (ix, first) in points.iter().enumerate() {
+        for second in points[ix + 1..].iter() {
+            if euclidean_distance(first, second) < MIN_CONTROL_POINT_SEPARATION {
+                return Err(TpsError::CoincidentControlPoints {
+                    first: *first,
+                    second: *second,
+                });
+            }
+        }
+    }
+    Ok(())
 }
 
 fn create_l_matrix(
@@ -121,6 +475,29 @@ fn solve_for_w_matrix(
     destination: &[Point],
 ) -> ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>> {
     let l_matrix = create_l_matrix(source, destination);
+    solve_l_system(&l_matrix, destination)
+}
+
+/// Solves for the W matrix with regularization[i] added to the i-th diagonal entry of K.
+///
+/// A regularized spline no longer passes through its control points; the larger a point's
+/// regularization, the less the surface bends to reach it.
+fn solve_for_regularized_w_matrix(
+    source: &[Point],
+    destination: &[Point],
+    regularization: &[f32],
+) -> ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>> {
+    let mut l_matrix = create_l_matrix(source, destination);
+    for (ix, value) in regularization.iter().enumerate() {
+        l_matrix[[ix, ix]] += value;
+    }
+    solve_l_system(&l_matrix, destination)
+}
+
+fn solve_l_system(
+    l_matrix: &ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>>,
+    destination: &[Point],
+) -> ArrayBase<OwnedRepr<f32>, Dim<[usize; 2]>> {
     let b_matrix = create_b_matrix(destination);
     let col_0 = b_matrix.column(0).to_owned();
     let col_1 = b_matrix.column(1).to_owned();
@@ -418,7 +795,327 @@ mod tests {
         let dst_points = test_transf.destination.clone();
         for (src_point, dst_point) in zip(src_points, dst_points) {
             let transformed_point = test_transf.transform_point(src_point);
-            assert!((transformed_point.x - dst_point.x) < 0.00001)
+            assert!(transformed_point.approx_eq(&dst_point, 0.0001))
+        }
+    }
+
+    #[test]
+    fn test_with_added_correspondence_coincident_source() {
+        let test_transf = create_testing_transform();
+        let existing_source = test_transf.source[0];
+        let result =
+            test_transf.with_added_correspondence(existing_source, Point { x: 1.2, y: 0.7 });
+        assert!(matches!(
+            result,
+            Err(TpsError::CoincidentControlPoints { .. })
+        ));
+    }
+
+    #[test]
+    fn test_with_added_correspondence() {
+        let test_transf = create_testing_transform();
+        let added_source = Point { x: 1.0, y: 1.0 };
+        let added_destination = Point { x: 1.2, y: 0.7 };
+        assert!(
+            !test_transf
+                .transform_point(added_source)
+                .approx_eq(&added_destination, 0.01)
+        );
+        let augmented = test_transf
+            .with_added_correspondence(added_source, added_destination)
+            .unwrap();
+        assert_eq!(augmented.source.len(), 5);
+        assert!(
+            augmented
+                .transform_point(added_source)
+                .approx_eq(&added_destination, 0.0001)
+        );
+        for (src_point, dst_point) in zip(&test_transf.source, &test_transf.destination) {
+            assert!(
+                augmented
+                    .transform_point(*src_point)
+                    .approx_eq(dst_point, 0.0001)
+            );
+        }
+    }
+
+    #[test]
+    fn test_bending_energy() {
+        let source: Vec<Point> = vec![
+            Point { x: 0_f32, y: 0_f32 },
+            Point { x: 4_f32, y: 0_f32 },
+            Point { x: 0_f32, y: 4_f32 },
+            Point { x: 4_f32, y: 4_f32 },
+            Point { x: 2_f32, y: 2_f32 },
+        ];
+        let identity = TpsTransform::new(source.clone(), source.clone());
+        let mut warped_destination = source.clone();
+        warped_destination[4] = Point { x: 3_f32, y: 1_f32 };
+        let warped = TpsTransform::new(source, warped_destination);
+        assert!(identity.bending_energy().abs() < 0.0001);
+        assert!(warped.bending_energy() > 0.01);
+    }
+
+    #[test]
+    fn test_bending_energy_hand_computed() {
+        // Corners of the unit square, with weights pushing opposite corners the same way along x.
+        // Sides have U(1) = 0 and diagonals U(sqrt(2)) = ln(2), so W^T K W = 4 ln(2).
+        let source: Vec<Point> = vec![
+            Point { x: 0_f32, y: 0_f32 },
+            Point { x: 1_f32, y: 0_f32 },
+            Point { x: 0_f32, y: 1_f32 },
+            Point { x: 1_f32, y: 1_f32 },
+        ];
+        let destination: Vec<Point> = source
+            .iter()
+            .map(|p| Point {
+                x: p.x + 0.5,
+                y: p.y * 2.0,
+            })
+            .collect();
+        let w_matrix = Array::from_shape_vec(
+            (7, 2),
+            vec![
+                1_f32, 0_f32, -1_f32, 0_f32, -1_f32, 0_f32, 1_f32, 0_f32, 0.5_f32, 0_f32, 1_f32,
+                0_f32, 0_f32, 2_f32,
+            ],
+        )
+        .unwrap();
+        let transform = TpsTransform {
+            source,
+            destination,
+            w_matrix,
+        };
+        assert!((transform.bending_energy() - 4_f32 * 2_f32.ln()).abs() < 0.0001);
+    }
+
+    #[test]
+    fn test_transform_points_matches_transform_point() {
+        let test_transf = create_testing_transform();
+        let points: Vec<Point> = vec![
+            Point { x: 0.5, y: 0.5 },
+            Point { x: 1.0, y: 1.5 },
+            Point { x: 2.0, y: 2.0 },
+            Point { x: 3.0, y: -1.0 },
+        ];
+        let batch = test_transf.transform_points(&points);
+        assert_eq!(batch.len(), points.len());
+        for (p, batch_point) in zip(points, batch) {
+            assert!(
+                test_transf
+                    .transform_point(p)
+                    .approx_eq(&batch_point, 0.0001)
+            );
+        }
+    }
+
+    #[test]
+    fn test_transform_bounding_boxes() {
+        let test_transf = create_testing_transform();
+        let boxes = vec![
+            BoundingBox::new(0.0, 0.0, 2.0, 2.0, "whole".to_string()).unwrap(),
+            BoundingBox::new(0.5, 0.5, 1.0, 1.5, "part".to_string()).unwrap(),
+        ];
+        let transformed = test_transf.transform_bounding_boxes(boxes);
+        let whole = transformed[0].as_ref().unwrap();
+        let true_whole = BoundingBox::new(0.0, 0.0, 1.5, 2.0, "whole".to_string()).unwrap();
+        assert!(whole.approx_eq(&true_whole, 0.0001));
+        let part = transformed[1].as_ref().unwrap();
+        let top_left = test_transf.transform_point(Point { x: 0.5, y: 0.5 });
+        assert!(
+            Point {
+                x: part.left(),
+                y: part.top()
+            }
+            .approx_eq(&top_left, 0.0001)
+        );
+        assert_eq!(part.category(), "part");
+    }
+
+    #[test]
+    fn test_transform_detections_preserves_confidence() {
+        let test_transf = create_testing_transform();
+        let dets = vec![
+            Detection {
+                annotation: BoundingBox::new(0.0, 0.0, 2.0, 2.0, "whole".to_string()).unwrap(),
+                confidence: 0.9,
+            },
+            Detection {
+                annotation: BoundingBox::new(0.5, 0.5, 1.0, 1.5, "part".to_string()).unwrap(),
+                confidence: 0.4,
+            },
+        ];
+        let transformed: Vec<Detection<BoundingBox>> = test_transf
+            .transform_detections(dets.clone())
+            .into_iter()
+            .map(|det| det.unwrap())
+            .collect();
+        assert_eq!(transformed.len(), 2);
+        assert_eq!(transformed[0].confidence, 0.9);
+        assert_eq!(transformed[1].confidence, 0.4);
+        let true_whole = BoundingBox::new(0.0, 0.0, 1.5, 2.0, "whole".to_string()).unwrap();
+        assert!(transformed[0].annotation.approx_eq(&true_whole, 0.0001));
+
+        // A mirror image moves every box's left edge past its right edge.
+        let mirror = TpsTransform::new(
+            vec![
+                Point { x: 0.0, y: 0.0 },
+                Point { x: 1.0, y: 0.0 },
+                Point { x: 0.0, y: 1.0 },
+            ],
+            vec![
+                Point { x: 0.0, y: 0.0 },
+                Point { x: -1.0, y: 0.0 },
+                Point { x: 0.0, y: 1.0 },
+            ],
+        );
+        let mirrored = mirror.transform_detections(dets);
+        assert_eq!(mirrored.len(), 2);
+        assert!(mirrored.iter().all(|det| det.is_err()));
+    }
+
+    fn create_testing_landmark(
+        x: f32,
+        y: f32,
+        category: &str,
+        confidence: f32,
+    ) -> Detection<BoundingBox> {
+        Detection {
+            annotation: BoundingBox::new(
+                x - 1_f32,
+                y - 1_f32,
+                x + 1_f32,
+                y + 1_f32,
+                category.to_string(),
+            )
+            .unwrap(),
+            confidence,
         }
     }
+
+    #[test]
+    fn from_detections_keeps_most_confident_duplicate() {
+        let mut source_dets = vec![
+            create_testing_landmark(0.0, 0.0, "top_left", 0.9),
+            create_testing_landmark(10.0, 0.0, "top_right", 0.9),
+            create_testing_landmark(0.0, 10.0, "bottom_left", 0.9),
+            create_testing_landmark(10.0, 10.0, "bottom_right", 0.9),
+            // A second, less confident firing on top of the real top_left landmark.
+            create_testing_landmark(0.0, 0.0, "bottom_right", 0.3),
+        ];
+        let destination_dets = vec![
+            create_testing_landmark(1.0, 1.0, "top_left", 0.9),
+            create_testing_landmark(11.0, 1.0, "top_right", 0.9),
+            create_testing_landmark(1.0, 11.0, "bottom_left", 0.9),
+            create_testing_landmark(11.0, 11.0, "bottom_right", 0.9),
+        ];
+        let transform = TpsTransform::from_detections(&source_dets, &destination_dets).unwrap();
+        source_dets.reverse();
+        let reversed = TpsTransform::from_detections(&source_dets, &destination_dets).unwrap();
+        for tps in [transform, reversed] {
+            assert_eq!(tps.source.len(), 4);
+            let moved = tps.transform_point(Point { x: 10.0, y: 10.0 });
+            assert!(moved.approx_eq(&Point { x: 11.0, y: 11.0 }, 0.001));
+            assert!(
+                tps.transform_point(Point { x: 5.0, y: 5.0 })
+                    .approx_eq(&Point { x: 6.0, y: 6.0 }, 0.001)
+            );
+        }
+    }
+
+    #[test]
+    fn from_detections_rejects_coincident_control_points() {
+        let source_dets = vec![
+            create_testing_landmark(0.0, 0.0, "a", 0.9),
+            create_testing_landmark(10.0, 0.0, "b", 0.9),
+            create_testing_landmark(10.0, 0.0, "c", 0.9),
+        ];
+        let destination_dets = vec![
+            create_testing_landmark(0.0, 0.0, "a", 0.9),
+            create_testing_landmark(10.0, 0.0, "b", 0.9),
+            create_testing_landmark(0.0, 10.0, "c", 0.9),
+        ];
+        assert_eq!(
+            TpsTransform::from_detections(&source_dets, &destination_dets).err(),
+            Some(TpsError::CoincidentControlPoints {
+                first: Point { x: 10.0, y: 0.0 },
+                second: Point { x: 10.0, y: 0.0 },
+            })
+        );
+        assert_eq!(
+            TpsTransform::from_detections(&source_dets[..2], &destination_dets).err(),
+            Some(TpsError::TooFewControlPoints { found: 2 })
+        );
+    }
+
+    #[test]
+    fn fit_robust_resists_outlier() {
+        let true_mapping = |p: &Point| Point {
+            x: 1.05 * p.x + 0.02 * p.y + 2_f32,
+            y: -0.03 * p.x + 0.95 * p.y - 3_f32,
+        };
+        let source: Vec<Point> = (0..5)
+            .flat_map(|row| {
+                (0..5).map(move |col| Point {
+                    x: col as f32 * 10_f32,
+                    y: row as f32 * 10_f32,
+                })
+            })
+            .collect();
+        let mut destination: Vec<Point> = source.iter().map(true_mapping).collect();
+        destination[12].x += 30_f32;
+        destination[12].y -= 20_f32;
+        // Points between the control points, where the true mapping is known.
+        let held_out: Vec<Point> = source
+            .iter()
+            .filter(|p| p.x < 40_f32 && p.y < 40_f32)
+            .map(|p| Point {
+                x: p.x + 5_f32,
+                y: p.y + 5_f32,
+            })
+            .collect();
+        let mean_residual = |transform: &TpsTransform| {
+            zip(transform.transform_points(&held_out), held_out.iter())
+                .map(|(fitted, p)| euclidean_distance(&fitted, &true_mapping(p)))
+                .sum::<f32>()
+                / held_out.len() as f32
+        };
+        let plain = TpsTransform::new(source.clone(), destination.clone());
+        let robust = TpsTransform::fit_robust(source, destination, 10);
+        assert!(mean_residual(&robust) < mean_residual(&plain));
+    }
+
+    #[test]
+    fn try_new_rejects_mismatched_point_counts() {
+        let source = vec![
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 1.0, y: 0.0 },
+            Point { x: 0.0, y: 1.0 },
+        ];
+        assert_eq!(
+            TpsTransform::try_new(source.clone(), source[..2].to_vec()).err(),
+            Some(TpsError::MismatchedPointCounts {
+                source: 3,
+                destination: 2
+            })
+        );
+        assert!(TpsTransform::try_new(source.clone(), source).is_ok());
+    }
+
+    #[test]
+    fn try_new_rejects_degenerate_control_points() {
+        let points = vec![
+            Point { x: 0.0, y: 0.0 },
+            Point { x: 1.0, y: 1.0 },
+            Point { x: 3.0, y: 3.0 },
+        ];
+        assert_eq!(
+            TpsTransform::try_new(points[..2].to_vec(), points[..2].to_vec()).err(),
+            Some(TpsError::TooFewControlPoints { found: 2 })
+        );
+        assert_eq!(
+            TpsTransform::try_new(points.clone(), points).err(),
+            Some(TpsError::CollinearControlPoints)
+        );
+    }
 }